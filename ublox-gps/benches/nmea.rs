@@ -0,0 +1,213 @@
+//! Throughput of the NMEA parser, against the regex-based parser it replaced.
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use ublox_gps_tec::parse_nmea;
+
+/// One epoch of NMEA output from a ZED-F9P receiver
+const EPOCH: &str = "$GNRMC,221515.00,A,4238.96342,N,07118.97943,W,0.046,,031024,,,D,V*0D
+$GNVTG,,T,,M,0.046,N,0.086,K,D*34
+$GNGGA,221515.00,4238.96342,N,07118.97943,W,2,12,1.04,36.7,M,-33.0,M,,0131*41
+$GNGSA,A,3,03,27,46,44,31,26,04,16,,,,,1.83,1.04,1.51,1*0A
+$GNGSA,A,3,68,78,79,67,,,,,,,,,1.83,1.04,1.51,2*06
+$GNGSA,A,3,21,29,19,,,,,,,,,,1.83,1.04,1.51,3*09
+$GNGSA,A,3,25,23,41,32,,,,,,,,,1.83,1.04,1.51,4*0C
+$GNGSA,A,3,,,,,,,,,,,,,1.83,1.04,1.51,5*0F
+$GPGSV,3,1,10,03,26,248,42,04,48,306,17,16,68,221,41,26,72,052,18,1*61
+$GPGSV,3,2,10,27,18,171,36,29,16,041,11,31,62,067,22,32,00,145,12,1*66
+$GPGSV,3,3,10,44,23,237,44,46,15,247,33,1*65
+$GPGSV,1,1,03,03,26,248,27,04,48,306,16,27,18,171,36,6*58
+$GPGSV,1,1,02,09,16,316,,28,30,090,,0*6D
+$GLGSV,2,1,05,67,20,174,38,68,63,216,41,78,65,004,21,79,41,266,37,1*79
+$GLGSV,2,2,05,86,05,011,20,1*44
+$GLGSV,2,1,05,67,20,174,34,68,63,216,33,78,65,004,13,79,41,266,22,3*77
+$GLGSV,2,2,05,86,05,011,24,3*42
+$GLGSV,1,1,04,69,45,316,,77,15,054,,87,17,056,,88,09,111,,0*70
+$GAGSV,1,1,02,19,74,181,28,29,30,147,35,2*71
+$GAGSV,1,1,03,19,74,181,35,21,78,057,09,29,30,147,20,7*4A
+$GAGSV,1,1,03,04,39,310,,06,14,315,,27,24,050,,0*49
+$GBGSV,2,1,07,23,56,275,33,25,62,050,18,32,43,291,27,33,07,172,35,1*73
+$GBGSV,2,2,07,37,07,259,29,41,43,210,44,43,09,146,27,1*4E
+$GBGSV,1,1,01,41,43,210,08,B*3D
+$GBGSV,1,1,04,20,03,330,,24,11,071,,34,22,102,,44,13,049,,0*79
+$GQGSV,1,1,00,0*64
+$GNGLL,4238.96342,N,07118.97943,W,221515.00,A,D*68
+$GNZDA,221515.00,03,10,2024,00,00*7E
+";
+
+/// The regex-based NMEA parser, as it was before the field parser
+mod regex_path {
+    use std::{collections::HashMap, sync::LazyLock};
+
+    use chrono::{DateTime, TimeZone, Utc};
+    use regex::{Captures, Regex};
+
+    pub struct Info {
+        pub time: DateTime<Utc>,
+        pub loc: (f64, f64, f64),
+        pub quality: u8,
+        pub headings: (f32, f32, f32),
+        pub dops: (f32, f32, f32),
+        pub sat_views: HashMap<([u8; 2], u8), (i8, u16)>,
+    }
+
+    static SENTENCE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(
+            r"\$(?P<payload>(?P<id>[A-Z]{2})(?P<kind>[A-Z]{3})\,(?P<data>.*?))\*(?P<cksum>[A-F0-9]{2})",
+        )
+        .unwrap()
+    });
+    static LAT: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"(?<deg>\d{2})(?<min>\d{2}\.\d{5})").unwrap());
+    static LON: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"(?<deg>\d{3})(?<min>\d{2}\.\d{5})").unwrap());
+    static ZDA: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(
+            r"(?<hour>\d{2})(?<minute>\d{2})(?<second>\d{2}\.\d{2}),(?<day>\d{2}),(?<month>\d{2}),(?<year>\d{4})",
+        )
+        .unwrap()
+    });
+    static GGA: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(
+            r"\d{6}\.\d{2},(?<lat>[\d\.]*),(?<lat_dir>[NS]),(?<lon>[\d\.]*),(?<lon_dir>[EW]),(?<quality>[0-9A-F]),(?<sat_views>\d*),[\d\.]*,(?<alt>[\-\d\.]*),M,(?<sep>[\-\d\.]*),M,",
+        )
+        .unwrap()
+    });
+    static VTG: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(
+            r"(?<true_heading>[\-\d\.]*),T,(?<mag_heading>[\-\d\.]*),M,[\d\.]*,N,(?<ground_speed>[\d\.]*),K,",
+        )
+        .unwrap()
+    });
+    static GSA: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(
+            r"[AM],\d,\d*,\d*,\d*,\d*,\d*,\d*,\d*,\d*,\d*,\d*,\d*,\d*,(?<pdop>[\d\.]*),(?<hdop>[\d\.]*),(?<vdop>[\d\.]*),",
+        )
+        .unwrap()
+    });
+    static GSV: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"\d*,\d*,\d*,(?<payload>[\d\,]*)[0-9A-F]").unwrap());
+    static GSV_SAT: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(?<svid>\d*),(?<elevation>\d*),(?<azimuth>\d*),(?<snr>\d*),").unwrap()
+    });
+
+    fn coordinate(re: &Regex, inp: &str, neg: bool) -> Option<f64> {
+        let caps = re.captures(inp)?;
+        let val = caps["deg"].parse::<f64>().ok()? + caps["min"].parse::<f64>().ok()? / 60.0;
+        Some(if neg { -val } else { val })
+    }
+
+    fn latest<'a>(
+        msgs: &'a HashMap<[u8; 3], Vec<([u8; 2], String)>>,
+        kind: &[u8; 3],
+        re: &Regex,
+    ) -> Option<Captures<'a>> {
+        msgs.get(kind)?
+            .iter()
+            .rev()
+            .find_map(|(_, x)| re.captures(x))
+    }
+
+    pub fn parse(data: &str) -> Option<Info> {
+        let mut msgs: HashMap<[u8; 3], Vec<([u8; 2], String)>> = HashMap::new();
+        for caps in SENTENCE.captures_iter(data) {
+            let calc = caps["payload"].bytes().fold(0, |acc, x| acc ^ x);
+            if u8::from_str_radix(&caps["cksum"], 16).ok() == Some(calc) {
+                let id = caps["id"].as_bytes().try_into().unwrap();
+                let kind = caps["kind"].as_bytes().try_into().unwrap();
+                msgs.entry(kind)
+                    .or_default()
+                    .push((id, caps["data"].to_string()));
+            }
+        }
+        let zda = latest(&msgs, b"ZDA", &ZDA)?;
+        let time = format!(
+            "{}-{}-{}T{}:{}:{}0",
+            &zda["year"], &zda["month"], &zda["day"], &zda["hour"], &zda["minute"], &zda["second"]
+        );
+        #[allow(deprecated)]
+        let time = TimeZone::datetime_from_str(&Utc, &time, "%Y-%m-%dT%H:%M:%S%.f").ok()?;
+        let gga = latest(&msgs, b"GGA", &GGA)?;
+        let alt = gga["alt"].parse::<f64>().ok()?;
+        let loc = (
+            coordinate(&LAT, &gga["lat"], &gga["lat_dir"] == "S")?,
+            coordinate(&LON, &gga["lon"], &gga["lon_dir"] == "W")?,
+            alt + gga["sep"].parse::<f64>().unwrap_or_default(),
+        );
+        let quality = u8::from_str_radix(&gga["quality"], 16).ok()?;
+        let headings = msgs
+            .get(b"VTG")
+            .and_then(|x| VTG.captures(&x.last()?.1))
+            .map(|x| {
+                (
+                    x["true_heading"].parse().unwrap_or_default(),
+                    x["mag_heading"].parse().unwrap_or_default(),
+                    x["ground_speed"].parse().unwrap_or_default(),
+                )
+            })
+            .unwrap_or_default();
+        let dops = msgs
+            .get(b"GSA")
+            .and_then(|x| GSA.captures(&x.last()?.1))
+            .map(|x| {
+                (
+                    x["pdop"].parse().unwrap_or_default(),
+                    x["hdop"].parse().unwrap_or_default(),
+                    x["vdop"].parse().unwrap_or_default(),
+                )
+            })
+            .unwrap_or_default();
+        let mut sat_views = HashMap::new();
+        for (id, data) in msgs.get(b"GSV").into_iter().flatten() {
+            let Some(caps) = GSV.captures(data) else {
+                continue;
+            };
+            for sat in GSV_SAT.captures_iter(&caps["payload"]) {
+                sat_views.insert(
+                    (*id, sat["svid"].parse().unwrap_or_default()),
+                    (
+                        sat["elevation"].parse().unwrap_or_default(),
+                        sat["azimuth"].parse().unwrap_or_default(),
+                    ),
+                );
+            }
+        }
+        Some(Info {
+            time,
+            loc,
+            quality,
+            headings,
+            dops,
+            sat_views,
+        })
+    }
+}
+
+fn bench_nmea(c: &mut Criterion) {
+    // Both parsers must agree on the epoch
+    let (info, _) = parse_nmea(EPOCH.as_bytes().to_vec()).unwrap();
+    let old = regex_path::parse(EPOCH).unwrap();
+    assert_eq!(info.time, old.time);
+    assert_eq!(
+        (info.loc.lat, info.loc.lon, info.loc.height),
+        (old.loc.0, old.loc.1, old.loc.2)
+    );
+    assert_eq!(
+        (info.true_heading, info.mag_heading, info.ground_speed),
+        old.headings
+    );
+    assert_eq!(info.quality, old.quality);
+    assert_eq!((info.pdop, info.hdop, info.vdop), old.dops);
+    assert_eq!(info.sat_views.len(), old.sat_views.len());
+
+    let mut group = c.benchmark_group("nmea_epoch");
+    group.throughput(Throughput::Bytes(EPOCH.len() as u64));
+    group.bench_function("field_parser", |b| {
+        b.iter(|| parse_nmea(black_box(EPOCH.as_bytes().to_vec())))
+    });
+    group.bench_function("regex", |b| b.iter(|| regex_path::parse(black_box(EPOCH))));
+    group.finish();
+}
+
+criterion_group!(benches, bench_nmea);
+criterion_main!(benches);
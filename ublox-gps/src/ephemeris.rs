@@ -0,0 +1,227 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    geo::Ecef,
+    iono::SPEED_OF_LIGHT,
+    nmea::GnssSatellite,
+    time::{GnssTime, TimeScale},
+};
+
+/// Earth gravitational constant used by GPS and QZSS (m³/s²)
+const GM_GPS: f64 = 3.986_005e14;
+/// Earth gravitational constant used by Galileo and BeiDou (m³/s²)
+const GM_GAL: f64 = 3.986_004_418e14;
+/// Earth rotation rate used by GPS, QZSS and Galileo (rad/s)
+const OMEGA_E_GPS: f64 = 7.292_115_146_7e-5;
+/// Earth rotation rate used by BeiDou (rad/s)
+const OMEGA_E_BDS: f64 = 7.292_115e-5;
+/// Half a week (s)
+const HALF_WEEK: f64 = 302_400.0;
+/// Maximum time from the reference epoch for which an ephemeris is used (s)
+const MAX_AGE: f64 = 4.0 * 3600.0;
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+/// Position, velocity and clock of a satellite
+pub struct SatState {
+    /// Position (ECEF, m)
+    pub position: Ecef,
+    /// Velocity (ECEF, m/s)
+    pub velocity: Ecef,
+    /// Satellite clock bias, including the relativistic correction (s)
+    pub clock_bias: f64,
+    /// Satellite clock drift (s/s)
+    pub clock_drift: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// Keplerian broadcast ephemeris of a GPS, Galileo, BeiDou or QZSS satellite.
+///
+/// Angles are in radians, and times of week are in the time scale of the
+/// satellite's constellation.
+pub struct Ephemeris {
+    /// Satellite
+    pub satellite: GnssSatellite,
+    /// Issue of data
+    pub iode: u16,
+    /// Broadcast week number (possibly truncated)
+    pub week: u16,
+    /// Satellite health (0 = healthy)
+    pub health: u8,
+    /// Reference time of ephemeris (s of week)
+    pub toe: f64,
+    /// Reference time of clock (s of week)
+    pub toc: f64,
+    /// Square root of the semi-major axis (m^0.5)
+    pub sqrt_a: f64,
+    /// Eccentricity
+    pub e: f64,
+    /// Inclination at the reference time
+    pub i0: f64,
+    /// Longitude of the ascending node at the start of the week
+    pub omega0: f64,
+    /// Argument of perigee
+    pub omega: f64,
+    /// Mean anomaly at the reference time
+    pub m0: f64,
+    /// Mean motion difference (rad/s)
+    pub delta_n: f64,
+    /// Rate of inclination (rad/s)
+    pub idot: f64,
+    /// Rate of right ascension (rad/s)
+    pub omega_dot: f64,
+    /// Cosine harmonic correction to the argument of latitude
+    pub cuc: f64,
+    /// Sine harmonic correction to the argument of latitude
+    pub cus: f64,
+    /// Cosine harmonic correction to the orbit radius (m)
+    pub crc: f64,
+    /// Sine harmonic correction to the orbit radius (m)
+    pub crs: f64,
+    /// Cosine harmonic correction to the inclination
+    pub cic: f64,
+    /// Sine harmonic correction to the inclination
+    pub cis: f64,
+    /// Clock bias (s)
+    pub af0: f64,
+    /// Clock drift (s/s)
+    pub af1: f64,
+    /// Clock drift rate (s/s²)
+    pub af2: f64,
+    /// Group delay of the primary signal (s): TGD for GPS and QZSS,
+    /// BGD(E1, E5b) for Galileo and TGD1 for BeiDou
+    pub tgd: f64,
+}
+
+impl Ephemeris {
+    /// Get the time scale of the ephemeris
+    pub fn time_scale(&self) -> Option<TimeScale> {
+        match self.satellite {
+            GnssSatellite::Gps(_) | GnssSatellite::Qzss(_) => Some(TimeScale::Gpst),
+            GnssSatellite::Galileo(_) => Some(TimeScale::Gst),
+            GnssSatellite::Beidou(_) => Some(TimeScale::Bdt),
+            _ => None,
+        }
+    }
+
+    /// Check if the satellite is healthy
+    pub fn is_healthy(&self) -> bool {
+        self.health == 0
+    }
+
+    /// Get the Earth rotation rate used by the constellation (rad/s)
+    pub(crate) fn earth_rotation(&self) -> f64 {
+        match self.satellite {
+            GnssSatellite::Beidou(_) => OMEGA_E_BDS,
+            _ => OMEGA_E_GPS,
+        }
+    }
+
+    fn gm(&self) -> f64 {
+        match self.satellite {
+            GnssSatellite::Gps(_) | GnssSatellite::Qzss(_) => GM_GPS,
+            _ => GM_GAL,
+        }
+    }
+
+    /// Check if the satellite is a BeiDou geostationary satellite
+    fn is_beidou_geo(&self) -> bool {
+        matches!(self.satellite, GnssSatellite::Beidou(prn) if prn <= 5 || prn >= 59)
+    }
+
+    /// Get the time (s) from a reference time of week, accounting for
+    /// the week crossover
+    fn since(&self, time: GnssTime, reference: f64) -> Option<f64> {
+        let (_, tow) = time.week_tow(self.time_scale()?)?;
+        let dt = (tow - reference + HALF_WEEK).rem_euclid(2.0 * HALF_WEEK) - HALF_WEEK;
+        Some(dt)
+    }
+
+    /// Get the eccentric anomaly at `tk` seconds from the reference time
+    fn eccentric_anomaly(&self, tk: f64) -> f64 {
+        let a = self.sqrt_a * self.sqrt_a;
+        let n = (self.gm() / (a * a * a)).sqrt() + self.delta_n;
+        let m = self.m0 + n * tk;
+        let mut e = m;
+        for _ in 0..30 {
+            let de = (e - self.e * e.sin() - m) / (1.0 - self.e * e.cos());
+            e -= de;
+            if de.abs() < 1e-14 {
+                break;
+            }
+        }
+        e
+    }
+
+    /// Get the satellite position at `tk` seconds from the reference time
+    fn position_at(&self, tk: f64) -> Ecef {
+        let a = self.sqrt_a * self.sqrt_a;
+        let ea = self.eccentric_anomaly(tk);
+        let nu = ((1.0 - self.e * self.e).sqrt() * ea.sin()).atan2(ea.cos() - self.e);
+        let phi = nu + self.omega;
+        let (s2, c2) = (2.0 * phi).sin_cos();
+        let u = phi + self.cus * s2 + self.cuc * c2;
+        let r = a * (1.0 - self.e * ea.cos()) + self.crs * s2 + self.crc * c2;
+        let i = self.i0 + self.cis * s2 + self.cic * c2 + self.idot * tk;
+        let (x, y) = (r * u.cos(), r * u.sin());
+        let we = self.earth_rotation();
+        if self.is_beidou_geo() {
+            let o = self.omega0 + self.omega_dot * tk - we * self.toe;
+            let xg = x * o.cos() - y * i.cos() * o.sin();
+            let yg = x * o.sin() + y * i.cos() * o.cos();
+            let zg = y * i.sin();
+            let (s5, c5) = (-5f64).to_radians().sin_cos();
+            let (so, co) = (we * tk).sin_cos();
+            Ecef::new(
+                xg * co + yg * so * c5 + zg * so * s5,
+                -xg * so + yg * co * c5 + zg * co * s5,
+                -yg * s5 + zg * c5,
+            )
+        } else {
+            let o = self.omega0 + (self.omega_dot - we) * tk - we * self.toe;
+            Ecef::new(
+                x * o.cos() - y * i.cos() * o.sin(),
+                x * o.sin() + y * i.cos() * o.cos(),
+                y * i.sin(),
+            )
+        }
+    }
+
+    /// Get the satellite clock bias (s) at `time`, including the
+    /// relativistic correction but not the group delay
+    pub fn clock_bias(&self, time: GnssTime) -> Option<f64> {
+        let dt = self.since(time, self.toc)?;
+        let ea = self.eccentric_anomaly(self.since(time, self.toe)?);
+        let rel = -2.0 * self.gm().sqrt() / (SPEED_OF_LIGHT * SPEED_OF_LIGHT)
+            * self.e
+            * self.sqrt_a
+            * ea.sin();
+        Some(self.af0 + self.af1 * dt + self.af2 * dt * dt + rel)
+    }
+
+    /// Get the satellite position, velocity and clock at `time`.
+    ///
+    /// # Returns
+    /// - `None` if the ephemeris is more than four hours from its
+    ///   reference time, or the constellation is not supported
+    pub fn state(&self, time: GnssTime) -> Option<SatState> {
+        let tk = self.since(time, self.toe)?;
+        if tk.abs() > MAX_AGE {
+            return None;
+        }
+        const H: f64 = 1e-3;
+        let position = self.position_at(tk);
+        let (p0, p1) = (self.position_at(tk - H), self.position_at(tk + H));
+        let velocity = Ecef::new(
+            (p1.x - p0.x) / (2.0 * H),
+            (p1.y - p0.y) / (2.0 * H),
+            (p1.z - p0.z) / (2.0 * H),
+        );
+        let dt = self.since(time, self.toc)?;
+        Some(SatState {
+            position,
+            velocity,
+            clock_bias: self.clock_bias(time)?,
+            clock_drift: self.af1 + 2.0 * self.af2 * dt,
+        })
+    }
+}
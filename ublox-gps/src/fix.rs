@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+/// Quality of a position fix.
+///
+/// Unifies the GGA fix quality, the positioning mode indicators of RMC,
+/// GLL, GNS and VTG, and the fix status of UBX-NAV-PVT. Converts to and
+/// from the raw GGA value with [`From`].
+pub enum FixQuality {
+    /// No fix, or an invalid fix (GGA `0`, mode `N`)
+    #[default]
+    Invalid,
+    /// Autonomous GNSS fix (GGA `1`, mode `A`)
+    Autonomous,
+    /// Differential GNSS fix, e.g. with SBAS corrections (GGA `2`, mode `D`)
+    Differential,
+    /// Precise (PPS) fix (GGA `3`, mode `P`)
+    Precise,
+    /// RTK fix with fixed integer ambiguities (GGA `4`, mode `R`)
+    RtkFixed,
+    /// RTK fix with float ambiguities (GGA `5`, mode `F`)
+    RtkFloat,
+    /// Estimated (dead reckoning) fix (GGA `6`, mode `E`)
+    DeadReckoning,
+    /// Manual input mode (GGA `7`, mode `M`)
+    Manual,
+    /// Simulator mode (GGA `8`, mode `S`)
+    Simulator,
+    /// Any other GGA fix quality
+    Unknown(u8),
+}
+
+impl FixQuality {
+    /// Get the fix quality of a positioning mode indicator
+    ///
+    /// # Returns
+    /// - The fix quality, or [`FixQuality::Invalid`] for `N` and unknown
+    ///   mode indicators
+    pub fn from_mode(mode: char) -> Self {
+        match mode {
+            'A' => Self::Autonomous,
+            'D' => Self::Differential,
+            'P' => Self::Precise,
+            'R' => Self::RtkFixed,
+            'F' => Self::RtkFloat,
+            'E' => Self::DeadReckoning,
+            'M' => Self::Manual,
+            'S' => Self::Simulator,
+            _ => Self::Invalid,
+        }
+    }
+
+    /// Get the positioning mode indicator of the fix quality, the inverse
+    /// of [`FixQuality::from_mode`]
+    pub fn mode(&self) -> char {
+        match self {
+            Self::Autonomous => 'A',
+            Self::Differential => 'D',
+            Self::Precise => 'P',
+            Self::RtkFixed => 'R',
+            Self::RtkFloat => 'F',
+            Self::DeadReckoning => 'E',
+            Self::Manual => 'M',
+            Self::Simulator => 'S',
+            Self::Invalid | Self::Unknown(_) => 'N',
+        }
+    }
+
+    /// Check if the fix is valid
+    pub fn is_valid(&self) -> bool {
+        *self != Self::Invalid
+    }
+}
+
+impl From<u8> for FixQuality {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::Invalid,
+            1 => Self::Autonomous,
+            2 => Self::Differential,
+            3 => Self::Precise,
+            4 => Self::RtkFixed,
+            5 => Self::RtkFloat,
+            6 => Self::DeadReckoning,
+            7 => Self::Manual,
+            8 => Self::Simulator,
+            x => Self::Unknown(x),
+        }
+    }
+}
+
+impl From<FixQuality> for u8 {
+    fn from(value: FixQuality) -> Self {
+        match value {
+            FixQuality::Invalid => 0,
+            FixQuality::Autonomous => 1,
+            FixQuality::Differential => 2,
+            FixQuality::Precise => 3,
+            FixQuality::RtkFixed => 4,
+            FixQuality::RtkFloat => 5,
+            FixQuality::DeadReckoning => 6,
+            FixQuality::Manual => 7,
+            FixQuality::Simulator => 8,
+            FixQuality::Unknown(x) => x,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// Type of a position fix, from the GSA fix mode or the UBX-NAV-PVT fix
+/// type
+pub enum FixMode {
+    /// No fix
+    NoFix,
+    /// Dead reckoning only
+    DeadReckoning,
+    /// 2D fix
+    Fix2D,
+    /// 3D fix
+    Fix3D,
+    /// GNSS and dead reckoning combined
+    GnssDeadReckoning,
+    /// Time only fix
+    TimeOnly,
+}
+
+impl FixMode {
+    /// Get the fix type of a GSA fix mode (`1` - `3`)
+    pub fn from_gsa(mode: u8) -> Option<Self> {
+        match mode {
+            1 => Some(Self::NoFix),
+            2 => Some(Self::Fix2D),
+            3 => Some(Self::Fix3D),
+            _ => None,
+        }
+    }
+
+    /// Get the GSA fix mode of the fix type
+    ///
+    /// Dead reckoning is reported as a 2D fix, and GNSS with dead
+    /// reckoning as a 3D fix.
+    pub fn gsa(&self) -> u8 {
+        match self {
+            Self::NoFix | Self::TimeOnly => 1,
+            Self::DeadReckoning | Self::Fix2D => 2,
+            Self::Fix3D | Self::GnssDeadReckoning => 3,
+        }
+    }
+
+    /// Get the fix type of a UBX-NAV-PVT `fixType` (`0` - `5`)
+    pub fn from_nav_pvt(fix_type: u8) -> Option<Self> {
+        match fix_type {
+            0 => Some(Self::NoFix),
+            1 => Some(Self::DeadReckoning),
+            2 => Some(Self::Fix2D),
+            3 => Some(Self::Fix3D),
+            4 => Some(Self::GnssDeadReckoning),
+            5 => Some(Self::TimeOnly),
+            _ => None,
+        }
+    }
+
+    /// Check if the fix has a position
+    pub fn has_position(&self) -> bool {
+        !matches!(self, Self::NoFix | Self::TimeOnly)
+    }
+}
+
+mod test {
+    #[test]
+    fn test_fix() {
+        use super::*;
+        for raw in 0..=0xF {
+            assert_eq!(u8::from(FixQuality::from(raw)), raw);
+        }
+        assert_eq!(FixQuality::from(5), FixQuality::RtkFloat);
+        assert_eq!(FixQuality::from(9), FixQuality::Unknown(9));
+        for mode in "ADPRFEMS".chars() {
+            assert_eq!(FixQuality::from_mode(mode).mode(), mode);
+        }
+        assert_eq!(FixQuality::from_mode('N'), FixQuality::Invalid);
+        assert!(!FixQuality::from_mode('X').is_valid());
+        assert_eq!(FixMode::from_gsa(2), Some(FixMode::Fix2D));
+        assert_eq!(FixMode::from_gsa(0), None);
+        for fix_type in 0..=5 {
+            let mode = FixMode::from_nav_pvt(fix_type).unwrap();
+            assert_eq!(
+                mode.has_position(),
+                FixMode::from_gsa(mode.gsa()) != Some(FixMode::NoFix)
+            );
+        }
+        assert_eq!(FixMode::from_nav_pvt(6), None);
+    }
+}
@@ -0,0 +1,404 @@
+use std::{
+    f64::consts::PI,
+    ops::{Add, Sub},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::iono::EARTH_RADIUS;
+
+/// WGS-84 semi-major axis (m)
+pub(crate) const WGS84_A: f64 = 6_378_137.0;
+/// WGS-84 flattening
+pub(crate) const WGS84_F: f64 = 1.0 / 298.257_223_563;
+/// WGS-84 first eccentricity squared
+pub(crate) const WGS84_E2: f64 = WGS84_F * (2.0 - WGS84_F);
+
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+/// WGS-84 geodetic coordinates
+pub struct Geodetic {
+    /// Latitude (degrees, north positive)
+    pub lat: f64,
+    /// Longitude (degrees, east positive)
+    pub lon: f64,
+    /// Height above the WGS-84 ellipsoid (m)
+    pub height: f64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+/// Earth-centered, Earth-fixed cartesian coordinates (m)
+pub struct Ecef {
+    /// X coordinate, towards the prime meridian (m)
+    pub x: f64,
+    /// Y coordinate, towards 90° east (m)
+    pub y: f64,
+    /// Z coordinate, towards the north pole (m)
+    pub z: f64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+/// Local east, north, up coordinates (m)
+pub struct Enu {
+    /// East component (m)
+    pub east: f64,
+    /// North component (m)
+    pub north: f64,
+    /// Up component (m)
+    pub up: f64,
+}
+
+impl Geodetic {
+    /// Create new geodetic coordinates
+    ///
+    /// # Arguments
+    /// - `lat`: Latitude (degrees)
+    /// - `lon`: Longitude (degrees)
+    /// - `height`: Height above the WGS-84 ellipsoid (m)
+    pub fn new(lat: f64, lon: f64, height: f64) -> Self {
+        Self { lat, lon, height }
+    }
+
+    /// Create geodetic coordinates from a height above mean sea level
+    ///
+    /// # Returns
+    /// - `None` if the geoid does not cover the location
+    pub fn from_msl(lat: f64, lon: f64, msl: f64, geoid: &Egm96) -> Option<Self> {
+        Some(Self::new(lat, lon, msl + geoid.undulation(lat, lon)?))
+    }
+
+    /// Get the height above mean sea level (m)
+    ///
+    /// # Returns
+    /// - `None` if the geoid does not cover the location
+    pub fn msl(&self, geoid: &Egm96) -> Option<f64> {
+        Some(self.height - geoid.undulation(self.lat, self.lon)?)
+    }
+
+    /// Convert to ECEF coordinates
+    pub fn to_ecef(&self) -> Ecef {
+        let (lat, lon) = (self.lat.to_radians(), self.lon.to_radians());
+        let n = WGS84_A / (1.0 - WGS84_E2 * lat.sin().powi(2)).sqrt();
+        Ecef {
+            x: (n + self.height) * lat.cos() * lon.cos(),
+            y: (n + self.height) * lat.cos() * lon.sin(),
+            z: (n * (1.0 - WGS84_E2) + self.height) * lat.sin(),
+        }
+    }
+
+    /// Get the position of `target` in the local ENU frame of this point
+    pub fn enu(&self, target: &Ecef) -> Enu {
+        (*target - self.to_ecef()).rotate_enu(self)
+    }
+
+    /// Get the azimuth (degrees), elevation (degrees) and range (m) of
+    /// `target` as seen from this point
+    pub fn look_angles(&self, target: &Ecef) -> (f64, f64, f64) {
+        let enu = self.enu(target);
+        (enu.azimuth(), enu.elevation(), enu.range())
+    }
+
+    /// Get the great-circle distance (m) to another point on a spherical
+    /// Earth, ignoring the heights
+    pub fn distance(&self, other: &Geodetic) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let dlat = lat2 - lat1;
+        let dlon = (other.lon - self.lon).to_radians();
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS * a.sqrt().min(1.0).asin()
+    }
+
+    /// Get the initial great-circle bearing (degrees east of north) to
+    /// another point
+    pub fn bearing(&self, other: &Geodetic) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let dlon = (other.lon - self.lon).to_radians();
+        let y = dlon.sin() * lat2.cos();
+        let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos();
+        y.atan2(x).to_degrees().rem_euclid(360.0)
+    }
+
+    /// Get the point reached by travelling `distance` (m) along a great
+    /// circle with initial `bearing` (degrees east of north), at the same
+    /// height
+    pub fn destination(&self, bearing: f64, distance: f64) -> Geodetic {
+        let (lat, lon) = (self.lat.to_radians(), self.lon.to_radians());
+        let (brg, d) = (bearing.to_radians(), distance / EARTH_RADIUS);
+        let lat2 = (lat.sin() * d.cos() + lat.cos() * d.sin() * brg.cos()).asin();
+        let lon2 = lon + (brg.sin() * d.sin() * lat.cos()).atan2(d.cos() - lat.sin() * lat2.sin());
+        Geodetic {
+            lat: lat2.to_degrees(),
+            lon: ((lon2 + PI).rem_euclid(2.0 * PI) - PI).to_degrees(),
+            height: self.height,
+        }
+    }
+}
+
+impl Ecef {
+    /// Create new ECEF coordinates (m)
+    pub fn new(x: f64, y: f64, z: f64) -> Self {
+        Self { x, y, z }
+    }
+
+    /// Get the distance from the center of the Earth (m)
+    pub fn norm(&self) -> f64 {
+        (self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
+    }
+
+    /// Get the distance to another point (m)
+    pub fn distance(&self, other: &Ecef) -> f64 {
+        (*other - *self).norm()
+    }
+
+    /// Convert to geodetic coordinates
+    pub fn to_geodetic(&self) -> Geodetic {
+        let p = self.x.hypot(self.y);
+        let lon = self.y.atan2(self.x);
+        let mut lat = self.z.atan2(p * (1.0 - WGS84_E2));
+        for _ in 0..10 {
+            let n = WGS84_A / (1.0 - WGS84_E2 * lat.sin().powi(2)).sqrt();
+            let next = (self.z + WGS84_E2 * n * lat.sin()).atan2(p);
+            let done = (next - lat).abs() < 1e-14;
+            lat = next;
+            if done {
+                break;
+            }
+        }
+        let height = p * lat.cos() + self.z * lat.sin()
+            - WGS84_A * (1.0 - WGS84_E2 * lat.sin().powi(2)).sqrt();
+        Geodetic {
+            lat: lat.to_degrees(),
+            lon: lon.to_degrees(),
+            height,
+        }
+    }
+
+    /// Rotate an ECEF vector into the local ENU frame at `origin`
+    fn rotate_enu(&self, origin: &Geodetic) -> Enu {
+        let (lat, lon) = (origin.lat.to_radians(), origin.lon.to_radians());
+        let (slat, clat, slon, clon) = (lat.sin(), lat.cos(), lon.sin(), lon.cos());
+        Enu {
+            east: -slon * self.x + clon * self.y,
+            north: -slat * clon * self.x - slat * slon * self.y + clat * self.z,
+            up: clat * clon * self.x + clat * slon * self.y + slat * self.z,
+        }
+    }
+}
+
+impl Add for Ecef {
+    type Output = Ecef;
+
+    fn add(self, other: Ecef) -> Ecef {
+        Ecef::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl Sub for Ecef {
+    type Output = Ecef;
+
+    fn sub(self, other: Ecef) -> Ecef {
+        Ecef::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl From<Geodetic> for Ecef {
+    fn from(value: Geodetic) -> Self {
+        value.to_ecef()
+    }
+}
+
+impl From<Ecef> for Geodetic {
+    fn from(value: Ecef) -> Self {
+        value.to_geodetic()
+    }
+}
+
+impl Enu {
+    /// Create new ENU coordinates (m)
+    pub fn new(east: f64, north: f64, up: f64) -> Self {
+        Self { east, north, up }
+    }
+
+    /// Get the azimuth (degrees east of north, 0 to 360)
+    pub fn azimuth(&self) -> f64 {
+        self.east.atan2(self.north).to_degrees().rem_euclid(360.0)
+    }
+
+    /// Get the elevation above the local horizon (degrees)
+    pub fn elevation(&self) -> f64 {
+        self.up.atan2(self.east.hypot(self.north)).to_degrees()
+    }
+
+    /// Get the range (m)
+    pub fn range(&self) -> f64 {
+        (self.east * self.east + self.north * self.north + self.up * self.up).sqrt()
+    }
+
+    /// Convert to ECEF coordinates, for a local frame at `origin`
+    pub fn to_ecef(&self, origin: &Geodetic) -> Ecef {
+        let (lat, lon) = (origin.lat.to_radians(), origin.lon.to_radians());
+        let (slat, clat, slon, clon) = (lat.sin(), lat.cos(), lon.sin(), lon.cos());
+        origin.to_ecef()
+            + Ecef {
+                x: -slon * self.east - slat * clon * self.north + clat * clon * self.up,
+                y: clon * self.east - slat * slon * self.north + clat * slon * self.up,
+                z: clat * self.north + slat * self.up,
+            }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Geoid undulation grid, such as the EGM96 15' grid
+/// (`WW15MGH.GRD`), interpolated bilinearly.
+pub struct Egm96 {
+    lat: (f64, f64),
+    lon: (f64, f64),
+    step: (f64, f64),
+    shape: (usize, usize),
+    values: Vec<f64>,
+}
+
+impl Egm96 {
+    /// Create a geoid grid from a `WW15MGH.GRD`-style header and values.
+    ///
+    /// # Arguments
+    /// - `header`: South, north, west and east bounds and the latitude and
+    ///   longitude spacing (degrees)
+    /// - `values`: Geoid undulations (m), row by row from north to south,
+    ///   each row from west to east
+    pub fn new(header: [f64; 6], values: Vec<f64>) -> Result<Self, &'static str> {
+        let [south, north, west, east, dlat, dlon] = header;
+        if !(dlat > 0.0 && dlon > 0.0 && north > south && east > west) {
+            return Err("Invalid geoid grid header");
+        }
+        let nlat = ((north - south) / dlat).round() as usize + 1;
+        let nlon = ((east - west) / dlon).round() as usize + 1;
+        if nlat < 2 || nlon < 2 {
+            return Err("Geoid grid must have at least 2 x 2 points");
+        }
+        if values.len() != nlat * nlon {
+            return Err("Invalid number of geoid grid values");
+        }
+        Ok(Self {
+            lat: (south, north),
+            lon: (west, east),
+            step: (dlat, dlon),
+            shape: (nlat, nlon),
+            values,
+        })
+    }
+
+    /// Load a geoid grid in the `WW15MGH.GRD` format
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, std::io::Error> {
+        let values = std::fs::read_to_string(path)?
+            .split_whitespace()
+            .map(|x| x.parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        if values.len() < 6 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Missing geoid grid header",
+            ));
+        }
+        let header = values[..6].try_into().expect("Header has six values");
+        Self::new(header, values[6..].to_vec())
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    /// Get the geoid undulation (height of the geoid above the WGS-84
+    /// ellipsoid, m) at a location.
+    ///
+    /// # Returns
+    /// - `None` if the grid does not cover the location
+    pub fn undulation(&self, lat: f64, lon: f64) -> Option<f64> {
+        let (nlat, nlon) = self.shape;
+        let (dlat, dlon) = self.step;
+        if !(self.lat.0..=self.lat.1).contains(&lat) {
+            return None;
+        }
+        let span = self.lon.1 - self.lon.0;
+        let mut lon = lon;
+        if span >= 360.0 - 1e-9 {
+            lon = (lon - self.lon.0).rem_euclid(360.0) + self.lon.0;
+        }
+        if !(self.lon.0..=self.lon.1).contains(&lon) {
+            return None;
+        }
+        let y = (self.lat.1 - lat) / dlat;
+        let x = (lon - self.lon.0) / dlon;
+        let (i, j) = (
+            (y.floor() as usize).min(nlat - 2),
+            (x.floor() as usize).min(nlon - 2),
+        );
+        let (fy, fx) = (y - i as f64, x - j as f64);
+        let v = |i: usize, j: usize| self.values[i * nlon + j];
+        Some(
+            (1.0 - fy) * ((1.0 - fx) * v(i, j) + fx * v(i, j + 1))
+                + fy * ((1.0 - fx) * v(i + 1, j) + fx * v(i + 1, j + 1)),
+        )
+    }
+}
+
+mod test {
+    #[test]
+    fn test_geo() {
+        use super::*;
+        // ECEF round trip
+        for (lat, lon, h) in [
+            (0.0, 0.0, 0.0),
+            (40.0, -105.0, 1650.0),
+            (-33.9, 151.2, -20.0),
+            (89.999, 10.0, 5000.0),
+            (-90.0, 0.0, 100.0),
+        ] {
+            let geo = Geodetic::new(lat, lon, h);
+            let back = geo.to_ecef().to_geodetic();
+            assert!((back.lat - lat).abs() < 1e-9);
+            assert!((back.height - h).abs() < 1e-6);
+            if lat.abs() < 90.0 {
+                assert!((back.lon - lon).abs() < 1e-9);
+            }
+        }
+        let ecef = Geodetic::new(0.0, 90.0, 0.0).to_ecef();
+        assert!(ecef.x.abs() < 1e-6 && (ecef.y - WGS84_A).abs() < 1e-6);
+
+        // Look angles
+        let rx = Geodetic::new(45.0, 10.0, 100.0);
+        let up = Enu::new(0.0, 0.0, 20_000e3).to_ecef(&rx);
+        let (_, el, range) = rx.look_angles(&up);
+        assert!((el - 90.0).abs() < 1e-9);
+        assert!((range - 20_000e3).abs() < 1e-6);
+        let enu = Enu::new(1000.0, 1000.0, 0.0);
+        let target = enu.to_ecef(&rx);
+        let back = rx.enu(&target);
+        assert!((back.east - 1000.0).abs() < 1e-6 && (back.up).abs() < 1e-6);
+        assert!((back.azimuth() - 45.0).abs() < 1e-9);
+        assert!(back.elevation().abs() < 1e-9);
+
+        // Distance and bearing
+        let a = Geodetic::new(0.0, 0.0, 0.0);
+        let b = Geodetic::new(0.0, 90.0, 0.0);
+        assert!((a.distance(&b) - EARTH_RADIUS * PI / 2.0).abs() < 1e-6);
+        assert!((a.bearing(&b) - 90.0).abs() < 1e-9);
+        assert!((b.bearing(&a) - 270.0).abs() < 1e-9);
+        let c = a.destination(0.0, EARTH_RADIUS * PI / 4.0);
+        assert!((c.lat - 45.0).abs() < 1e-9 && c.lon.abs() < 1e-9);
+
+        // Geoid grid, 10 m north of the equator rising to 30 m
+        let geoid = Egm96::new(
+            [-90.0, 90.0, 0.0, 360.0, 90.0, 180.0],
+            vec![30.0, 30.0, 30.0, 10.0, 10.0, 10.0, -10.0, -10.0, -10.0],
+        )
+        .unwrap();
+        assert_eq!(geoid.undulation(45.0, 0.0), Some(20.0));
+        assert_eq!(geoid.undulation(45.0, -90.0), Some(20.0));
+        assert_eq!(geoid.undulation(-90.0, 180.0), Some(-10.0));
+        let loc = Geodetic::from_msl(45.0, 10.0, 100.0, &geoid).unwrap();
+        assert_eq!(loc.height, 120.0);
+        assert_eq!(loc.msl(&geoid), Some(100.0));
+        assert!(Egm96::new([-90.0, 90.0, 0.0, 360.0, 90.0, 180.0], vec![0.0]).is_err());
+        // A single point is not a grid
+        assert!(Egm96::new([0.0, 1.0, 0.0, 1.0, 5.0, 5.0], vec![0.0]).is_err());
+    }
+}
@@ -0,0 +1,833 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    time::Duration,
+};
+
+use chrono::{DateTime, Datelike, TimeDelta, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    geo::Geodetic,
+    iono::{mapping_function, pierce_point, IonoModel},
+    roti::window_start,
+    TecInfo,
+};
+
+/// Value of a missing grid point in an IONEX file
+const MISSING: i64 = 9999;
+/// Number of grid values per line in an IONEX file
+const VALUES_PER_LINE: usize = 16;
+
+#[derive(Error, Debug)]
+/// Errors encountered while reading IONEX files
+pub enum IonexError {
+    /// Failed to read the file
+    #[error("Failed to read IONEX file: {0}")]
+    Io(#[from] std::io::Error),
+    /// A required header record is missing
+    #[error("Missing IONEX header record: {0}")]
+    MissingHeader(&'static str),
+    /// The file contents are malformed
+    #[error("Malformed IONEX file at line {line}: {msg}")]
+    Format {
+        /// Line number (1-indexed)
+        line: usize,
+        /// Description of the error
+        msg: String,
+    },
+    /// The file uses features that are not supported
+    #[error("Unsupported IONEX file: {0}")]
+    Unsupported(String),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+/// A regular latitude/longitude grid on a thin ionospheric shell
+pub struct IonexGrid {
+    /// First latitude, last latitude and latitude step (degrees)
+    pub lat: (f64, f64, f64),
+    /// First longitude, last longitude and longitude step (degrees)
+    pub lon: (f64, f64, f64),
+    /// Height of the ionospheric shell (km)
+    pub height: f64,
+}
+
+impl Default for IonexGrid {
+    /// Global 2.5° x 5° grid at 450 km, as used by the IGS analysis centers
+    fn default() -> Self {
+        Self {
+            lat: (87.5, -87.5, -2.5),
+            lon: (-180.0, 180.0, 5.0),
+            height: 450.0,
+        }
+    }
+}
+
+impl IonexGrid {
+    /// Get the number of latitude rows
+    pub fn num_lat(&self) -> usize {
+        ((self.lat.1 - self.lat.0) / self.lat.2).round() as usize + 1
+    }
+
+    /// Get the number of longitude columns
+    pub fn num_lon(&self) -> usize {
+        ((self.lon.1 - self.lon.0) / self.lon.2).round() as usize + 1
+    }
+
+    /// Check if the grid wraps around in longitude
+    fn is_global(&self) -> bool {
+        ((self.lon.1 - self.lon.0).abs() - 360.0).abs() < 1e-6
+    }
+
+    /// Copy the first longitude column of a global grid to the last one,
+    /// which covers the same meridian
+    fn wrap_columns(&self, values: &mut [Option<f64>]) {
+        if self.is_global() {
+            for row in values.chunks_mut(self.num_lon()) {
+                row[row.len() - 1] = row[0];
+            }
+        }
+    }
+
+    /// Get the index of the grid point nearest to a location
+    fn nearest(&self, lat: f64, lon: f64) -> Option<usize> {
+        let i = ((lat - self.lat.0) / self.lat.2).round();
+        let mut j = ((lon - self.lon.0) / self.lon.2).round();
+        if self.is_global() {
+            j = j.rem_euclid((self.num_lon() - 1) as f64);
+        }
+        if i < 0.0 || i >= self.num_lat() as f64 || j < 0.0 || j >= self.num_lon() as f64 {
+            return None;
+        }
+        Some(i as usize * self.num_lon() + j as usize)
+    }
+
+    /// Bilinear interpolation of grid values at a location
+    fn interpolate(&self, values: &[Option<f64>], lat: f64, lon: f64) -> Option<f64> {
+        const EPS: f64 = 1e-9;
+        let (nlat, nlon) = (self.num_lat(), self.num_lon());
+        let fi = (lat - self.lat.0) / self.lat.2;
+        let mut fj = (lon - self.lon.0) / self.lon.2;
+        if self.is_global() {
+            fj = fj.rem_euclid((nlon - 1) as f64);
+        }
+        if fi < -EPS || fi > (nlat - 1) as f64 + EPS || fj < -EPS || fj > (nlon - 1) as f64 + EPS {
+            return None;
+        }
+        let i0 = (fi.floor().max(0.0) as usize).min(nlat.saturating_sub(2));
+        let j0 = (fj.floor().max(0.0) as usize).min(nlon.saturating_sub(2));
+        let i1 = (i0 + 1).min(nlat - 1);
+        let j1 = (j0 + 1).min(nlon - 1);
+        let p = (fi - i0 as f64).clamp(0.0, 1.0);
+        let q = (fj - j0 as f64).clamp(0.0, 1.0);
+        // Grid points with zero weight may be missing
+        [
+            (i0, j0, (1.0 - p) * (1.0 - q)),
+            (i0, j1, (1.0 - p) * q),
+            (i1, j0, p * (1.0 - q)),
+            (i1, j1, p * q),
+        ]
+        .into_iter()
+        .filter(|(_, _, w)| *w > EPS)
+        .map(|(i, j, w)| values[i * nlon + j].map(|v| w * v))
+        .sum()
+    }
+}
+
+/// Per-cell accumulator: (sum, sum of squares, sum of squared errors, count)
+type CellStats = (f64, f64, f64, usize);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Configuration for building IONEX maps from TEC measurements
+pub struct IonexConfig {
+    /// Map grid
+    pub grid: IonexGrid,
+    /// Interval between maps. Map epochs are aligned to multiples
+    /// of this interval since the UNIX epoch, and each map holds the
+    /// samples within half an interval of its epoch.
+    pub interval: Duration,
+    /// Minimum satellite elevation (degrees)
+    pub elevation_cutoff: f64,
+}
+
+impl Default for IonexConfig {
+    fn default() -> Self {
+        Self {
+            grid: IonexGrid::default(),
+            interval: Duration::from_secs(3600),
+            elevation_cutoff: 20.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A single vertical TEC map
+pub struct IonexMap {
+    epoch: DateTime<Utc>,
+    tec: Vec<Option<f64>>,
+    rms: Option<Vec<Option<f64>>>,
+}
+
+impl IonexMap {
+    /// Get the epoch of the map
+    pub fn epoch(&self) -> DateTime<Utc> {
+        self.epoch
+    }
+
+    /// Get the vertical TEC (TECU) at the grid points, in row-major order
+    /// starting from the first latitude and longitude of the grid
+    pub fn tec(&self) -> &Vec<Option<f64>> {
+        &self.tec
+    }
+
+    /// Get the RMS error (TECU) of the vertical TEC at the grid points
+    pub fn rms(&self) -> Option<&Vec<Option<f64>>> {
+        self.rms.as_ref()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Global or regional ionosphere maps in the IONosphere map EXchange
+/// (IONEX) format, version 1.0.
+///
+/// Only two-dimensional (single-layer) maps are supported.
+pub struct Ionex {
+    program: String,
+    agency: String,
+    description: Vec<String>,
+    mapping_function: String,
+    elevation_cutoff: f64,
+    observables: String,
+    base_radius: f64,
+    exponent: i32,
+    grid: IonexGrid,
+    interval: Duration,
+    maps: Vec<IonexMap>,
+}
+
+impl Ionex {
+    /// Load IONEX maps from a file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, IonexError> {
+        let file = std::fs::File::open(path)?;
+        Self::read(BufReader::new(file))
+    }
+
+    /// Read IONEX maps
+    pub fn read<R: BufRead>(reader: R) -> Result<Self, IonexError> {
+        let mut lines = reader.lines().enumerate();
+        let mut next = |expect: &'static str| -> Result<(usize, String), IonexError> {
+            match lines.next() {
+                Some((n, line)) => Ok((n + 1, line?)),
+                None => Err(IonexError::MissingHeader(expect)),
+            }
+        };
+        let err = |line: usize, msg: &str| IonexError::Format {
+            line,
+            msg: msg.to_string(),
+        };
+
+        // Header
+        let mut ionex = Ionex {
+            program: String::new(),
+            agency: String::new(),
+            description: Vec::new(),
+            mapping_function: "NONE".into(),
+            elevation_cutoff: 0.0,
+            observables: String::new(),
+            base_radius: 6371.0,
+            exponent: -1,
+            grid: IonexGrid::default(),
+            interval: Duration::ZERO,
+            maps: Vec::new(),
+        };
+        let (mut lat, mut lon, mut hgt, mut version) = (None, None, None, None);
+        loop {
+            let (n, line) = next("END OF HEADER")?;
+            let (content, label) = split_label(&line);
+            match label {
+                "IONEX VERSION / TYPE" => {
+                    version = Some(parse_num::<f64>(content, 0..8).ok_or(err(n, "version"))?);
+                }
+                "PGM / RUN BY / DATE" => {
+                    ionex.program = field(content, 0..20).to_string();
+                    ionex.agency = field(content, 20..40).to_string();
+                }
+                "DESCRIPTION" => ionex.description.push(content.trim_end().to_string()),
+                "INTERVAL" => {
+                    let secs = parse_num::<u64>(content, 0..6).ok_or(err(n, "interval"))?;
+                    ionex.interval = Duration::from_secs(secs);
+                }
+                "MAPPING FUNCTION" => ionex.mapping_function = field(content, 2..6).to_string(),
+                "ELEVATION CUTOFF" => {
+                    ionex.elevation_cutoff =
+                        parse_num(content, 0..8).ok_or(err(n, "elevation cutoff"))?
+                }
+                "OBSERVABLES USED" => ionex.observables = content.trim().to_string(),
+                "BASE RADIUS" => {
+                    ionex.base_radius = parse_num(content, 0..8).ok_or(err(n, "base radius"))?
+                }
+                "MAP DIMENSION" => {
+                    let dim = parse_num::<u8>(content, 0..6).ok_or(err(n, "map dimension"))?;
+                    if dim != 2 {
+                        return Err(IonexError::Unsupported(format!("{dim}-dimensional maps")));
+                    }
+                }
+                "HGT1 / HGT2 / DHGT" => hgt = Some(parse_triple(content).ok_or(err(n, "height"))?),
+                "LAT1 / LAT2 / DLAT" => {
+                    lat = Some(parse_triple(content).ok_or(err(n, "latitude"))?)
+                }
+                "LON1 / LON2 / DLON" => {
+                    lon = Some(parse_triple(content).ok_or(err(n, "longitude"))?)
+                }
+                "EXPONENT" => {
+                    ionex.exponent = parse_num(content, 0..6).ok_or(err(n, "exponent"))?
+                }
+                "END OF HEADER" => break,
+                _ => {}
+            }
+        }
+        version.ok_or(IonexError::MissingHeader("IONEX VERSION / TYPE"))?;
+        let hgt = hgt.ok_or(IonexError::MissingHeader("HGT1 / HGT2 / DHGT"))?;
+        if hgt.2 != 0.0 {
+            return Err(IonexError::Unsupported("multiple map heights".into()));
+        }
+        ionex.grid = IonexGrid {
+            lat: lat.ok_or(IonexError::MissingHeader("LAT1 / LAT2 / DLAT"))?,
+            lon: lon.ok_or(IonexError::MissingHeader("LON1 / LON2 / DLON"))?,
+            height: hgt.0,
+        };
+        if ionex.grid.lat.2 == 0.0 || ionex.grid.lon.2 == 0.0 {
+            return Err(err(0, "zero grid step"));
+        }
+
+        // Data records
+        let grid = ionex.grid;
+        let (nlat, nlon) = (grid.num_lat(), grid.num_lon());
+        let scale = 10f64.powi(ionex.exponent);
+        let mut tec_maps: HashMap<usize, IonexMap> = HashMap::new();
+        let mut rms_maps: HashMap<usize, Vec<Option<f64>>> = HashMap::new();
+        loop {
+            // A file that ends without the END OF FILE record is truncated
+            let (n, line) = next("END OF FILE")?;
+            let (content, label) = split_label(&line);
+            let kind = match label {
+                "START OF TEC MAP" => "TEC",
+                "START OF RMS MAP" => "RMS",
+                "START OF HEIGHT MAP" => "HEIGHT",
+                "END OF FILE" => break,
+                _ => continue,
+            };
+            let index = parse_num::<usize>(content, 0..6).ok_or(err(n, "map index"))?;
+            let end = format!("END OF {kind} MAP");
+            let mut epoch = None;
+            let mut values = vec![None; nlat * nlon];
+            loop {
+                let (n, line) = next("END OF MAP")?;
+                let (content, label) = split_label(&line);
+                match label {
+                    "EPOCH OF CURRENT MAP" => {
+                        epoch = Some(parse_epoch(content).ok_or(err(n, "epoch"))?);
+                    }
+                    "LAT/LON1/LON2/DLON/H" => {
+                        let row_lat = parse_num::<f64>(content, 2..8).ok_or(err(n, "latitude"))?;
+                        let row = ((row_lat - grid.lat.0) / grid.lat.2).round();
+                        if row < 0.0 || row >= nlat as f64 {
+                            return Err(err(n, "latitude outside grid"));
+                        }
+                        let row = row as usize;
+                        let mut col = 0;
+                        while col < nlon {
+                            let (n, line) = next("grid values")?;
+                            for chunk in line.as_bytes().chunks(5) {
+                                if col >= nlon {
+                                    break;
+                                }
+                                let val = std::str::from_utf8(chunk)
+                                    .ok()
+                                    .and_then(|x| x.trim().parse::<i64>().ok())
+                                    .ok_or(err(n, "grid value"))?;
+                                values[row * nlon + col] =
+                                    (val != MISSING).then_some(val as f64 * scale);
+                                col += 1;
+                            }
+                        }
+                    }
+                    x if x == end => break,
+                    _ => {}
+                }
+            }
+            match kind {
+                "TEC" => {
+                    let epoch = epoch.ok_or(err(n, "missing map epoch"))?;
+                    tec_maps.insert(
+                        index,
+                        IonexMap {
+                            epoch,
+                            tec: values,
+                            rms: None,
+                        },
+                    );
+                }
+                "RMS" => {
+                    rms_maps.insert(index, values);
+                }
+                _ => {}
+            }
+        }
+        let mut maps = tec_maps.into_iter().collect::<Vec<_>>();
+        maps.sort_by_key(|(idx, _)| *idx);
+        ionex.maps = maps
+            .into_iter()
+            .map(|(idx, mut map)| {
+                map.rms = rms_maps.remove(&idx);
+                map
+            })
+            .collect();
+        Ok(ionex)
+    }
+
+    /// Build vertical TEC maps from a series of TEC measurements.
+    ///
+    /// The absolute (pseudorange) slant TEC of each satellite is mapped to
+    /// vertical TEC at its ionospheric pierce point using the thin-shell
+    /// mapping function, and averaged over the nearest grid point. Grid
+    /// points without measurements are left empty. On global grids, the
+    /// last longitude column repeats the first.
+    pub fn from_series<'a, I: IntoIterator<Item = &'a TecInfo>>(
+        series: I,
+        config: IonexConfig,
+    ) -> Option<Self> {
+        let grid = config.grid;
+        let ncell = grid.num_lat() * grid.num_lon();
+        let half = config.interval / 2;
+        let mut bins: HashMap<DateTime<Utc>, Vec<CellStats>> = HashMap::new();
+        for tec in series {
+            let epoch = window_start(tec.timestamp() + half, config.interval);
+            for data in tec.tec() {
+                let el = data.elevation() as f64;
+                if el < config.elevation_cutoff || el < 0.0 {
+                    continue;
+                }
+                let Some(stec) = data.range_tec() else {
+                    continue;
+                };
+                let az = data.azimuth() as f64;
+                let (lat, lon) = pierce_point(tec.location(), az, el, grid.height * 1e3);
+                let Some(cell) = grid.nearest(lat, lon) else {
+                    continue;
+                };
+                let mf = mapping_function(el, grid.height * 1e3);
+                let (vtec, err) = (stec.value() / mf, stec.error() / mf);
+                let bin = bins
+                    .entry(epoch)
+                    .or_insert_with(|| vec![(0.0, 0.0, 0.0, 0); ncell]);
+                let cell = &mut bin[cell];
+                cell.0 += vtec;
+                cell.1 += vtec * vtec;
+                cell.2 += err * err;
+                cell.3 += 1;
+            }
+        }
+        let first = *bins.keys().min()?;
+        let last = *bins.keys().max()?;
+        let step = TimeDelta::from_std(config.interval).ok()?;
+        let mut maps = Vec::new();
+        let mut epoch = first;
+        while epoch <= last {
+            let (mut tec, mut rms): (Vec<_>, Vec<_>) = match bins.get(&epoch) {
+                Some(bin) => bin
+                    .iter()
+                    .map(|&(sum, sumsq, errsq, count)| {
+                        if count == 0 {
+                            return (None, None);
+                        }
+                        let n = count as f64;
+                        let mean = sum / n;
+                        let var = (sumsq / n - mean * mean).max(0.0);
+                        (Some(mean), Some((var + errsq / n).sqrt()))
+                    })
+                    .unzip(),
+                None => (vec![None; ncell], vec![None; ncell]),
+            };
+            grid.wrap_columns(&mut tec);
+            grid.wrap_columns(&mut rms);
+            maps.push(IonexMap {
+                epoch,
+                tec,
+                rms: Some(rms),
+            });
+            epoch += step;
+        }
+        Some(Ionex {
+            program: env!("CARGO_PKG_NAME").into(),
+            agency: String::new(),
+            description: vec!["Vertical TEC from dual-frequency GNSS pseudoranges".into()],
+            mapping_function: "COSZ".into(),
+            elevation_cutoff: config.elevation_cutoff,
+            observables: "Dual-frequency pseudorange".into(),
+            base_radius: 6371.0,
+            exponent: -1,
+            grid,
+            interval: config.interval,
+            maps,
+        })
+    }
+
+    /// Write the maps in IONEX format
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), std::io::Error> {
+        let mut record = |content: String, label: &str| writeln!(writer, "{content:<60}{label}");
+        record(
+            format!(
+                "{:8.1}{:12}{:<20}{:<20}",
+                1.0, "", "IONOSPHERE MAPS", "GNSS"
+            ),
+            "IONEX VERSION / TYPE",
+        )?;
+        record(
+            format!(
+                "{:<20.20}{:<20.20}{:<20.20}",
+                self.program,
+                self.agency,
+                Utc::now().format("%d-%b-%y %H:%M")
+            ),
+            "PGM / RUN BY / DATE",
+        )?;
+        for line in self.description.iter() {
+            record(format!("{line:.60}"), "DESCRIPTION")?;
+        }
+        if let (Some(first), Some(last)) = (self.maps.first(), self.maps.last()) {
+            record(format_epoch(first.epoch), "EPOCH OF FIRST MAP")?;
+            record(format_epoch(last.epoch), "EPOCH OF LAST MAP")?;
+        }
+        record(format!("{:6}", self.interval.as_secs()), "INTERVAL")?;
+        record(format!("{:6}", self.maps.len()), "# OF MAPS IN FILE")?;
+        record(
+            format!("  {:<4.4}", self.mapping_function),
+            "MAPPING FUNCTION",
+        )?;
+        record(format!("{:8.1}", self.elevation_cutoff), "ELEVATION CUTOFF")?;
+        record(format!("{:.60}", self.observables), "OBSERVABLES USED")?;
+        record(format!("{:8.1}", self.base_radius), "BASE RADIUS")?;
+        record(format!("{:6}", 2), "MAP DIMENSION")?;
+        let g = &self.grid;
+        record(
+            format!("  {:6.1}{:6.1}{:6.1}", g.height, g.height, 0.0),
+            "HGT1 / HGT2 / DHGT",
+        )?;
+        record(
+            format!("  {:6.1}{:6.1}{:6.1}", g.lat.0, g.lat.1, g.lat.2),
+            "LAT1 / LAT2 / DLAT",
+        )?;
+        record(
+            format!("  {:6.1}{:6.1}{:6.1}", g.lon.0, g.lon.1, g.lon.2),
+            "LON1 / LON2 / DLON",
+        )?;
+        record(format!("{:6}", self.exponent), "EXPONENT")?;
+        record(String::new(), "END OF HEADER")?;
+        for (idx, map) in self.maps.iter().enumerate() {
+            self.write_map(writer, idx + 1, map.epoch, &map.tec, "TEC")?;
+        }
+        for (idx, map) in self.maps.iter().enumerate() {
+            if let Some(rms) = &map.rms {
+                self.write_map(writer, idx + 1, map.epoch, rms, "RMS")?;
+            }
+        }
+        writeln!(writer, "{:<60}END OF FILE", "")
+    }
+
+    fn write_map<W: Write>(
+        &self,
+        writer: &mut W,
+        index: usize,
+        epoch: DateTime<Utc>,
+        values: &[Option<f64>],
+        kind: &str,
+    ) -> Result<(), std::io::Error> {
+        let g = &self.grid;
+        let scale = 10f64.powi(-self.exponent);
+        writeln!(writer, "{:<60}START OF {kind} MAP", format!("{index:6}"))?;
+        writeln!(writer, "{:<60}EPOCH OF CURRENT MAP", format_epoch(epoch))?;
+        for (row, values) in values.chunks(g.num_lon()).enumerate() {
+            let lat = g.lat.0 + row as f64 * g.lat.2;
+            writeln!(
+                writer,
+                "{:<60}LAT/LON1/LON2/DLON/H",
+                format!(
+                    "  {:6.1}{:6.1}{:6.1}{:6.1}{:6.1}",
+                    lat, g.lon.0, g.lon.1, g.lon.2, g.height
+                )
+            )?;
+            for line in values.chunks(VALUES_PER_LINE) {
+                let line = line
+                    .iter()
+                    .map(|v| {
+                        let v =
+                            v.map_or(MISSING, |v| ((v * scale).round() as i64).min(MISSING - 1));
+                        format!("{v:5}")
+                    })
+                    .collect::<String>();
+                writeln!(writer, "{line}")?;
+            }
+        }
+        writeln!(writer, "{:<60}END OF {kind} MAP", format!("{index:6}"))
+    }
+
+    /// Get the vertical TEC (TECU) at a location on the ionospheric shell.
+    ///
+    /// The maps bracketing `time` are rotated by the Earth's rotation
+    /// relative to the Sun, bilinearly interpolated in space, and linearly
+    /// interpolated in time, as recommended by the IONEX specification.
+    ///
+    /// # Arguments
+    /// - `time`: Time of the observation
+    /// - `lat`: Latitude (degrees)
+    /// - `lon`: Longitude (degrees)
+    pub fn vtec(&self, time: DateTime<Utc>, lat: f64, lon: f64) -> Option<f64> {
+        let idx = self.maps.partition_point(|m| m.epoch <= time);
+        let rotated = |map: &IonexMap| {
+            let dt = (time - map.epoch).num_milliseconds() as f64 * 1e-3;
+            self.grid
+                .interpolate(&map.tec, lat, lon + 360.0 * dt / 86400.0)
+        };
+        match (
+            idx.checked_sub(1).map(|i| &self.maps[i]),
+            self.maps.get(idx),
+        ) {
+            (Some(m0), _) if m0.epoch == time => self.grid.interpolate(&m0.tec, lat, lon),
+            (Some(m0), Some(m1)) => {
+                let span = (m1.epoch - m0.epoch).num_milliseconds() as f64;
+                let w = (time - m0.epoch).num_milliseconds() as f64 / span;
+                Some((1.0 - w) * rotated(m0)? + w * rotated(m1)?)
+            }
+            _ => None,
+        }
+    }
+
+    /// Get the grid of the maps
+    pub fn grid(&self) -> IonexGrid {
+        self.grid
+    }
+
+    /// Get the interval between maps
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Get the name of the program that created the file
+    pub fn program(&self) -> &str {
+        &self.program
+    }
+
+    /// Get the agency that created the file
+    pub fn agency(&self) -> &str {
+        &self.agency
+    }
+
+    /// Get the mapping function used to create the maps
+    pub fn mapping_function(&self) -> &str {
+        &self.mapping_function
+    }
+
+    /// Get the elevation cutoff (degrees) used to create the maps
+    pub fn elevation_cutoff(&self) -> f64 {
+        self.elevation_cutoff
+    }
+
+    /// Get the maps
+    pub fn maps(&self) -> &Vec<IonexMap> {
+        &self.maps
+    }
+}
+
+impl IonoModel for Ionex {
+    fn slant_tec(
+        &self,
+        time: DateTime<Utc>,
+        location: Geodetic,
+        azimuth: f64,
+        elevation: f64,
+    ) -> Option<f64> {
+        let vtec = self.vertical_tec(time, location, azimuth, elevation)?;
+        Some(vtec * mapping_function(elevation, self.grid.height * 1e3))
+    }
+
+    fn vertical_tec(
+        &self,
+        time: DateTime<Utc>,
+        location: Geodetic,
+        azimuth: f64,
+        elevation: f64,
+    ) -> Option<f64> {
+        if elevation < 0.0 {
+            return None;
+        }
+        let (lat, lon) = pierce_point(location, azimuth, elevation, self.grid.height * 1e3);
+        self.vtec(time, lat, lon)
+    }
+}
+
+/// Split an IONEX record into its contents and its label
+fn split_label(line: &str) -> (&str, &str) {
+    match line.get(60..) {
+        Some(label) => (&line[..60], label.trim()),
+        None => (line, ""),
+    }
+}
+
+fn field(content: &str, range: std::ops::Range<usize>) -> &str {
+    let end = range.end.min(content.len());
+    content.get(range.start.min(end)..end).unwrap_or("").trim()
+}
+
+fn parse_num<T: std::str::FromStr>(content: &str, range: std::ops::Range<usize>) -> Option<T> {
+    field(content, range).parse().ok()
+}
+
+fn parse_triple(content: &str) -> Option<(f64, f64, f64)> {
+    Some((
+        parse_num(content, 2..8)?,
+        parse_num(content, 8..14)?,
+        parse_num(content, 14..20)?,
+    ))
+}
+
+fn parse_epoch(content: &str) -> Option<DateTime<Utc>> {
+    let v = (0..6)
+        .map(|i| parse_num::<u32>(content, 6 * i..6 * (i + 1)))
+        .collect::<Option<Vec<_>>>()?;
+    Utc.with_ymd_and_hms(v[0] as i32, v[1], v[2], v[3], v[4], v[5])
+        .single()
+}
+
+fn format_epoch(epoch: DateTime<Utc>) -> String {
+    format!(
+        "{:6}{:6}{:6}{:6}{:6}{:6}",
+        epoch.year(),
+        epoch.month(),
+        epoch.day(),
+        epoch.hour(),
+        epoch.minute(),
+        epoch.second()
+    )
+}
+
+mod test {
+    #[test]
+    fn test_ionex() {
+        use super::*;
+        use crate::{tec::TecData, GnssSatellite, GpsFreq, Uncertain};
+        let grid = IonexGrid {
+            lat: (50.0, 30.0, -2.5),
+            lon: (-110.0, -90.0, 5.0),
+            height: 450.0,
+        };
+        let start = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        // Zenith measurements of 20 TECU in the first hour, 30 TECU in the third
+        let series = [(0, 20.0), (10, 20.0), (7200, 30.0)]
+            .into_iter()
+            .map(|(dt, tec)| TecInfo {
+                timestamp: start + TimeDelta::seconds(dt),
+                location: Geodetic::new(40.0, -100.0, 0.0),
+                tec: vec![TecData {
+                    source: GnssSatellite::Gps(1),
+                    pointing: (0, 90),
+                    channels: (GpsFreq::L1CA.into(), GpsFreq::L2CL.into()),
+                    phase_tec: None,
+                    range_tec: Some(Uncertain::new(tec, 0.0)),
+                    trk_stat: Default::default(),
+                    modeled_tec: None,
+                }],
+            })
+            .collect::<Vec<_>>();
+        let config = IonexConfig {
+            grid,
+            ..Default::default()
+        };
+        let ionex = Ionex::from_series(&series, config).unwrap();
+        assert_eq!(ionex.maps().len(), 3);
+        let mut buf = Vec::new();
+        ionex.write(&mut buf).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        assert!(text.lines().all(|l| l.len() <= 80));
+
+        let ionex = Ionex::read(text.as_bytes()).unwrap();
+        assert_eq!(ionex.grid(), grid);
+        assert_eq!(ionex.interval(), Duration::from_secs(3600));
+        assert_eq!(ionex.maps().len(), 3);
+        assert_eq!(ionex.maps()[1].tec().iter().flatten().count(), 0);
+        assert_eq!(ionex.vtec(start, 40.0, -100.0), Some(20.0));
+        assert_eq!(ionex.maps()[0].rms().unwrap()[4 * 5 + 2], Some(0.0));
+        // Neighbouring grid points are empty
+        assert_eq!(ionex.vtec(start, 41.0, -100.0), None);
+        let stec = ionex.slant_tec(start, Geodetic::new(40.0, -100.0, 0.0), 0.0, 90.0);
+        assert!((stec.unwrap() - 20.0).abs() < 1e-9);
+
+        // Interpolation in space and time
+        let mut text = String::new();
+        for line in [
+            "     1.0            IONOSPHERE MAPS     GPS                 IONEX VERSION / TYPE",
+            "  1200                                                      INTERVAL",
+            "     2                                                      MAP DIMENSION",
+            "   450.0 450.0   0.0                                        HGT1 / HGT2 / DHGT",
+            "    10.0   0.0 -10.0                                        LAT1 / LAT2 / DLAT",
+            "     0.0  10.0  10.0                                        LON1 / LON2 / DLON",
+            "    -1                                                      EXPONENT",
+            "                                                            END OF HEADER",
+            "     1                                                      START OF TEC MAP",
+            "  2024     3     1     0     0     0                        EPOCH OF CURRENT MAP",
+            "    10.0   0.0  10.0  10.0 450.0                            LAT/LON1/LON2/DLON/H",
+            "  100  200",
+            "     0.0   0.0  10.0  10.0 450.0                            LAT/LON1/LON2/DLON/H",
+            "  300  400",
+            "     1                                                      END OF TEC MAP",
+            "     2                                                      START OF TEC MAP",
+            "  2024     3     1     0    20     0                        EPOCH OF CURRENT MAP",
+            "    10.0   0.0  10.0  10.0 450.0                            LAT/LON1/LON2/DLON/H",
+            "  300  400",
+            "     0.0   0.0  10.0  10.0 450.0                            LAT/LON1/LON2/DLON/H",
+            "  500  600",
+            "     2                                                      END OF TEC MAP",
+            "                                                            END OF FILE",
+        ] {
+            text.push_str(line);
+            text.push('\n');
+        }
+        let ionex = Ionex::read(text.as_bytes()).unwrap();
+        assert_eq!(ionex.vtec(start, 5.0, 5.0), Some(25.0));
+        assert_eq!(ionex.vtec(start, 10.0, 10.0), Some(20.0));
+        // Halfway between the maps, each map is rotated by 2.5 degrees
+        let mid = start + TimeDelta::minutes(10);
+        let v = ionex.vtec(mid, 5.0, 5.0).unwrap();
+        assert!((v - 0.5 * (27.5 + 42.5)).abs() < 1e-9);
+        assert_eq!(ionex.vtec(start + TimeDelta::hours(1), 5.0, 5.0), None);
+
+        // The +180 degree column of a global grid repeats the -180 degree column
+        let series = [TecInfo {
+            location: Geodetic::new(40.0, -180.0, 0.0),
+            ..series[0].clone()
+        }];
+        let ionex = Ionex::from_series(&series, IonexConfig::default()).unwrap();
+        let mut buf = Vec::new();
+        ionex.write(&mut buf).unwrap();
+        let ionex = Ionex::read(buf.as_slice()).unwrap();
+        let row = &ionex.maps()[0].tec()[19 * 73..20 * 73];
+        assert_eq!((row[0], row[72]), (Some(20.0), Some(20.0)));
+        assert_eq!(ionex.maps()[0].tec().iter().flatten().count(), 2);
+
+        // Truncated files and read errors
+        let truncated = text.trim_end().rsplit_once('\n').unwrap().0;
+        assert!(matches!(
+            Ionex::read(truncated.as_bytes()),
+            Err(IonexError::MissingHeader("END OF FILE"))
+        ));
+        let mut bytes = text.into_bytes();
+        let eof = bytes.len() - 5;
+        bytes[eof] = 0xff;
+        assert!(matches!(
+            Ionex::read(bytes.as_slice()),
+            Err(IonexError::Io(_))
+        ));
+    }
+}
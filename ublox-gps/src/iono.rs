@@ -0,0 +1,220 @@
+use std::f64::consts::PI;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{geo::Geodetic, ubx::GPS_EPOCH};
+
+/// Speed of light in vacuum (m/s)
+pub(crate) const SPEED_OF_LIGHT: f64 = 299_792_458.0;
+/// GPS L1 carrier frequency (Hz)
+pub(crate) const GPS_L1: f64 = 1575.42e6;
+/// Mean Earth radius used in the thin-shell ionosphere approximation (m)
+pub(crate) const EARTH_RADIUS: f64 = 6_371_000.0;
+
+/// Ionospheric delay (m) of 1 TECU on a carrier of frequency `freq` (Hz)
+pub(crate) fn tecu_delay(freq: f64) -> f64 {
+    40.308e16 / (freq * freq)
+}
+
+/// A broadcast ionospheric model, providing the modeled total electron
+/// content along the line of sight from a receiver to a satellite.
+pub trait IonoModel {
+    /// Get the modeled slant TEC (TECU).
+    ///
+    /// # Arguments
+    /// - `time`: Time of the observation
+    /// - `location`: Receiver location
+    /// - `azimuth`: Satellite azimuth (degrees)
+    /// - `elevation`: Satellite elevation (degrees)
+    fn slant_tec(
+        &self,
+        time: DateTime<Utc>,
+        location: Geodetic,
+        azimuth: f64,
+        elevation: f64,
+    ) -> Option<f64>;
+
+    /// Get the modeled vertical TEC (TECU) at the ionospheric pierce point
+    /// of the line of sight.
+    ///
+    /// # Arguments
+    /// - `time`: Time of the observation
+    /// - `location`: Receiver location
+    /// - `azimuth`: Satellite azimuth (degrees)
+    /// - `elevation`: Satellite elevation (degrees)
+    fn vertical_tec(
+        &self,
+        time: DateTime<Utc>,
+        location: Geodetic,
+        azimuth: f64,
+        elevation: f64,
+    ) -> Option<f64>;
+}
+
+/// Get the ionospheric pierce point of a line of sight for a thin-shell
+/// ionosphere.
+///
+/// # Arguments
+/// - `location`: Receiver location
+/// - `azimuth`: Satellite azimuth (degrees)
+/// - `elevation`: Satellite elevation (degrees)
+/// - `shell_height`: Height of the ionospheric shell (m)
+///
+/// # Returns
+/// - A tuple of (latitude, longitude) of the pierce point in degrees
+pub fn pierce_point(
+    location: Geodetic,
+    azimuth: f64,
+    elevation: f64,
+    shell_height: f64,
+) -> (f64, f64) {
+    let (lat, lon) = (location.lat.to_radians(), location.lon.to_radians());
+    let (az, el) = (azimuth.to_radians(), elevation.to_radians());
+    let psi = PI / 2.0 - el - (EARTH_RADIUS / (EARTH_RADIUS + shell_height) * el.cos()).asin();
+    let lat_ipp = (lat.sin() * psi.cos() + lat.cos() * psi.sin() * az.cos()).asin();
+    let lon_ipp =
+        lon + (psi.sin() * az.sin() * lat.cos()).atan2(psi.cos() - lat.sin() * lat_ipp.sin());
+    let lon_ipp = (lon_ipp + PI).rem_euclid(2.0 * PI) - PI;
+    (lat_ipp.to_degrees(), lon_ipp.to_degrees())
+}
+
+/// Get the thin-shell mapping function (slant to vertical TEC ratio)
+/// of a line of sight.
+///
+/// # Arguments
+/// - `elevation`: Satellite elevation (degrees)
+/// - `shell_height`: Height of the ionospheric shell (m)
+pub fn mapping_function(elevation: f64, shell_height: f64) -> f64 {
+    let x = EARTH_RADIUS / (EARTH_RADIUS + shell_height) * elevation.to_radians().cos();
+    1.0 / (1.0 - x * x).sqrt()
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+/// GPS broadcast (Klobuchar) ionospheric model, as specified in IS-GPS-200.
+pub struct Klobuchar {
+    alpha: [f64; 4],
+    beta: [f64; 4],
+}
+
+impl Klobuchar {
+    /// Create a Klobuchar model from the broadcast coefficients.
+    ///
+    /// # Arguments
+    /// - `alpha`: Amplitude coefficients (s, s/semicircle, s/semicircle², s/semicircle³)
+    /// - `beta`: Period coefficients (s, s/semicircle, s/semicircle², s/semicircle³)
+    pub fn new(alpha: [f64; 4], beta: [f64; 4]) -> Self {
+        Self { alpha, beta }
+    }
+
+    /// Get the amplitude coefficients
+    pub fn alpha(&self) -> [f64; 4] {
+        self.alpha
+    }
+
+    /// Get the period coefficients
+    pub fn beta(&self) -> [f64; 4] {
+        self.beta
+    }
+
+    /// Get the modeled ionospheric delay on the GPS L1 carrier.
+    ///
+    /// # Arguments
+    /// - `time`: Time of the observation
+    /// - `location`: Receiver location
+    /// - `azimuth`: Satellite azimuth (degrees)
+    /// - `elevation`: Satellite elevation (degrees)
+    ///
+    /// # Returns
+    /// - A tuple of (slant delay in m, obliquity factor)
+    pub fn l1_delay(
+        &self,
+        time: DateTime<Utc>,
+        location: Geodetic,
+        azimuth: f64,
+        elevation: f64,
+    ) -> (f64, f64) {
+        // Angles in semicircles
+        let phi_u = location.lat / 180.0;
+        let lambda_u = location.lon / 180.0;
+        let el = elevation / 180.0;
+        let az = azimuth.to_radians();
+        // Earth central angle between the user and the pierce point
+        let psi = 0.0137 / (el + 0.11) - 0.022;
+        let phi_i = (phi_u + psi * az.cos()).clamp(-0.416, 0.416);
+        let lambda_i = lambda_u + psi * az.sin() / (phi_i * PI).cos();
+        // Geomagnetic latitude of the pierce point
+        let phi_m = phi_i + 0.064 * ((lambda_i - 1.617) * PI).cos();
+        // Local time at the pierce point
+        let tow = (time - GPS_EPOCH).num_milliseconds() as f64 * 1e-3;
+        let t = (4.32e4 * lambda_i + tow).rem_euclid(86400.0);
+        let obliquity = 1.0 + 16.0 * (0.53 - el).powi(3);
+        let poly = |c: &[f64; 4]| c.iter().rev().fold(0.0, |acc, x| acc * phi_m + x);
+        let amp = poly(&self.alpha).max(0.0);
+        let per = poly(&self.beta).max(72000.0);
+        let x = 2.0 * PI * (t - 50400.0) / per;
+        let delay = if x.abs() < 1.57 {
+            obliquity * (5e-9 + amp * (1.0 - x * x / 2.0 + x.powi(4) / 24.0))
+        } else {
+            obliquity * 5e-9
+        };
+        (delay * SPEED_OF_LIGHT, obliquity)
+    }
+}
+
+impl IonoModel for Klobuchar {
+    fn slant_tec(
+        &self,
+        time: DateTime<Utc>,
+        location: Geodetic,
+        azimuth: f64,
+        elevation: f64,
+    ) -> Option<f64> {
+        if elevation < 0.0 {
+            return None;
+        }
+        let (delay, _) = self.l1_delay(time, location, azimuth, elevation);
+        Some(delay / tecu_delay(GPS_L1))
+    }
+
+    fn vertical_tec(
+        &self,
+        time: DateTime<Utc>,
+        location: Geodetic,
+        azimuth: f64,
+        elevation: f64,
+    ) -> Option<f64> {
+        if elevation < 0.0 {
+            return None;
+        }
+        let (delay, obliquity) = self.l1_delay(time, location, azimuth, elevation);
+        Some(delay / obliquity / tecu_delay(GPS_L1))
+    }
+}
+
+mod test {
+    #[test]
+    fn test_klobuchar() {
+        use super::*;
+        // Worked example of the IS-GPS-200 algorithm
+        let klob = Klobuchar::new(
+            [3.82e-8, 1.49e-8, -1.79e-7, 0.0],
+            [1.43e5, 0.0, -3.28e5, 1.13e5],
+        );
+        // 20:45:00 GPS time, receiver at 40 N, 100 W, satellite at 210 az, 20 el
+        let time = GPS_EPOCH + chrono::TimeDelta::seconds(593_100 + 604_800 * 1000);
+        let (delay, obliquity) = klob.l1_delay(time, Geodetic::new(40.0, -100.0, 0.0), 210.0, 20.0);
+        assert!((obliquity - 2.17602).abs() < 1e-5);
+        assert!((delay / SPEED_OF_LIGHT - 7.93354e-8).abs() < 1e-13);
+        let stec = klob
+            .slant_tec(time, Geodetic::new(40.0, -100.0, 0.0), 210.0, 20.0)
+            .unwrap();
+        let vtec = klob
+            .vertical_tec(time, Geodetic::new(40.0, -100.0, 0.0), 210.0, 20.0)
+            .unwrap();
+        assert!((stec / vtec - obliquity).abs() < 1e-9);
+        assert!(klob
+            .slant_tec(time, Geodetic::new(40.0, -100.0, 0.0), 210.0, -1.0)
+            .is_none());
+    }
+}
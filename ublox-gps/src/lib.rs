@@ -2,30 +2,96 @@
 //! # UBX GPS Parser
 //! A limited capability parser for UBX GPS messages.
 //!
-//! Parses NMEA GGA, GSA, GSV and VTG messages, along with UBX-RXM-RAWX messages.
+//! Parses NMEA ZDA, GGA, RMC, GLL, GNS, GST, GSA, GSV and VTG messages, along
+//! with UBX-RXM-RAWX, UBX-RXM-SFRBX and UBX-NAV-PVT messages, and encodes
+//! NMEA sentences from the decoded data.
 //! Provides a simple interface to extract timestamp, location, carrier phase
 //! and satellite information.
+mod ephemeris;
+mod fix;
+mod geo;
+mod ionex;
+mod iono;
+mod multipath;
+mod nav;
+mod nequick;
 mod nmea;
+mod nmea_encoder;
+mod nmea_fields;
 mod read_until;
+mod roti;
+mod s4;
+mod spp;
+pub mod stats;
 mod tec;
+mod time;
+pub mod tropo;
 mod ubx;
 mod uncertain;
+mod units;
 
 use std::io::Read;
 
+pub use fix::{FixMode, FixQuality};
 use log::warn;
-pub use nmea::{GnssSatellite, GpsError, NmeaGpsInfo};
+pub use nmea::{
+    ClockInfo, Constellation, GnssSatellite, GpsError, NmeaGpsInfo, PositionAccuracy,
+    PositionErrors, SignalView, TimeSource,
+};
+pub use nmea_encoder::{NmeaEncoder, NmeaVersion};
 use serde::{ser::SerializeMap, Deserialize, Serialize};
 pub use ubx::{
-    BeidouFreq, CarrierMeas, GalileoFreq, GlonassFreq, GnssFreq, GpsFreq, QzssFreq, SatPathInfo,
-    UbxGpsInfo,
+    BeidouFreq, CarrierMeas, GalileoFreq, GlonassFreq, GnssFreq, GpsFreq, HalfCyclePolicy,
+    NavicFreq, PhaseAmbiguity, QzssFreq, RecvStat, SatPathInfo, TrkStat, UbxAck, UbxClass,
+    UbxGpsInfo, UbxNav, UbxNavPvt, UbxRxm, UbxRxmRawx,
 };
 
-pub use tec::{TecData, TecInfo};
-pub use uncertain::Uncertain;
+pub use ephemeris::{Ephemeris, SatState};
+pub use geo::{Ecef, Egm96, Enu, Geodetic};
+pub use ionex::{Ionex, IonexConfig, IonexError, IonexGrid, IonexMap};
+pub use iono::{mapping_function, pierce_point, IonoModel, Klobuchar};
+pub use multipath::{
+    MultipathBin, MultipathConfig, MultipathData, MultipathEstimator, MultipathInfo,
+};
+pub use nav::{NavStore, UbxRxmSfrbx};
+pub use nequick::{NeQuickG, NequickData};
+pub use roti::{RotiConfig, RotiData, RotiEstimator, RotiInfo};
+pub use s4::{S4Config, S4Data, S4Estimator, S4Info};
+pub use spp::{Dop, IonoCorrection, Spp, SppConfig, SppSolution, VelocitySolution};
+pub use stats::{LeastSquares, PolyFit};
+pub use tec::{PairSelection, SignalPairPolicy, TecData, TecInfo};
+pub use time::{GnssTime, LeapSeconds, TimeScale};
+pub use tropo::{MappingFunction, TropoData, TropoInfo, Troposphere, ZenithDelay, ZenithModel};
+pub use uncertain::{Correlated, Uncertain};
+pub use units::{Cycles, GeometryFree, Meters, Tecu};
 
 use nmea::RawNmea;
-use ubx::{split_ubx, UbxFormat, UbxMessage, UbxRxmRawx};
+use ubx::{split_ubx, UbxFormat, UbxMessage};
+
+/// Decode UBX messages into carrier phase measurements and navigation data subframes
+fn decode_ubx<I: IntoIterator<Item = UbxMessage>>(ubx: I) -> (Vec<UbxRxmRawx>, Vec<UbxRxmSfrbx>) {
+    let mut rxm = Vec::with_capacity(1);
+    let mut nav = Vec::new();
+    for msg in ubx {
+        let res = match UbxClass::try_from((msg.class, msg.id)) {
+            Ok(UbxClass::Receiver(UbxRxm::SfrbX)) => {
+                UbxRxmSfrbx::from_message(msg).map(|x| nav.push(x))
+            }
+            _ => UbxRxmRawx::from_message(msg).map(|x| rxm.push(x)),
+        };
+        if let Err(e) = res {
+            warn!("Error parsing UBX message: {}", e);
+        }
+    }
+    (rxm, nav)
+}
+
+/// Get the UTC time of carrier phase measurements, if the receiver
+/// time is known
+fn rawx_time(rawx: Option<&UbxRxmRawx>) -> Option<chrono::DateTime<chrono::Utc>> {
+    rawx.filter(|x| x.gnss_time().is_some())
+        .map(|x| x.timestamp)
+}
 
 /// Default delimiter for separating UBX messages in a datafile
 pub const DEFAULT_DELIM: [u8; 8] = *b"\r\r\n\n\r\r\n\n";
@@ -46,6 +112,9 @@ pub struct GpsPacket {
     pub nmea_raw: NmeaMsgGroup,
     /// Raw RXM carrier data
     pub rxm: Option<UbxRxmRawx>,
+    /// Navigation data subframes
+    #[serde(default)]
+    pub nav: Vec<UbxRxmSfrbx>,
 }
 
 impl Serialize for NmeaMsgGroup {
@@ -92,7 +161,7 @@ impl From<GpsPacket> for UbxGpsInfo {
         if value.nmea.sat_views.is_empty() {
             value.nmea.insert_gsv(&mut value.nmea_raw);
         }
-        UbxGpsInfo::new(value.nmea, value.rxm, value.nmea_raw)
+        UbxGpsInfo::new(value.nmea, value.rxm, value.nmea_raw).with_navigation(value.nav)
     }
 }
 
@@ -109,7 +178,30 @@ impl From<GpsPacket> for UbxGpsInfo {
 pub fn parse_nmea(buf: Vec<u8>) -> Result<(NmeaGpsInfo, NmeaMsgGroup), GpsError> {
     let buf = std::str::from_utf8(&buf).map_err(|e| GpsError::ParseError(e.to_string()))?;
     let mut gpsmsg = RawNmea::parse_str(buf);
-    let nmea = NmeaGpsInfo::create(&mut gpsmsg, true)?;
+    let nmea = NmeaGpsInfo::create(&mut gpsmsg, true, None)?;
+    Ok((nmea, gpsmsg))
+}
+
+/// Parse a buffer to extract GPS positional information from NMEA messages only,
+/// with a geoid model.
+///
+/// Like [`parse_nmea`], but the height above the ellipsoid is computed with
+/// the geoid model if the receiver does not report the geoid separation.
+///
+/// # Arguments
+/// - `buf` - A vector of bytes containing the NMEA messages.
+/// - `geoid` - Geoid model, such as the EGM96 grid.
+///
+/// # Returns
+/// - A tuple containing the parsed NMEA GPS information and a group of unprocessed NMEA messages.
+///
+pub fn parse_nmea_with_geoid(
+    buf: Vec<u8>,
+    geoid: &Egm96,
+) -> Result<(NmeaGpsInfo, NmeaMsgGroup), GpsError> {
+    let buf = std::str::from_utf8(&buf).map_err(|e| GpsError::ParseError(e.to_string()))?;
+    let mut gpsmsg = RawNmea::parse_str(buf);
+    let nmea = NmeaGpsInfo::create_with_geoid(&mut gpsmsg, true, None, Some(geoid))?;
     Ok((nmea, gpsmsg))
 }
 
@@ -118,48 +210,49 @@ pub fn parse_messages(buf: Vec<u8>) -> Result<UbxGpsInfo, GpsError> {
     // 1. Separate into UBX and NMEA messages
     let (ubx, buf) = split_ubx(buf);
     // 2. Parse UBX messages
-    let mut rxm = Vec::new();
-    for msg in ubx {
-        match UbxRxmRawx::from_message(msg) {
-            Ok(msg) => {
-                rxm.push(msg);
-            }
-            Err(e) => warn!("Error parsing UBX message: {}", e),
-        }
-    }
+    let (mut rxm, nav) = decode_ubx(ubx);
     // 3. Parse NMEA messages
     let buf = std::str::from_utf8(&buf).map_err(|e| GpsError::ParseError(e.to_string()))?;
     let mut gpsmsg = RawNmea::parse_str(buf);
-    let nmea = NmeaGpsInfo::create(&mut gpsmsg, true)?;
-    let gpsinfo = UbxGpsInfo::new(nmea, rxm.pop(), gpsmsg);
+    let nmea = NmeaGpsInfo::create(&mut gpsmsg, true, rawx_time(rxm.last()))?;
+    let gpsinfo = UbxGpsInfo::new(nmea, rxm.pop(), gpsmsg).with_navigation(nav);
     Ok(gpsinfo)
 }
 
+/// Parse a buffer to extract UBX-NAV-PVT navigation solutions.
+///
+/// The solutions can be converted into [`NmeaGpsInfo`] to regenerate
+/// NMEA sentences with an [`NmeaEncoder`].
+pub fn parse_nav_pvt(buf: Vec<u8>) -> Vec<UbxNavPvt> {
+    let (ubx, _) = split_ubx(buf);
+    ubx.into_iter()
+        .filter(|msg| (msg.class, msg.id) == (0x1, 0x07))
+        .filter_map(|msg| {
+            UbxNavPvt::from_message(msg)
+                .map_err(|e| warn!("Error parsing UBX message: {}", e))
+                .ok()
+        })
+        .collect()
+}
+
 /// Parse a buffer into a GPS Packet
 pub fn parse_binary(buf: Vec<u8>) -> Result<GpsPacket, GpsError> {
     // 1. Separate into UBX and NMEA messages
     let (ubx, buf) = split_ubx(buf);
     // 2. Parse UBX messages
-    let mut rxm = Vec::with_capacity(1);
-    for msg in ubx {
-        match UbxRxmRawx::from_message(msg) {
-            Ok(msg) => {
-                rxm.push(msg);
-            }
-            Err(e) => warn!("Error parsing UBX message: {}", e),
-        }
-    }
+    let (mut rxm, nav) = decode_ubx(ubx);
     if rxm.len() > 1 {
         warn!("More than one RXM message in buffer.");
     }
     // 3. Parse NMEA messages
     let buf = std::str::from_utf8(&buf).map_err(|e| GpsError::ParseError(e.to_string()))?;
     let mut gpsmsg = RawNmea::parse_str(buf);
-    let nmea = NmeaGpsInfo::create(&mut gpsmsg, true)?;
+    let nmea = NmeaGpsInfo::create(&mut gpsmsg, true, rawx_time(rxm.last()))?;
     Ok(GpsPacket {
         nmea,
         nmea_raw: gpsmsg,
         rxm: rxm.pop(),
+        nav,
     })
 }
 
@@ -173,7 +266,10 @@ pub fn parse_partial(
     // 2. Parse NMEA messages
     let buf = std::str::from_utf8(&buf).map_err(|e| GpsError::ParseError(e.to_string()))?;
     let mut gpsmsg = RawNmea::parse_str(buf);
-    let nmea = NmeaGpsInfo::create(&mut gpsmsg, process_gsv)?;
+    let rawx = ubx
+        .last()
+        .and_then(|msg| UbxRxmRawx::from_message(msg.clone()).ok());
+    let nmea = NmeaGpsInfo::create(&mut gpsmsg, process_gsv, rawx_time(rawx.as_ref()))?;
     Ok((nmea, gpsmsg, ubx.pop()))
 }
 
@@ -183,15 +279,7 @@ pub fn from_partial(
     nmea_raw: NmeaMsgGroup,
     ubx: Option<UbxMessage>,
 ) -> Result<GpsPacket, GpsError> {
-    let mut rxm = Vec::new();
-    if let Some(ubx) = ubx {
-        match UbxRxmRawx::from_message(ubx) {
-            Ok(msg) => {
-                rxm.push(msg);
-            }
-            Err(e) => warn!("Error parsing UBX message: {}", e),
-        }
-    }
+    let (mut rxm, nav) = decode_ubx(ubx);
     if rxm.len() > 1 {
         warn!("More than one RXM message in buffer.");
     }
@@ -199,6 +287,7 @@ pub fn from_partial(
         nmea,
         nmea_raw,
         rxm: rxm.pop(),
+        nav,
     })
 }
 
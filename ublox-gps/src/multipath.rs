@@ -0,0 +1,573 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    geo::Geodetic,
+    iono::SPEED_OF_LIGHT,
+    stats::detrend,
+    tec::MIN_FREQ_SEPARATION,
+    ubx::{Frequency, PhaseAmbiguity},
+    uncertain::Uncertain,
+    CarrierMeas, GnssFreq, GnssSatellite, UbxGpsInfo,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Configuration for the code multipath (MP) computation
+pub struct MultipathConfig {
+    /// Maximum gap between consecutive samples before the
+    /// satellite arc is considered broken
+    pub max_gap: Duration,
+    /// Minimum number of samples in a continuous arc. Shorter arcs
+    /// are discarded, since their mean is poorly determined.
+    pub min_arc: usize,
+    /// Maximum change of the multipath combination (m) between
+    /// consecutive samples. Larger changes are treated as cycle
+    /// slips, and break the satellite arc.
+    pub max_jump: Option<f64>,
+    /// Minimum satellite elevation (degrees). Samples below this
+    /// elevation are ignored.
+    pub min_elevation: Option<i8>,
+    /// Width of the elevation bins (degrees) over which the RMS
+    /// multipath is reported
+    pub elevation_bin: u8,
+    /// Degree of the polynomial removed from the code-minus-carrier
+    /// of signals without a second frequency, to approximate the
+    /// ionospheric divergence. Such signals are ignored if `None`.
+    pub single_frequency: Option<usize>,
+}
+
+impl Default for MultipathConfig {
+    fn default() -> Self {
+        Self {
+            max_gap: Duration::from_secs(60),
+            min_arc: 20,
+            max_jump: Some(5.0),
+            min_elevation: Some(5),
+            elevation_bin: 5,
+            single_frequency: None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+/// RMS multipath over a range of elevations
+pub struct MultipathBin {
+    elevation: (i8, i8),
+    samples: usize,
+    rms: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Code multipath of a single satellite and signal
+pub struct MultipathData {
+    source: GnssSatellite,
+    channel: GnssFreq,
+    reference: Option<GnssFreq>,
+    arcs: usize,
+    samples: Vec<(DateTime<Utc>, i8, f64)>,
+    rms: f64,
+    bins: Vec<MultipathBin>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Code multipath of all satellites and signals over a session
+pub struct MultipathInfo {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    location: Geodetic,
+    elevation_bin: u8,
+    multipath: Vec<MultipathData>,
+}
+
+#[derive(Debug, Clone)]
+struct MpArc {
+    reference: Option<GnssFreq>,
+    last: DateTime<Utc>,
+    locktime: (u16, u16),
+    ambiguity: (PhaseAmbiguity, PhaseAmbiguity),
+    samples: Vec<(DateTime<Utc>, i8, f64)>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct MpSeries {
+    reference: Option<GnssFreq>,
+    arcs: usize,
+    samples: Vec<(DateTime<Utc>, i8, f64)>,
+}
+
+#[derive(Debug, Clone)]
+/// Code multipath (MP) estimator for site qualification.
+///
+/// For every signal with a valid pseudo-range and carrier phase, the
+/// code-minus-carrier combination
+///
+/// `MPi = Pi - Li - 2 fj² / (fi² - fj²) (Li - Lj)`
+///
+/// is formed with the carrier phase of a second signal `j` of the same
+/// satellite, which removes the geometry, clocks, troposphere and the
+/// first-order ionospheric divergence. The mean of each continuous arc,
+/// which holds the carrier phase ambiguities and hardware delays, is
+/// then removed, leaving the code multipath and noise of signal `i`.
+pub struct MultipathEstimator {
+    config: MultipathConfig,
+    arcs: HashMap<(GnssSatellite, GnssFreq), MpArc>,
+    series: HashMap<(GnssSatellite, GnssFreq), MpSeries>,
+    span: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    location: Geodetic,
+}
+
+impl MultipathEstimator {
+    /// Create a new multipath estimator
+    pub fn new(config: MultipathConfig) -> Self {
+        Self {
+            config,
+            arcs: HashMap::new(),
+            series: HashMap::new(),
+            span: None,
+            location: Default::default(),
+        }
+    }
+
+    /// Get the estimator configuration
+    pub fn config(&self) -> &MultipathConfig {
+        &self.config
+    }
+
+    /// Add an epoch of measurements to the estimator.
+    pub fn push(&mut self, info: &UbxGpsInfo) {
+        let tstamp = info.timestamp();
+        self.span = Some(match self.span {
+            Some((start, end)) => (start.min(tstamp), end.max(tstamp)),
+            None => (tstamp, tstamp),
+        });
+        self.location = info.location();
+        for (sat, ch) in info.carrier_phase() {
+            if self
+                .config
+                .min_elevation
+                .is_some_and(|min| ch.elevation < min)
+            {
+                continue;
+            }
+            for m in ch.meas.iter() {
+                let key = (*sat, m.channel);
+                let previous = self.arcs.get(&key).and_then(|arc| arc.reference);
+                let Some((mp, reference)) = self.combination(m, &ch.meas, previous) else {
+                    self.close(key);
+                    continue;
+                };
+                let locktime = (m.locktime, reference.map_or(0, |r| r.locktime));
+                let ambiguity = (
+                    m.ambiguity(),
+                    reference.map_or(PhaseAmbiguity::Invalid, |r| r.ambiguity()),
+                );
+                let reference = reference.map(|r| r.channel);
+                let broken = self.arcs.get(&key).is_some_and(|arc| {
+                    let gap = (tstamp - arc.last)
+                        .to_std()
+                        .map_or(true, |dt| dt.is_zero() || dt > self.config.max_gap);
+                    let jump = arc.samples.last().is_some_and(|(_, _, last)| {
+                        self.config
+                            .max_jump
+                            .is_some_and(|max| (mp - last).abs() > max)
+                    });
+                    gap || jump
+                        || arc.reference != reference
+                        || locktime.0 < arc.locktime.0
+                        || locktime.1 < arc.locktime.1
+                        || arc.ambiguity != ambiguity
+                });
+                if broken {
+                    self.close(key);
+                }
+                let arc = self.arcs.entry(key).or_insert_with(|| MpArc {
+                    reference,
+                    last: tstamp,
+                    locktime,
+                    ambiguity,
+                    samples: Vec::new(),
+                });
+                arc.last = tstamp;
+                arc.locktime = locktime;
+                arc.samples.push((tstamp, ch.elevation, mp));
+            }
+        }
+        // Close arcs that have not been updated recently
+        let max_gap = self.config.max_gap;
+        let stale: Vec<_> = self
+            .arcs
+            .iter()
+            .filter(|(_, arc)| (tstamp - arc.last).to_std().is_ok_and(|dt| dt > max_gap))
+            .map(|(key, _)| *key)
+            .collect();
+        stale.into_iter().for_each(|key| self.close(key));
+    }
+
+    /// Compute the multipath over all epochs added so far, and reset
+    /// the estimator.
+    pub fn finish(&mut self) -> Option<MultipathInfo> {
+        let keys: Vec<_> = self.arcs.keys().copied().collect();
+        keys.into_iter().for_each(|key| self.close(key));
+        let (start, end) = self.span.take()?;
+        let width = self.config.elevation_bin.max(1);
+        let mut multipath: Vec<_> = self
+            .series
+            .drain()
+            .map(|((source, channel), series)| MultipathData {
+                source,
+                channel,
+                reference: series.reference,
+                arcs: series.arcs,
+                rms: rms(series.samples.iter().map(|(_, _, mp)| *mp)),
+                bins: elevation_bins(series.samples.iter().map(|(_, el, mp)| (*el, *mp)), width),
+                samples: series.samples,
+            })
+            .collect();
+        if multipath.is_empty() {
+            return None;
+        }
+        multipath.sort_by_key(|a| (a.source, a.channel));
+        Some(MultipathInfo {
+            start,
+            end,
+            location: self.location,
+            elevation_bin: width,
+            multipath,
+        })
+    }
+
+    /// Compute the multipath over a series of measurement epochs.
+    pub fn from_series<'a, I: IntoIterator<Item = &'a UbxGpsInfo>>(
+        series: I,
+        config: MultipathConfig,
+    ) -> Option<MultipathInfo> {
+        let mut est = Self::new(config);
+        series.into_iter().for_each(|x| est.push(x));
+        est.finish()
+    }
+
+    /// Form the multipath combination of `meas`, using the carrier phase
+    /// of another signal of the same satellite to remove the ionosphere.
+    ///
+    /// The reference signal of the current arc is kept if available.
+    fn combination<'a>(
+        &self,
+        meas: &CarrierMeas,
+        all: &'a [CarrierMeas],
+        previous: Option<GnssFreq>,
+    ) -> Option<(f64, Option<&'a CarrierMeas>)> {
+        let (pr, _) = meas.pseudo_range?;
+        if meas.ambiguity() == PhaseAmbiguity::Invalid || !meas.channel.is_known() {
+            return None;
+        }
+        let fi = meas.channel.get_freq();
+        let li = meas.carrier_phase?.0 * SPEED_OF_LIGHT / fi;
+        let candidates = all.iter().filter(|r| {
+            r.ambiguity() != PhaseAmbiguity::Invalid
+                && r.channel.is_known()
+                && (r.channel.get_freq() - fi).abs() >= MIN_FREQ_SEPARATION
+        });
+        let reference = previous
+            .and_then(|prev| candidates.clone().find(|r| r.channel == prev))
+            .or_else(|| candidates.clone().next());
+        match reference {
+            Some(r) => {
+                let fj = r.channel.get_freq();
+                let lj = r.carrier_phase?.0 * SPEED_OF_LIGHT / fj;
+                let k = 2.0 * fj * fj / (fi * fi - fj * fj);
+                Some((pr - li - k * (li - lj), Some(r)))
+            }
+            None if self.config.single_frequency.is_some() => Some((pr - li, None)),
+            None => None,
+        }
+    }
+
+    /// Close the arc of a satellite and signal, removing its mean
+    fn close(&mut self, key: (GnssSatellite, GnssFreq)) {
+        let Some(arc) = self.arcs.remove(&key) else {
+            return;
+        };
+        if arc.samples.len() < self.config.min_arc.max(2) {
+            return;
+        }
+        let degree = match arc.reference {
+            Some(_) => 0,
+            None => self.config.single_frequency.unwrap_or_default(),
+        };
+        let t0 = arc.samples[0].0;
+        let x: Vec<_> = arc
+            .samples
+            .iter()
+            .map(|(t, _, _)| (*t - t0).num_milliseconds() as f64 * 1e-3)
+            .collect();
+        let y: Vec<_> = arc
+            .samples
+            .iter()
+            .map(|(_, _, mp)| Uncertain(*mp, 0.0))
+            .collect();
+        let Some(residuals) = detrend(&x, &y, degree) else {
+            return;
+        };
+        let series = self.series.entry(key).or_default();
+        series.reference = arc.reference;
+        series.arcs += 1;
+        series.samples.extend(
+            arc.samples
+                .iter()
+                .zip(residuals)
+                .map(|((t, el, _), mp)| (*t, *el, mp.0)),
+        );
+    }
+}
+
+/// Root mean square of a series
+fn rms<I: IntoIterator<Item = f64>>(values: I) -> f64 {
+    let (n, sum) = values
+        .into_iter()
+        .fold((0usize, 0.0), |(n, sum), x| (n + 1, sum + x * x));
+    if n == 0 {
+        return f64::NAN;
+    }
+    (sum / n as f64).sqrt()
+}
+
+/// RMS of a series of (elevation, value) samples in elevation bins of
+/// `width` degrees
+fn elevation_bins<I: IntoIterator<Item = (i8, f64)>>(samples: I, width: u8) -> Vec<MultipathBin> {
+    let width = width.max(1) as i16;
+    let mut bins: HashMap<i16, (usize, f64)> = HashMap::new();
+    for (el, mp) in samples {
+        let bin = bins.entry((el as i16).div_euclid(width)).or_default();
+        bin.0 += 1;
+        bin.1 += mp * mp;
+    }
+    let mut bins: Vec<_> = bins
+        .into_iter()
+        .map(|(bin, (n, sum))| MultipathBin {
+            elevation: (
+                (bin * width).clamp(-90, 90) as i8,
+                ((bin + 1) * width).clamp(-90, 90) as i8,
+            ),
+            samples: n,
+            rms: (sum / n as f64).sqrt(),
+        })
+        .collect();
+    bins.sort_by_key(|b| b.elevation);
+    bins
+}
+
+impl MultipathInfo {
+    /// Get the time of the first epoch
+    pub fn start(&self) -> DateTime<Utc> {
+        self.start
+    }
+
+    /// Get the time of the last epoch
+    pub fn end(&self) -> DateTime<Utc> {
+        self.end
+    }
+
+    /// Get the location of the receiver
+    pub fn location(&self) -> Geodetic {
+        self.location
+    }
+
+    /// Get the multipath of every satellite and signal
+    pub fn multipath(&self) -> &Vec<MultipathData> {
+        &self.multipath
+    }
+
+    /// Get the RMS multipath (m) as a function of elevation, combining
+    /// the satellites and signals selected by `filter`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let gps_l1 = info.by_elevation(|d| d.channel() == GpsFreq::L1CA.into());
+    /// ```
+    pub fn by_elevation<F: Fn(&MultipathData) -> bool>(&self, filter: F) -> Vec<MultipathBin> {
+        elevation_bins(
+            self.multipath
+                .iter()
+                .filter(|d| filter(d))
+                .flat_map(|d| d.samples.iter().map(|(_, el, mp)| (*el, *mp))),
+            self.elevation_bin,
+        )
+    }
+}
+
+impl MultipathData {
+    /// Get the satellite source of the multipath data
+    pub fn source(&self) -> GnssSatellite {
+        self.source
+    }
+
+    /// Get the signal whose code multipath is measured
+    pub fn channel(&self) -> GnssFreq {
+        self.channel
+    }
+
+    /// Get the signal used to remove the ionospheric divergence, if any.
+    ///
+    /// `None` for single-frequency signals, for which a polynomial is
+    /// removed from each arc instead.
+    pub fn reference(&self) -> Option<GnssFreq> {
+        self.reference
+    }
+
+    /// Get the number of continuous arcs
+    pub fn arcs(&self) -> usize {
+        self.arcs
+    }
+
+    /// Get the multipath samples as (time, elevation in degrees, MP in m)
+    pub fn samples(&self) -> &Vec<(DateTime<Utc>, i8, f64)> {
+        &self.samples
+    }
+
+    /// Get the RMS multipath over all elevations (m)
+    pub fn rms(&self) -> f64 {
+        self.rms
+    }
+
+    /// Get the RMS multipath in elevation bins
+    pub fn bins(&self) -> &Vec<MultipathBin> {
+        &self.bins
+    }
+}
+
+impl MultipathBin {
+    /// Get the elevation range of the bin (degrees), as `[min, max)`
+    pub fn elevation(&self) -> (i8, i8) {
+        self.elevation
+    }
+
+    /// Get the number of samples in the bin
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// Get the RMS multipath in the bin (m)
+    pub fn rms(&self) -> f64 {
+        self.rms
+    }
+}
+
+mod test {
+    #[test]
+    fn test_multipath() {
+        use super::*;
+        use crate::{ubx::UbxRxmRawx, GpsFreq, NmeaGpsInfo, NmeaMsgGroup, TrkStat};
+        use chrono::TimeDelta;
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let (f1, f2) = (
+            GnssFreq::from(GpsFreq::L1CA).get_freq(),
+            GnssFreq::from(GpsFreq::L2CL).get_freq(),
+        );
+        let gps = GnssSatellite::Gps(3);
+        let single = GnssSatellite::Gps(4);
+        let epochs = (0..600)
+            .map(|i| {
+                let time = start + TimeDelta::seconds(i);
+                let t = i as f64;
+                // Elevation rises from 10 to 69 degrees
+                let el = 10 + (i / 10) as i8;
+                let mut nmea = NmeaGpsInfo {
+                    time,
+                    ..Default::default()
+                };
+                nmea.sat_views.insert(gps, (el, 90));
+                nmea.sat_views.insert(single, (el, 270));
+                let range = 2.2e7 + 500.0 * t;
+                // Slowly growing slant ionosphere on L1 (m)
+                let iono = 5.0 + 0.01 * t;
+                // Code multipath on L1 of 1 m below 40 degrees, 0.1 m above
+                let mp = if el < 40 { 1.0 } else { 0.1 } * if i % 2 == 0 { 1.0 } else { -1.0 };
+                // Cycle slip on both signals half-way through
+                let (n1, n2, lock) = if i < 300 {
+                    (1000.0, -200.0, (i * 1000).min(64500) as u16)
+                } else {
+                    (1234.0, 55.0, ((i - 300) * 1000).min(64500) as u16)
+                };
+                let meas = |channel: GnssFreq, pr: f64, cp: f64| CarrierMeas {
+                    channel,
+                    pseudo_range: Some((pr, 0.1)),
+                    carrier_phase: Some((cp, 0.01)),
+                    doppler: (0.0, 0.0),
+                    locktime: lock,
+                    carrier_snr: 45,
+                    trk_stat: TrkStat::from(0x7),
+                };
+                let phase = |f: f64, iono: f64, n: f64| (range - iono) * f / SPEED_OF_LIGHT + n;
+                let iono2 = iono * f1 * f1 / (f2 * f2);
+                let rxm = UbxRxmRawx {
+                    timestamp: time,
+                    rcv_tow: 0.0,
+                    week: 0,
+                    leap_second: 0,
+                    receiver_status: Default::default(),
+                    version: 1,
+                    meas: [
+                        (
+                            gps,
+                            vec![
+                                meas(GpsFreq::L1CA.into(), range + iono + mp, phase(f1, iono, n1)),
+                                meas(GpsFreq::L2CL.into(), range + iono2, phase(f2, iono2, n2)),
+                            ],
+                        ),
+                        // Single frequency, ignored by default
+                        (
+                            single,
+                            vec![meas(
+                                GpsFreq::L1CA.into(),
+                                range + iono,
+                                phase(f1, iono, n1),
+                            )],
+                        ),
+                    ]
+                    .into_iter()
+                    .collect(),
+                };
+                UbxGpsInfo::new(nmea, Some(rxm), NmeaMsgGroup(Default::default()))
+            })
+            .collect::<Vec<_>>();
+        let info = MultipathEstimator::from_series(&epochs, MultipathConfig::default()).unwrap();
+        assert_eq!(info.start(), start);
+        assert_eq!(info.multipath().len(), 2);
+        let mp1 = &info.multipath()[0];
+        assert_eq!((mp1.source(), mp1.channel()), (gps, GpsFreq::L1CA.into()));
+        assert_eq!(mp1.reference(), Some(GpsFreq::L2CL.into()));
+        assert_eq!(mp1.arcs(), 2);
+        assert_eq!(mp1.samples().len(), 600);
+        assert_eq!(mp1.bins().len(), 12);
+        for bin in mp1.bins() {
+            let expected = if bin.elevation().0 < 40 { 1.0 } else { 0.1 };
+            assert_eq!(bin.samples(), 50);
+            assert!((bin.rms() - expected).abs() < 0.05, "{:?}", bin);
+        }
+        let mp2 = &info.multipath()[1];
+        assert_eq!(mp2.channel(), GpsFreq::L2CL.into());
+        // L2 code is clean
+        assert!(mp2.rms() < 0.05);
+        let low = info.by_elevation(|d| d.channel() == GpsFreq::L1CA.into());
+        assert_eq!(low.len(), 12);
+        // Single frequency signals with a quadratic removed
+        let info = MultipathEstimator::from_series(
+            &epochs,
+            MultipathConfig {
+                single_frequency: Some(2),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let sf = info
+            .multipath()
+            .iter()
+            .find(|d| d.source() == single)
+            .unwrap();
+        assert_eq!(sf.reference(), None);
+        assert_eq!(sf.arcs(), 2);
+        assert!(sf.rms() < 1e-3);
+    }
+}
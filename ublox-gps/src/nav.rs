@@ -0,0 +1,470 @@
+use std::{collections::HashMap, f64::consts::PI};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ephemeris::Ephemeris,
+    iono::Klobuchar,
+    nmea::GnssSatellite,
+    ubx::{UbxFormat, UbxMessage},
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// UBX RXM-SFRBX message
+pub struct UbxRxmSfrbx {
+    /// Source satellite
+    pub satellite: GnssSatellite,
+    /// Signal identifier
+    pub sig_id: u8,
+    /// GLONASS frequency slot + 7 (0 - 13)
+    pub freq_id: u8,
+    /// Tracking channel number
+    pub channel: u8,
+    /// Message version (0x1 or 0x2)
+    pub version: u8,
+    /// Navigation data words
+    pub words: Vec<u32>,
+}
+
+impl UbxFormat for UbxRxmSfrbx {
+    fn from_message(message: UbxMessage) -> Result<Self, &'static str>
+    where
+        Self: Sized,
+    {
+        if message.class != 0x2 {
+            return Err("Invalid UBX message class");
+        }
+        if message.id != 0x13 {
+            return Err("Invalid UBX message ID");
+        }
+        if message.payload.len() < 8 {
+            return Err("Invalid UBX message length, malformed message");
+        }
+        let num_words = message.payload[4] as usize;
+        if message.payload.len() != 8 + 4 * num_words {
+            return Err("Invalid number of words, malformed message");
+        }
+        let gnss_id = message.payload[0];
+        if !matches!(gnss_id, 0 | 1 | 2 | 3 | 5 | 6) {
+            return Err("Unsupported GNSS ID");
+        }
+        let words = message.payload[8..]
+            .chunks_exact(4)
+            .map(|x| u32::from_le_bytes([x[0], x[1], x[2], x[3]]))
+            .collect();
+        Ok(UbxRxmSfrbx {
+            satellite: GnssSatellite::from_ubx(gnss_id, message.payload[1])
+                .ok_or("Invalid satellite ID")?,
+            sig_id: message.payload[2],
+            freq_id: message.payload[3],
+            channel: message.payload[5],
+            version: message.payload[6],
+            words,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+/// Broadcast navigation data decoded from UBX-RXM-SFRBX messages.
+///
+/// Decodes the ionospheric correction parameters broadcast in the
+/// GPS LNAV (subframe 4, page 18) and Galileo I/NAV (word type 5)
+/// navigation messages, and the ephemerides broadcast in the GPS and
+/// QZSS LNAV (subframes 1 - 3) and Galileo I/NAV (word types 1 - 5)
+/// navigation messages.
+pub struct NavStore {
+    klobuchar: Option<Klobuchar>,
+    nequick: Option<[f64; 3]>,
+    #[serde(default)]
+    ephemeris: HashMap<GnssSatellite, Ephemeris>,
+    /// Latest subframes or words of each type, per satellite
+    #[serde(skip)]
+    pages: HashMap<(GnssSatellite, u8), Vec<u8>>,
+}
+
+impl NavStore {
+    /// Create an empty navigation data store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Update the store with a navigation data subframe.
+    ///
+    /// # Returns
+    /// - `true` if the subframe contained data that updated the store
+    pub fn update(&mut self, msg: &UbxRxmSfrbx) -> bool {
+        match msg.satellite {
+            GnssSatellite::Gps(_) | GnssSatellite::Qzss(_)
+                if msg.sig_id == 0 && msg.words.len() == 10 =>
+            {
+                self.update_gps_lnav(msg.satellite, &msg.words)
+            }
+            GnssSatellite::Galileo(_) if msg.words.len() == 8 => {
+                self.update_gal_inav(msg.satellite, &msg.words)
+            }
+            _ => false,
+        }
+    }
+
+    /// Update the store with a sequence of navigation data subframes.
+    pub fn extend<'a, I: IntoIterator<Item = &'a UbxRxmSfrbx>>(&mut self, msgs: I) {
+        for msg in msgs {
+            self.update(msg);
+        }
+    }
+
+    /// Get the latest GPS Klobuchar ionospheric model
+    pub fn klobuchar(&self) -> Option<Klobuchar> {
+        self.klobuchar
+    }
+
+    /// Get the latest Galileo NeQuick-G effective ionisation level
+    /// coefficients (ai0, ai1, ai2)
+    pub fn nequick(&self) -> Option<[f64; 3]> {
+        self.nequick
+    }
+
+    /// Get the latest broadcast ephemeris of a satellite
+    pub fn ephemeris(&self, sat: &GnssSatellite) -> Option<&Ephemeris> {
+        self.ephemeris.get(sat)
+    }
+
+    /// Add an ephemeris obtained from another source, replacing the
+    /// ephemeris of the same satellite
+    pub fn insert_ephemeris(&mut self, eph: Ephemeris) {
+        self.ephemeris.insert(eph.satellite, eph);
+    }
+
+    /// Get the latest broadcast ephemerides of all satellites
+    pub fn ephemerides(&self) -> &HashMap<GnssSatellite, Ephemeris> {
+        &self.ephemeris
+    }
+
+    fn update_gps_lnav(&mut self, sat: GnssSatellite, words: &[u32]) -> bool {
+        // 24 data bits of each 30-bit word, parity removed
+        let mut buf = [0u8; 30];
+        for (i, word) in words.iter().enumerate() {
+            setbitu(&mut buf, 24 * i, 24, (word >> 6) & 0xFF_FFFF);
+        }
+        if getbitu(&buf, 0, 8) != 0x8B {
+            return false;
+        }
+        let subframe = getbitu(&buf, 43, 3);
+        if (1..=3).contains(&subframe) {
+            self.pages.insert((sat, subframe as u8), buf.to_vec());
+            return self.decode_lnav_ephemeris(sat);
+        }
+        let svid = getbitu(&buf, 50, 6);
+        if subframe != 4 || svid != 56 || !matches!(sat, GnssSatellite::Gps(_)) {
+            return false;
+        }
+        let alpha = [
+            getbits(&buf, 56, 8) as f64 * 2f64.powi(-30),
+            getbits(&buf, 64, 8) as f64 * 2f64.powi(-27),
+            getbits(&buf, 72, 8) as f64 * 2f64.powi(-24),
+            getbits(&buf, 80, 8) as f64 * 2f64.powi(-24),
+        ];
+        let beta = [
+            getbits(&buf, 88, 8) as f64 * 2f64.powi(11),
+            getbits(&buf, 96, 8) as f64 * 2f64.powi(14),
+            getbits(&buf, 104, 8) as f64 * 2f64.powi(16),
+            getbits(&buf, 112, 8) as f64 * 2f64.powi(16),
+        ];
+        self.klobuchar = Some(Klobuchar::new(alpha, beta));
+        true
+    }
+
+    fn decode_lnav_ephemeris(&mut self, sat: GnssSatellite) -> bool {
+        let (Some(sf1), Some(sf2), Some(sf3)) = (
+            self.pages.get(&(sat, 1)),
+            self.pages.get(&(sat, 2)),
+            self.pages.get(&(sat, 3)),
+        ) else {
+            return false;
+        };
+        let iodc = (getbitu(sf1, 70, 2) << 8) | getbitu(sf1, 168, 8);
+        let iode = getbitu(sf2, 48, 8);
+        if iode != getbitu(sf3, 216, 8) || iode != iodc & 0xFF {
+            return false;
+        }
+        let eph = Ephemeris {
+            satellite: sat,
+            iode: iode as u16,
+            week: getbitu(sf1, 48, 10) as u16,
+            health: getbitu(sf1, 64, 6) as u8,
+            tgd: getbits(sf1, 160, 8) as f64 * 2f64.powi(-31),
+            toc: getbitu(sf1, 176, 16) as f64 * 16.0,
+            af2: getbits(sf1, 192, 8) as f64 * 2f64.powi(-55),
+            af1: getbits(sf1, 200, 16) as f64 * 2f64.powi(-43),
+            af0: getbits(sf1, 216, 22) as f64 * 2f64.powi(-31),
+            crs: getbits(sf2, 56, 16) as f64 * 2f64.powi(-5),
+            delta_n: getbits(sf2, 72, 16) as f64 * 2f64.powi(-43) * PI,
+            m0: getbits(sf2, 88, 32) as f64 * 2f64.powi(-31) * PI,
+            cuc: getbits(sf2, 120, 16) as f64 * 2f64.powi(-29),
+            e: getbitu(sf2, 136, 32) as f64 * 2f64.powi(-33),
+            cus: getbits(sf2, 168, 16) as f64 * 2f64.powi(-29),
+            sqrt_a: getbitu(sf2, 184, 32) as f64 * 2f64.powi(-19),
+            toe: getbitu(sf2, 216, 16) as f64 * 16.0,
+            cic: getbits(sf3, 48, 16) as f64 * 2f64.powi(-29),
+            omega0: getbits(sf3, 64, 32) as f64 * 2f64.powi(-31) * PI,
+            cis: getbits(sf3, 96, 16) as f64 * 2f64.powi(-29),
+            i0: getbits(sf3, 112, 32) as f64 * 2f64.powi(-31) * PI,
+            crc: getbits(sf3, 144, 16) as f64 * 2f64.powi(-5),
+            omega: getbits(sf3, 160, 32) as f64 * 2f64.powi(-31) * PI,
+            omega_dot: getbits(sf3, 192, 24) as f64 * 2f64.powi(-43) * PI,
+            idot: getbits(sf3, 224, 14) as f64 * 2f64.powi(-43) * PI,
+        };
+        self.ephemeris.insert(sat, eph);
+        true
+    }
+
+    fn decode_inav_ephemeris(&mut self, sat: GnssSatellite) -> bool {
+        let (Some(w1), Some(w2), Some(w3), Some(w4)) = (
+            self.pages.get(&(sat, 1)),
+            self.pages.get(&(sat, 2)),
+            self.pages.get(&(sat, 3)),
+            self.pages.get(&(sat, 4)),
+        ) else {
+            return false;
+        };
+        let iod = getbitu(w1, 6, 10);
+        if [w2, w3, w4].iter().any(|w| getbitu(w, 6, 10) != iod) {
+            return false;
+        }
+        // Group delay and E1-B signal health from word type 5, if available
+        let (tgd, health) = self
+            .pages
+            .get(&(sat, 5))
+            .map(|w5| {
+                (
+                    getbits(w5, 57, 10) as f64 * 2f64.powi(-32),
+                    getbitu(w5, 69, 2) as u8,
+                )
+            })
+            .unwrap_or_default();
+        let eph = Ephemeris {
+            satellite: sat,
+            iode: iod as u16,
+            week: self
+                .pages
+                .get(&(sat, 5))
+                .map(|w5| getbitu(w5, 73, 12) as u16)
+                .unwrap_or_default(),
+            health,
+            tgd,
+            toe: getbitu(w1, 16, 14) as f64 * 60.0,
+            m0: getbits(w1, 30, 32) as f64 * 2f64.powi(-31) * PI,
+            e: getbitu(w1, 62, 32) as f64 * 2f64.powi(-33),
+            sqrt_a: getbitu(w1, 94, 32) as f64 * 2f64.powi(-19),
+            omega0: getbits(w2, 16, 32) as f64 * 2f64.powi(-31) * PI,
+            i0: getbits(w2, 48, 32) as f64 * 2f64.powi(-31) * PI,
+            omega: getbits(w2, 80, 32) as f64 * 2f64.powi(-31) * PI,
+            idot: getbits(w2, 112, 14) as f64 * 2f64.powi(-43) * PI,
+            omega_dot: getbits(w3, 16, 24) as f64 * 2f64.powi(-43) * PI,
+            delta_n: getbits(w3, 40, 16) as f64 * 2f64.powi(-43) * PI,
+            cuc: getbits(w3, 56, 16) as f64 * 2f64.powi(-29),
+            cus: getbits(w3, 72, 16) as f64 * 2f64.powi(-29),
+            crc: getbits(w3, 88, 16) as f64 * 2f64.powi(-5),
+            crs: getbits(w3, 104, 16) as f64 * 2f64.powi(-5),
+            cic: getbits(w4, 22, 16) as f64 * 2f64.powi(-29),
+            cis: getbits(w4, 38, 16) as f64 * 2f64.powi(-29),
+            toc: getbitu(w4, 54, 14) as f64 * 60.0,
+            af0: getbits(w4, 68, 31) as f64 * 2f64.powi(-34),
+            af1: getbits(w4, 99, 21) as f64 * 2f64.powi(-46),
+            af2: getbits(w4, 120, 6) as f64 * 2f64.powi(-59),
+        };
+        self.ephemeris.insert(sat, eph);
+        true
+    }
+
+    fn update_gal_inav(&mut self, sat: GnssSatellite, words: &[u32]) -> bool {
+        let mut buf = [0u8; 32];
+        for (i, word) in words.iter().enumerate() {
+            setbitu(&mut buf, 32 * i, 32, *word);
+        }
+        // Even page part followed by odd page part, nominal pages only
+        if getbitu(&buf, 0, 1) != 0
+            || getbitu(&buf, 1, 1) != 0
+            || getbitu(&buf, 128, 1) != 1
+            || getbitu(&buf, 129, 1) != 0
+        {
+            return false;
+        }
+        // 128-bit word: 112 bits from the even part, 16 bits from the odd part
+        let mut word = [0u8; 16];
+        for (i, byte) in word.iter_mut().enumerate() {
+            let pos = if i < 14 {
+                2 + 8 * i
+            } else {
+                130 + 8 * (i - 14)
+            };
+            *byte = getbitu(&buf, pos, 8) as u8;
+        }
+        let word_type = getbitu(&word, 0, 6);
+        if !(1..=5).contains(&word_type) {
+            return false;
+        }
+        self.pages.insert((sat, word_type as u8), word.to_vec());
+        if word_type != 5 {
+            return self.decode_inav_ephemeris(sat);
+        }
+        if let Some(eph) = self.ephemeris.get_mut(&sat) {
+            eph.tgd = getbits(&word, 57, 10) as f64 * 2f64.powi(-32);
+            eph.health = getbitu(&word, 69, 2) as u8;
+            eph.week = getbitu(&word, 73, 12) as u16;
+        }
+        self.nequick = Some([
+            getbitu(&word, 6, 11) as f64 * 2f64.powi(-2),
+            getbits(&word, 17, 11) as f64 * 2f64.powi(-8),
+            getbits(&word, 28, 14) as f64 * 2f64.powi(-15),
+        ]);
+        true
+    }
+}
+
+/// Extract an unsigned bit field from a big-endian bit buffer
+pub(crate) fn getbitu(buf: &[u8], pos: usize, len: usize) -> u32 {
+    (pos..pos + len).fold(0, |acc, i| {
+        (acc << 1) | ((buf[i / 8] >> (7 - i % 8)) & 1) as u32
+    })
+}
+
+/// Extract a two's complement signed bit field from a big-endian bit buffer
+pub(crate) fn getbits(buf: &[u8], pos: usize, len: usize) -> i32 {
+    let bits = getbitu(buf, pos, len);
+    if len == 0 || len >= 32 || bits & (1 << (len - 1)) == 0 {
+        bits as i32
+    } else {
+        (bits | (!0u32 << len)) as i32
+    }
+}
+
+/// Set a bit field in a big-endian bit buffer
+fn setbitu(buf: &mut [u8], pos: usize, len: usize, data: u32) {
+    for i in 0..len {
+        let bit = (data >> (len - 1 - i)) & 1;
+        let idx = pos + i;
+        if bit == 1 {
+            buf[idx / 8] |= 1 << (7 - idx % 8);
+        } else {
+            buf[idx / 8] &= !(1 << (7 - idx % 8));
+        }
+    }
+}
+
+mod test {
+    #[test]
+    fn test_iono_params() {
+        use super::*;
+        // GPS LNAV subframe 4, page 18
+        let mut buf = [0u8; 30];
+        setbitu(&mut buf, 0, 8, 0x8B);
+        setbitu(&mut buf, 43, 3, 4);
+        setbitu(&mut buf, 50, 6, 56);
+        for (i, v) in [0x0Bi32, 0x11, -0x13, 0x12, 0x48, -0x20, 0x01, 0x07]
+            .iter()
+            .enumerate()
+        {
+            setbitu(&mut buf, 56 + 8 * i, 8, *v as u32 & 0xFF);
+        }
+        let words = (0..10)
+            .map(|i| getbitu(&buf, 24 * i, 24) << 6)
+            .collect::<Vec<_>>();
+        let mut store = NavStore::new();
+        assert!(store.update(&UbxRxmSfrbx {
+            satellite: GnssSatellite::Gps(3),
+            sig_id: 0,
+            freq_id: 0,
+            channel: 1,
+            version: 2,
+            words,
+        }));
+        let klob = store.klobuchar().unwrap();
+        assert_eq!(klob.alpha()[0], 11.0 * 2f64.powi(-30));
+        assert_eq!(klob.alpha()[2], -19.0 * 2f64.powi(-24));
+        assert_eq!(klob.beta()[1], -32.0 * 2f64.powi(14));
+
+        // Galileo I/NAV word type 5
+        let mut buf = [0u8; 32];
+        setbitu(&mut buf, 2, 6, 5);
+        setbitu(&mut buf, 8, 11, 250);
+        setbitu(&mut buf, 19, 11, (-12i32 as u32) & 0x7FF);
+        setbitu(&mut buf, 30, 14, 400);
+        setbitu(&mut buf, 128, 1, 1);
+        let words = (0..8)
+            .map(|i| getbitu(&buf, 32 * i, 32))
+            .collect::<Vec<_>>();
+        assert!(store.update(&UbxRxmSfrbx {
+            satellite: GnssSatellite::Galileo(5),
+            sig_id: 1,
+            freq_id: 0,
+            channel: 2,
+            version: 2,
+            words,
+        }));
+        assert_eq!(
+            store.nequick().unwrap(),
+            [62.5, -12.0 / 256.0, 400.0 / 32768.0]
+        );
+    }
+
+    #[test]
+    fn test_ephemeris() {
+        use super::*;
+        // GPS LNAV subframes 1 - 3
+        let subframe = |id: u32, fields: &[(usize, usize, i64)]| {
+            let mut buf = [0u8; 30];
+            setbitu(&mut buf, 0, 8, 0x8B);
+            setbitu(&mut buf, 43, 3, id);
+            for (pos, len, v) in fields {
+                setbitu(&mut buf, *pos, *len, (*v as u32) & (u32::MAX >> (32 - len)));
+            }
+            UbxRxmSfrbx {
+                satellite: GnssSatellite::Gps(7),
+                sig_id: 0,
+                freq_id: 0,
+                channel: 1,
+                version: 2,
+                words: (0..10).map(|i| getbitu(&buf, 24 * i, 24) << 6).collect(),
+            }
+        };
+        let sf1 = subframe(
+            1,
+            &[
+                (48, 10, 276),
+                (168, 8, 0xA5),
+                (160, 8, -3),
+                (176, 16, 21600),
+                (216, 22, -1000),
+            ],
+        );
+        let sf2 = subframe(
+            2,
+            &[
+                (48, 8, 0xA5),
+                (136, 32, 85_899_346),
+                (184, 32, 2_702_023_066),
+                (216, 16, 21600),
+            ],
+        );
+        let sf3 = |iode| subframe(3, &[(112, 32, 644_245_094), (216, 8, iode), (224, 14, -5)]);
+        let mut store = NavStore::new();
+        assert!(!store.update(&sf1));
+        assert!(!store.update(&sf2));
+        assert!(!store.update(&sf3(0xA6)));
+        assert!(store.ephemeris(&GnssSatellite::Gps(7)).is_none());
+        assert!(store.update(&sf3(0xA5)));
+        let eph = store.ephemeris(&GnssSatellite::Gps(7)).unwrap();
+        assert_eq!(eph.week, 276);
+        assert_eq!(eph.iode, 0xA5);
+        assert_eq!(eph.toe, 345_600.0);
+        assert_eq!(eph.toc, 345_600.0);
+        assert_eq!(eph.tgd, -3.0 * 2f64.powi(-31));
+        assert_eq!(eph.af0, -1000.0 * 2f64.powi(-31));
+        assert!((eph.e - 0.01).abs() < 1e-9);
+        assert!((eph.sqrt_a - 5153.7).abs() < 1e-5);
+        assert!((eph.i0 - 0.3 * PI).abs() < 1e-9);
+        assert_eq!(eph.idot, -5.0 * 2f64.powi(-43) * PI);
+        assert!(eph.is_healthy());
+    }
+}
@@ -0,0 +1,646 @@
+use std::{f64::consts::PI, path::Path, sync::Arc};
+
+use chrono::{DateTime, Datelike, Timelike, Utc};
+
+use crate::{
+    geo::Geodetic,
+    iono::{pierce_point, IonoModel},
+};
+
+/// Earth radius used by NeQuick (km)
+const EARTH_RADIUS: f64 = 6371.2;
+/// Height of the E layer peak (km)
+const HM_E: f64 = 120.0;
+/// Solar zenith angle at night-time (degrees)
+const CHI_0: f64 = 86.23292796211615;
+/// Maximum recursion depth of the adaptive integration
+const MAX_LEVEL: usize = 50;
+
+/// Number of values in a MODIP grid file
+const MODIP_LEN: usize = 39 * 39;
+/// Number of values in a CCIR coefficient file
+const CCIR_LEN: usize = 2 * 76 * 13 + 2 * 49 * 9;
+
+/// Gauss-Kronrod G7-K15 abscissae
+const XK: [f64; 8] = [
+    0.9914553711208126,
+    0.9491079123427585,
+    0.8648644233597691,
+    0.7415311855993945,
+    0.5860872354676911,
+    0.4058451513773972,
+    0.20778495500789848,
+    0.0,
+];
+/// Kronrod K15 weights
+const WK: [f64; 8] = [
+    0.022935322010529224,
+    0.06309209262997856,
+    0.10479001032225019,
+    0.14065325971552592,
+    0.1690047266392679,
+    0.19035057806478542,
+    0.20443294007529889,
+    0.20948214108472782,
+];
+/// Gauss G7 weights, for the odd Kronrod abscissae
+const WG: [f64; 4] = [
+    0.1294849661688697,
+    0.27970539148927664,
+    0.3818300505051189,
+    0.4179591836734694,
+];
+
+#[derive(Debug, Clone)]
+/// Climatological data required by the NeQuick-G model: the modified dip
+/// latitude (MODIP) grid and the ITU-R (CCIR) foF2 and M(3000)F2 maps.
+///
+/// These are distributed with the Galileo ionospheric correction algorithm
+/// reference implementation as `modipNeQG_wrapped.asc` and
+/// `ccir11.asc` to `ccir22.asc`.
+pub struct NequickData {
+    modip: Vec<f64>,
+    ccir: Vec<Vec<f64>>,
+}
+
+impl NequickData {
+    /// Create the NeQuick-G data from the MODIP grid and CCIR coefficients.
+    ///
+    /// # Arguments
+    /// - `modip`: Wrapped MODIP grid, 39 rows of 39 values
+    /// - `ccir`: CCIR coefficients for each month, starting with January
+    pub fn new(modip: Vec<f64>, ccir: Vec<Vec<f64>>) -> Result<Self, &'static str> {
+        if modip.len() != MODIP_LEN {
+            return Err("Invalid MODIP grid size");
+        }
+        if ccir.len() != 12 || ccir.iter().any(|x| x.len() != CCIR_LEN) {
+            return Err("Invalid CCIR coefficient size");
+        }
+        Ok(Self { modip, ccir })
+    }
+
+    /// Load the NeQuick-G data from the reference implementation data files
+    /// in a directory.
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> Result<Self, std::io::Error> {
+        let dir = dir.as_ref();
+        let modip = read_values(&dir.join("modipNeQG_wrapped.asc"))?;
+        let ccir = (11..23)
+            .map(|i| read_values(&dir.join(format!("ccir{}.asc", i))))
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::new(modip, ccir).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    /// Get the modified dip latitude (degrees) at a location.
+    pub fn modip(&self, lat: f64, lon: f64) -> f64 {
+        if lat <= -90.0 {
+            return -90.0;
+        }
+        if lat >= 90.0 {
+            return 90.0;
+        }
+        let lng1 = (lon + 180.0) / 10.0;
+        let mut sj = lng1.floor() as i64 - 2;
+        let dj = lng1 - lng1.floor();
+        if sj < 0 {
+            sj += 36;
+        }
+        if sj > 33 {
+            sj -= 36;
+        }
+        let lat1 = (lat + 90.0) / 5.0 + 1.0;
+        let si = (lat1 - 1e-6).floor() as i64 - 2;
+        let di = lat1 - si as f64 - 2.0;
+        let mut z = [0.0; 4];
+        for (k, z) in z.iter_mut().enumerate() {
+            let row = (si + k as i64 + 1) as usize;
+            let mut zj = [0.0; 4];
+            for (j, zj) in zj.iter_mut().enumerate() {
+                let col = (sj + j as i64 + 1) as usize;
+                *zj = self.modip[row * 39 + col];
+            }
+            *z = interpolate(&zj, dj);
+        }
+        interpolate(&z, di)
+    }
+}
+
+fn read_values(path: &Path) -> Result<Vec<f64>, std::io::Error> {
+    std::fs::read_to_string(path)?
+        .split_whitespace()
+        .map(|x| x.replace(['D', 'd'], "E").parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+/// Third-order interpolation between `z[1]` and `z[2]`
+fn interpolate(z: &[f64; 4], x: f64) -> f64 {
+    if (2.0 * x).abs() < 1e-10 {
+        return z[1];
+    }
+    let delta = 2.0 * x - 1.0;
+    let g1 = z[2] + z[1];
+    let g2 = z[2] - z[1];
+    let g3 = z[3] + z[0];
+    let g4 = (z[3] - z[0]) / 3.0;
+    let a0 = 9.0 * g1 - g3;
+    let a1 = 9.0 * g2 - g4;
+    let a2 = g3 - g1;
+    let a3 = g4 - g2;
+    (a0 + delta * (a1 + delta * (a2 + delta * a3))) / 16.0
+}
+
+fn clip_exp(x: f64) -> f64 {
+    if x > 80.0 {
+        5.5406e34
+    } else if x < -80.0 {
+        1.8049e-35
+    } else {
+        x.exp()
+    }
+}
+
+/// Smoothly join `f1` (for `x` > 0) and `f2` (for `x` < 0)
+fn join(f1: f64, f2: f64, alpha: f64, x: f64) -> f64 {
+    let ee = clip_exp(alpha * x);
+    (f1 * ee + f2) / (ee + 1.0)
+}
+
+/// Epstein function
+fn epstein(x: f64, y: f64, z: f64, w: f64) -> f64 {
+    let ee = clip_exp((w - y) / z);
+    x * ee / ((1.0 + ee) * (1.0 + ee))
+}
+
+#[derive(Debug, Clone)]
+/// Galileo NeQuick-G ionospheric model, as specified in the European GNSS
+/// (Galileo) Open Service Ionospheric Correction Algorithm for Galileo
+/// Single Frequency Users, issue 1.2.
+pub struct NeQuickG {
+    data: Arc<NequickData>,
+    coeffs: [f64; 3],
+    sat_height: f64,
+}
+
+/// Time and solar activity dependent model parameters
+struct Context {
+    month: u32,
+    ut: f64,
+    az: f64,
+    az_r: f64,
+    sin_delta: f64,
+    cos_delta: f64,
+    cf2: [f64; 76],
+    cm3: [f64; 49],
+}
+
+impl NeQuickG {
+    /// Create a NeQuick-G model.
+    ///
+    /// # Arguments
+    /// - `data`: MODIP grid and CCIR maps
+    /// - `coeffs`: Broadcast effective ionisation level coefficients (ai0, ai1, ai2)
+    pub fn new(data: Arc<NequickData>, coeffs: [f64; 3]) -> Self {
+        Self {
+            data,
+            coeffs,
+            sat_height: 20_200_000.0,
+        }
+    }
+
+    /// Set the satellite height (m) used to terminate the line of sight
+    /// when the satellite is given by azimuth and elevation. Defaults to
+    /// the GPS orbital height.
+    pub fn with_satellite_height(mut self, height: f64) -> Self {
+        self.sat_height = height;
+        self
+    }
+
+    /// Get the effective ionisation level coefficients
+    pub fn coeffs(&self) -> [f64; 3] {
+        self.coeffs
+    }
+
+    /// Get the slant TEC (TECU) between two points.
+    ///
+    /// # Arguments
+    /// - `time`: Time of the observation
+    /// - `rx`: Receiver (latitude, longitude, height) in degrees and m
+    /// - `sat`: Satellite (latitude, longitude, height) in degrees and m
+    pub fn integrate(&self, time: DateTime<Utc>, rx: (f64, f64, f64), sat: (f64, f64, f64)) -> f64 {
+        let ctx = self.context(time, rx.0, rx.1);
+        let p1 = to_cartesian(rx.0, rx.1, EARTH_RADIUS + rx.2 * 1e-3);
+        let p2 = to_cartesian(sat.0, sat.1, EARTH_RADIUS + sat.2 * 1e-3);
+        self.integrate_ray(&ctx, p1, p2)
+    }
+
+    fn context(&self, time: DateTime<Utc>, lat: f64, lon: f64) -> Context {
+        let month = time.month();
+        let ut = time.hour() as f64
+            + time.minute() as f64 / 60.0
+            + (time.second() as f64 + time.nanosecond() as f64 * 1e-9) / 3600.0;
+        // Effective ionisation level
+        let modip = self.data.modip(lat, lon);
+        let [a0, a1, a2] = self.coeffs;
+        let az = if a0 == 0.0 && a1 == 0.0 && a2 == 0.0 {
+            63.7
+        } else {
+            (a0 + a1 * modip + a2 * modip * modip).clamp(0.0, 400.0)
+        };
+        let az_r = (167273.0 + (az - 63.7) * 1123.6).sqrt() - 408.99;
+        // Solar declination
+        let t = 30.5 * month as f64 - 15.0 + (18.0 - ut) / 24.0;
+        let am = (0.9856 * t - 3.289).to_radians();
+        let al = am + (1.916 * am.sin() + 0.020 * (2.0 * am).sin() + 282.634).to_radians();
+        let sin_delta = 0.39782 * al.sin();
+        let cos_delta = (1.0 - sin_delta * sin_delta).sqrt();
+        // CCIR maps interpolated for solar activity and time of day
+        let ccir = &self.data.ccir[month as usize - 1];
+        let (f2, fm3) = ccir.split_at(2 * 76 * 13);
+        let w = az_r / 100.0;
+        let tt = (15.0 * ut - 180.0).to_radians();
+        let mut cf2 = [0.0; 76];
+        for (i, cf2) in cf2.iter_mut().enumerate() {
+            let af2 = |j: usize| f2[i * 13 + j] * (1.0 - w) + f2[(76 + i) * 13 + j] * w;
+            *cf2 = af2(0);
+            for k in 1..7 {
+                let kt = k as f64 * tt;
+                *cf2 += af2(2 * k - 1) * kt.sin() + af2(2 * k) * kt.cos();
+            }
+        }
+        let mut cm3 = [0.0; 49];
+        for (i, cm3) in cm3.iter_mut().enumerate() {
+            let am3 = |j: usize| fm3[i * 9 + j] * (1.0 - w) + fm3[(49 + i) * 9 + j] * w;
+            *cm3 = am3(0);
+            for k in 1..5 {
+                let kt = k as f64 * tt;
+                *cm3 += am3(2 * k - 1) * kt.sin() + am3(2 * k) * kt.cos();
+            }
+        }
+        Context {
+            month,
+            ut,
+            az,
+            az_r,
+            sin_delta,
+            cos_delta,
+            cf2,
+            cm3,
+        }
+    }
+
+    /// Electron density (m⁻³) at a point
+    fn density(&self, ctx: &Context, lat: f64, lon: f64, h: f64) -> f64 {
+        let modip = self.data.modip(lat, lon);
+        let (sin_lat, cos_lat) = lat.to_radians().sin_cos();
+        // Effective solar zenith angle
+        let lt = ctx.ut + lon / 15.0;
+        let cos_chi =
+            sin_lat * ctx.sin_delta + cos_lat * ctx.cos_delta * (PI / 12.0 * (12.0 - lt)).cos();
+        let chi = (1.0 - cos_chi * cos_chi)
+            .max(0.0)
+            .sqrt()
+            .atan2(cos_chi)
+            .to_degrees();
+        let chi_eff = join(
+            90.0 - 0.24 * clip_exp(20.0 - 0.2 * chi),
+            chi,
+            12.0,
+            chi - CHI_0,
+        );
+        // E layer
+        let seas = match ctx.month {
+            1 | 2 | 11 | 12 => -1.0,
+            3 | 4 | 9 | 10 => 0.0,
+            _ => 1.0,
+        };
+        let ee = clip_exp(0.3 * lat);
+        let seasp = seas * (ee - 1.0) / (ee + 1.0);
+        let fo_e = ((1.112 - 0.019 * seasp).powi(2)
+            * ctx.az.sqrt()
+            * chi_eff.to_radians().cos().max(0.0).powf(0.6)
+            + 0.49)
+            .sqrt();
+        let nm_e = 0.124 * fo_e * fo_e;
+        // F2 layer critical frequency and propagation factor
+        let (fo_f2, m3000) = self.legendre(ctx, modip, lat, lon);
+        let nm_f2 = 0.124 * fo_f2 * fo_f2;
+        // F1 layer
+        let mut fo_f1 = if fo_e >= 2.0 { 1.4 * fo_e } else { 0.0 };
+        if fo_f1 > 0.85 * fo_f2 {
+            fo_f1 = 0.85 * fo_f2;
+        }
+        if fo_f1 < 1e-6 {
+            fo_f1 = 0.0;
+        }
+        let nm_f1 = if fo_f1 <= 0.0 && fo_e > 2.0 {
+            0.124 * (fo_e + 0.5).powi(2)
+        } else {
+            0.124 * fo_f1 * fo_f1
+        };
+        // Peak heights
+        let ratio = fo_f2 / fo_e;
+        let eta = join(ratio, 1.75, 20.0, ratio - 1.75);
+        let dm = 0.253 / (eta - 1.215) - 0.012;
+        let hm_f2 = 1490.0
+            * m3000
+            * ((0.0196 * m3000 * m3000 + 1.0) / (1.2967 * m3000 * m3000 - 1.0)).sqrt()
+            / (m3000 + dm)
+            - 176.0;
+        let hm_f1 = (hm_f2 + HM_E) / 2.0;
+        // Thickness parameters
+        let dndh = (-3.467 + 0.857 * (fo_f2 * fo_f2).ln() + 2.02 * m3000.ln()).exp();
+        let b2_bot = 0.385 * nm_f2 / (0.01 * dndh);
+        let b1_top = 0.3 * (hm_f2 - hm_f1);
+        let b1_bot = 0.5 * (hm_f1 - HM_E);
+        let be_top = b1_bot.max(7.0);
+        let be_bot = 5.0;
+        // Layer amplitudes
+        let a1 = 4.0 * nm_f2;
+        let (a2, a3) = if fo_f1 < 0.5 {
+            (0.0, 4.0 * (nm_e - epstein(a1, hm_f2, b2_bot, HM_E)))
+        } else {
+            let mut a3a = 4.0 * nm_e;
+            let mut a2a = 0.0;
+            for _ in 0..5 {
+                a2a = 4.0
+                    * (nm_f1
+                        - epstein(a1, hm_f2, b2_bot, hm_f1)
+                        - epstein(a3a, HM_E, be_top, hm_f1));
+                a2a = join(a2a, 0.8 * nm_f1, 1.0, a2a - 0.8 * nm_f1);
+                a3a = 4.0
+                    * (nm_e - epstein(a2a, hm_f1, b1_bot, HM_E) - epstein(a1, hm_f2, b2_bot, HM_E));
+            }
+            (a2a, join(a3a, 0.05, 60.0, a3a - 0.005))
+        };
+        if h <= hm_f2 {
+            // Bottomside
+            let hh = h.max(100.0);
+            let be = if hh > HM_E { be_top } else { be_bot };
+            let bf1 = if hh > hm_f1 { b1_top } else { b1_bot };
+            let corr = (10.0 / (1.0 + (hh - hm_f2).abs())).exp();
+            let alpha = [
+                (hh - hm_f2) / b2_bot,
+                (hh - hm_f1) / bf1 * corr,
+                (hh - HM_E) / be * corr,
+            ];
+            let amp = [a1, a2, a3];
+            let thick = [b2_bot, bf1, be];
+            let mut s = [0.0; 3];
+            for i in 0..3 {
+                if alpha[i].abs() <= 25.0 {
+                    let ee = alpha[i].exp();
+                    s[i] = amp[i] * ee / ((1.0 + ee) * (1.0 + ee));
+                }
+            }
+            let sum: f64 = s.iter().sum();
+            if h >= 100.0 {
+                sum * 1e11
+            } else {
+                let mut ds = 0.0;
+                for i in 0..3 {
+                    if alpha[i].abs() <= 25.0 {
+                        let ee = alpha[i].exp();
+                        ds += s[i] * (1.0 - ee) / ((1.0 + ee) * thick[i]);
+                    }
+                }
+                let bc = 1.0 - 10.0 * ds / sum;
+                let z = 0.1 * (h - 100.0);
+                sum * clip_exp(1.0 - bc * z - clip_exp(-z)) * 1e11
+            }
+        } else {
+            // Topside
+            let mut k = 3.22 - 0.0538 * fo_f2 - 0.00664 * hm_f2
+                + 0.113 * hm_f2 / b2_bot
+                + 0.00257 * ctx.az_r;
+            k = join(k, 1.0, 2.0, k - 1.0);
+            k = join(8.0, k, 2.0, k - 8.0);
+            let h0 = k * b2_bot;
+            const G: f64 = 0.125;
+            const R: f64 = 100.0;
+            let dh = h - hm_f2;
+            let z = dh / (h0 * (1.0 + R * G * dh / (R * h0 + G * dh)));
+            let ea = clip_exp(z);
+            if ea > 1e11 {
+                4.0 * nm_f2 / ea * 1e11
+            } else {
+                4.0 * nm_f2 * ea / ((1.0 + ea) * (1.0 + ea)) * 1e11
+            }
+        }
+    }
+
+    /// foF2 (MHz) and M(3000)F2 from the CCIR maps
+    fn legendre(&self, ctx: &Context, modip: f64, lat: f64, lon: f64) -> (f64, f64) {
+        let sin_mu = modip.to_radians().sin();
+        let cos_lat = lat.to_radians().cos();
+        let mut m = [1.0; 12];
+        for k in 1..12 {
+            m[k] = m[k - 1] * sin_mu;
+        }
+        let mut p = [1.0; 9];
+        for n in 1..9 {
+            p[n] = p[n - 1] * cos_lat;
+        }
+        let mut c = [1.0; 9];
+        let mut s = [0.0; 9];
+        for n in 1..9 {
+            let (sn, cn) = (n as f64 * lon).to_radians().sin_cos();
+            s[n] = sn;
+            c[n] = cn;
+        }
+        const Q: [usize; 9] = [12, 12, 9, 5, 2, 1, 1, 1, 1];
+        let mut fo_f2: f64 = (0..12).map(|k| ctx.cf2[k] * m[k]).sum();
+        let mut idx = Q[0];
+        for n in 1..9 {
+            for (k, mk) in m.iter().take(Q[n]).enumerate() {
+                fo_f2 +=
+                    (ctx.cf2[idx + 2 * k] * c[n] + ctx.cf2[idx + 2 * k + 1] * s[n]) * mk * p[n];
+            }
+            idx += 2 * Q[n];
+        }
+        const R: [usize; 7] = [7, 8, 6, 3, 2, 1, 1];
+        let mut m3000: f64 = (0..7).map(|k| ctx.cm3[k] * m[k]).sum();
+        let mut idx = R[0];
+        for n in 1..7 {
+            for (k, mk) in m.iter().take(R[n]).enumerate() {
+                m3000 +=
+                    (ctx.cm3[idx + 2 * k] * c[n] + ctx.cm3[idx + 2 * k + 1] * s[n]) * mk * p[n];
+            }
+            idx += 2 * R[n];
+        }
+        (fo_f2, m3000)
+    }
+
+    /// Integrate the electron density along a straight line between two
+    /// points in Earth-centered coordinates (km), returning TECU.
+    fn integrate_ray(&self, ctx: &Context, p1: [f64; 3], p2: [f64; 3]) -> f64 {
+        let d = [p2[0] - p1[0], p2[1] - p1[1], p2[2] - p1[2]];
+        let len = (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt();
+        if len <= 0.0 {
+            return 0.0;
+        }
+        let u = [d[0] / len, d[1] / len, d[2] / len];
+        // Ray perigee, and distances along the ray from the perigee
+        let s1 = p1[0] * u[0] + p1[1] * u[1] + p1[2] * u[2];
+        let s2 = s1 + len;
+        let perigee = [p1[0] - s1 * u[0], p1[1] - s1 * u[1], p1[2] - s1 * u[2]];
+        let rp2 = perigee.iter().map(|x| x * x).sum::<f64>();
+        let point = |s: f64| {
+            let p = [
+                perigee[0] + s * u[0],
+                perigee[1] + s * u[1],
+                perigee[2] + s * u[2],
+            ];
+            let r = (p[0] * p[0] + p[1] * p[1] + p[2] * p[2]).sqrt();
+            let lat = (p[2] / r).asin().to_degrees();
+            let lon = p[1].atan2(p[0]).to_degrees();
+            (lat, lon, r - EARTH_RADIUS)
+        };
+        let f = |s: f64| {
+            let (lat, lon, h) = point(s);
+            self.density(ctx, lat, lon, h)
+        };
+        // Split the integration at 1000 km and 2000 km height
+        let mut bounds = vec![(s1, 0.001)];
+        for h in [1000.0, 2000.0] {
+            let r = EARTH_RADIUS + h;
+            let s = (r * r - rp2).max(0.0).sqrt();
+            if s > s1 && s < s2 && point(s1).2 < h {
+                bounds.push((s, 0.01));
+            }
+        }
+        bounds.push((s2, 0.01));
+        let tec = bounds
+            .windows(2)
+            .map(|w| kronrod(&f, w[0].0, w[1].0, w[0].1, 0))
+            .sum::<f64>();
+        // km to m, electrons/m² to TECU
+        tec * 1e3 * 1e-16
+    }
+}
+
+/// Adaptive Gauss-Kronrod G7-K15 integration
+fn kronrod<F: Fn(f64) -> f64>(f: &F, a: f64, b: f64, tol: f64, level: usize) -> f64 {
+    let mid = 0.5 * (a + b);
+    let half = 0.5 * (b - a);
+    let fc = f(mid);
+    let mut k15 = fc * WK[7];
+    let mut g7 = fc * WG[3];
+    for i in 0..7 {
+        let dx = half * XK[i];
+        let fsum = f(mid - dx) + f(mid + dx);
+        k15 += WK[i] * fsum;
+        if i % 2 == 1 {
+            g7 += WG[i / 2] * fsum;
+        }
+    }
+    k15 *= half;
+    g7 *= half;
+    if (k15 - g7).abs() <= tol * k15.abs() || level >= MAX_LEVEL {
+        k15
+    } else {
+        kronrod(f, a, mid, tol, level + 1) + kronrod(f, mid, b, tol, level + 1)
+    }
+}
+
+fn to_cartesian(lat: f64, lon: f64, r: f64) -> [f64; 3] {
+    let (sin_lat, cos_lat) = lat.to_radians().sin_cos();
+    let (sin_lon, cos_lon) = lon.to_radians().sin_cos();
+    [r * cos_lat * cos_lon, r * cos_lat * sin_lon, r * sin_lat]
+}
+
+/// Satellite location on a sphere of radius `r` (km), seen from `rx`
+/// at the given azimuth and elevation
+fn project(rx: [f64; 3], lat: f64, lon: f64, azimuth: f64, elevation: f64, r: f64) -> [f64; 3] {
+    let (sin_lat, cos_lat) = lat.to_radians().sin_cos();
+    let (sin_lon, cos_lon) = lon.to_radians().sin_cos();
+    let (sin_az, cos_az) = azimuth.to_radians().sin_cos();
+    let (sin_el, cos_el) = elevation.to_radians().sin_cos();
+    let (e, n, u) = (cos_el * sin_az, cos_el * cos_az, sin_el);
+    let d = [
+        -sin_lon * e - sin_lat * cos_lon * n + cos_lat * cos_lon * u,
+        cos_lon * e - sin_lat * sin_lon * n + cos_lat * sin_lon * u,
+        cos_lat * n + sin_lat * u,
+    ];
+    let b = rx[0] * d[0] + rx[1] * d[1] + rx[2] * d[2];
+    let c = rx.iter().map(|x| x * x).sum::<f64>() - r * r;
+    let t = -b + (b * b - c).max(0.0).sqrt();
+    [rx[0] + t * d[0], rx[1] + t * d[1], rx[2] + t * d[2]]
+}
+
+impl IonoModel for NeQuickG {
+    fn slant_tec(
+        &self,
+        time: DateTime<Utc>,
+        location: Geodetic,
+        azimuth: f64,
+        elevation: f64,
+    ) -> Option<f64> {
+        if elevation < 0.0 {
+            return None;
+        }
+        let ctx = self.context(time, location.lat, location.lon);
+        let p1 = to_cartesian(
+            location.lat,
+            location.lon,
+            EARTH_RADIUS + location.height * 1e-3,
+        );
+        let p2 = project(
+            p1,
+            location.lat,
+            location.lon,
+            azimuth,
+            elevation,
+            EARTH_RADIUS + self.sat_height * 1e-3,
+        );
+        Some(self.integrate_ray(&ctx, p1, p2))
+    }
+
+    fn vertical_tec(
+        &self,
+        time: DateTime<Utc>,
+        location: Geodetic,
+        azimuth: f64,
+        elevation: f64,
+    ) -> Option<f64> {
+        if elevation < 0.0 {
+            return None;
+        }
+        let (lat, lon) = pierce_point(location, azimuth, elevation, 350e3);
+        let ctx = self.context(time, location.lat, location.lon);
+        let p1 = to_cartesian(lat, lon, EARTH_RADIUS + location.height * 1e-3);
+        let p2 = to_cartesian(lat, lon, EARTH_RADIUS + self.sat_height * 1e-3);
+        Some(self.integrate_ray(&ctx, p1, p2))
+    }
+}
+
+mod test {
+    #[test]
+    fn test_nequick() {
+        use super::*;
+        // Quadrature of a smooth function
+        let res = kronrod(&|x: f64| x.exp(), 0.0, 2.0, 1e-10, 0);
+        assert!((res - (2f64.exp() - 1.0)).abs() < 1e-9);
+        // Cubic interpolation is exact for a line
+        assert!((interpolate(&[1.0, 2.0, 3.0, 4.0], 0.25) - 2.25).abs() < 1e-12);
+        assert!(NequickData::new(vec![0.0; 10], vec![vec![0.0; CCIR_LEN]; 12]).is_err());
+
+        // Uniform ionosphere with foF2 = 8 MHz and M(3000)F2 = 3
+        let mut ccir = vec![0.0; CCIR_LEN];
+        ccir[0] = 8.0;
+        ccir[76 * 13] = 8.0;
+        ccir[2 * 76 * 13] = 3.0;
+        ccir[2 * 76 * 13 + 49 * 9] = 3.0;
+        let data = NequickData::new(vec![30.0; MODIP_LEN], vec![ccir; 12]).unwrap();
+        assert!((data.modip(12.3, 45.6) - 30.0).abs() < 1e-9);
+        let model = NeQuickG::new(Arc::new(data), [100.0, 0.0, 0.0]);
+        let time = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let loc = Geodetic::new(40.0, -100.0, 0.0);
+        let vtec = model.vertical_tec(time, loc, 0.0, 90.0).unwrap();
+        let stec = model.slant_tec(time, loc, 0.0, 90.0).unwrap();
+        assert!(vtec > 1.0 && vtec < 100.0);
+        assert!((stec - vtec).abs() / vtec < 0.01);
+        let low = model.slant_tec(time, loc, 0.0, 20.0).unwrap();
+        assert!(low > 1.5 * vtec);
+        assert!(model.slant_tec(time, loc, 0.0, -5.0).is_none());
+    }
+}
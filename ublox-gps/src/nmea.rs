@@ -1,27 +1,50 @@
-use chrono::{DateTime, TimeZone, Utc};
-use lazy_static::lazy_static;
-use regex::{Captures, Regex};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    str::FromStr,
+};
 use thiserror::Error;
 
-use crate::NmeaMsgGroup;
+use crate::{
+    fix::{FixMode, FixQuality},
+    geo::{Egm96, Geodetic, WGS84_A, WGS84_E2},
+    nmea_encoder::NmeaVersion,
+    nmea_fields::{Fields, Sentences, PROPRIETARY},
+    ubx::{BeidouFreq, GalileoFreq, GlonassFreq, GnssFreq, GpsFreq, NavicFreq, QzssFreq},
+    uncertain::Uncertain,
+    NmeaMsgGroup,
+};
+
+/// Conversion from knots to km/h
+const KNOTS_TO_KMH: f32 = 1.852;
 
 #[derive(Debug, Copy, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
-/// A GNSS satellite
+/// A GNSS satellite.
+///
+/// Formats and parses as a RINEX satellite ID, such as `G26` for GPS
+/// PRN 26 or `S23` for SBAS PRN 123.
+///
+/// Serializes to the system letter and the RINEX number in three digits,
+/// such as `G026`. Three-character strings are read as the legacy
+/// serialized form, with the satellite number in hexadecimal (such as
+/// `G1A` for GPS PRN 26, or `G10` for GPS PRN 16).
 pub enum GnssSatellite {
-    /// A GPS satellite (ID: 0 - 32)
+    /// A GPS satellite (PRN: 1 - 32)
     Gps(u8),
-    /// A SBAS satellite (ID: 120 - 158)
+    /// A SBAS satellite (PRN: 120 - 158)
     Sbas(u8),
     /// A Galileo satellite (ID: 1 - 36)
     Galileo(u8),
-    /// A Beidou satellite (ID: 1 - 37)
+    /// A Beidou satellite (ID: 1 - 63)
     Beidou(u8),
-    /// A QZSS satellite (ID: 1-5)
+    /// A QZSS satellite (ID: 1 - 10)
     Qzss(u8),
-    /// A Glonass satellite (ID: 1 - 32)
+    /// A Glonass satellite (slot: 1 - 32)
     Glonass(u8),
+    /// A NavIC satellite (ID: 1 - 14)
+    Navic(u8),
 }
 
 impl Serialize for GnssSatellite {
@@ -29,14 +52,8 @@ impl Serialize for GnssSatellite {
     where
         S: serde::Serializer,
     {
-        match self {
-            Self::Gps(svid) => serializer.serialize_str(&format!("G{:02X}", svid)),
-            Self::Sbas(svid) => serializer.serialize_str(&format!("S{:02X}", svid)),
-            Self::Galileo(svid) => serializer.serialize_str(&format!("E{:02X}", svid)),
-            Self::Beidou(svid) => serializer.serialize_str(&format!("C{:02X}", svid)),
-            Self::Qzss(svid) => serializer.serialize_str(&format!("J{:02X}", svid)),
-            Self::Glonass(svid) => serializer.serialize_str(&format!("R{:02X}", svid)),
-        }
+        // Four characters, distinct from the legacy form
+        serializer.collect_str(&format_args!("{}{:03}", self.system(), self.prn()))
     }
 }
 
@@ -46,50 +63,235 @@ impl<'de> Deserialize<'de> for GnssSatellite {
         D: serde::Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        if s.len() != 3 {
-            return Err(serde::de::Error::custom("Invalid length"));
+        match s.len() {
+            3 => GnssSatellite::from_legacy(&s).ok_or(GpsError::InvalidSatellite(s)),
+            _ => s.parse(),
+        }
+        .map_err(serde::de::Error::custom)
+    }
+}
+
+impl fmt::Display for GnssSatellite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{:02}", self.system(), self.prn())
+    }
+}
+
+impl FromStr for GnssSatellite {
+    type Err = GpsError;
+
+    /// Parse a RINEX satellite ID, such as `G26`, `R05` or `G 5`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || GpsError::InvalidSatellite(s.into());
+        let mut chars = s.chars();
+        let system = chars.next().ok_or_else(err)?;
+        let prn = chars.as_str().trim_start();
+        if prn.is_empty() || !prn.bytes().all(|x| x.is_ascii_digit()) {
+            return Err(err());
         }
-        let svid = u8::from_str_radix(&s[1..], 16).unwrap_or_default();
-        Ok(GnssSatellite::from_internal(s.as_bytes()[0], svid))
+        let prn: u8 = prn.parse().map_err(|_| err())?;
+        let sat = match system {
+            'G' => Self::Gps(prn),
+            // RINEX SBAS numbers are the PRN - 100
+            'S' => Self::Sbas(prn.checked_add(100).ok_or_else(err)?),
+            'E' => Self::Galileo(prn),
+            'C' => Self::Beidou(prn),
+            'J' => Self::Qzss(prn),
+            'R' => Self::Glonass(prn),
+            'I' => Self::Navic(prn),
+            _ => return Err(err()),
+        };
+        sat.validate().ok_or_else(err)
     }
 }
 
 impl GnssSatellite {
-    pub(crate) fn from_nmea_svid(cls: &[u8], svid: u8) -> Self {
-        match cls {
-            b"GP" => Self::Gps(svid),
-            b"GB" => Self::Beidou(svid),
-            b"GA" => Self::Galileo(svid),
-            b"GL" => Self::Glonass(svid - 64),
-            b"GN" => Self::Sbas(svid),
-            b"GQ" => Self::Qzss(svid),
-            _ => unreachable!(),
+    /// Get the RINEX system identifier of the satellite
+    pub fn system(&self) -> char {
+        match self {
+            Self::Gps(_) => 'G',
+            Self::Sbas(_) => 'S',
+            Self::Galileo(_) => 'E',
+            Self::Beidou(_) => 'C',
+            Self::Qzss(_) => 'J',
+            Self::Glonass(_) => 'R',
+            Self::Navic(_) => 'I',
         }
     }
 
-    pub(crate) fn from_internal(cls: u8, svid: u8) -> Self {
-        match cls {
-            b'G' => Self::Gps(svid),
-            b'C' => Self::Beidou(svid),
-            b'E' => Self::Galileo(svid),
-            b'R' => Self::Glonass(svid - 64),
-            b'S' => Self::Sbas(svid),
-            b'J' => Self::Qzss(svid),
-            _ => unreachable!(),
+    /// Get the RINEX satellite number (PRN, or slot for GLONASS)
+    ///
+    /// # Note
+    /// The RINEX number of SBAS satellites is the PRN - 100.
+    pub fn prn(&self) -> u8 {
+        match *self {
+            Self::Sbas(prn) => prn.saturating_sub(100),
+            Self::Gps(x)
+            | Self::Galileo(x)
+            | Self::Beidou(x)
+            | Self::Qzss(x)
+            | Self::Glonass(x)
+            | Self::Navic(x) => x,
         }
     }
 
-    pub(crate) fn from_ubx(cls: u8, svid: u8) -> Self {
-        match cls {
-            0 => Self::Gps(svid),
-            1 => Self::Sbas(svid),
-            2 => Self::Galileo(svid),
-            3 => Self::Beidou(svid),
-            5 => Self::Qzss(svid),
-            6 => Self::Glonass(svid),
-            _ => unreachable!(),
+    /// Check that the satellite number is in the range of its system
+    fn validate(self) -> Option<Self> {
+        let valid = match self {
+            Self::Gps(x) => (1..=32).contains(&x),
+            Self::Sbas(x) => (120..=158).contains(&x),
+            Self::Galileo(x) => (1..=36).contains(&x),
+            Self::Beidou(x) => (1..=63).contains(&x),
+            Self::Qzss(x) => (1..=10).contains(&x),
+            Self::Glonass(x) => (1..=32).contains(&x),
+            Self::Navic(x) => (1..=14).contains(&x),
+        };
+        valid.then_some(self)
+    }
+
+    /// Get the satellite of an NMEA satellite ID.
+    ///
+    /// Satellite IDs are numbered as in NMEA 4.10 and 4.11:
+    ///
+    /// | Talker | System  | Satellite ID           |
+    /// |--------|---------|------------------------|
+    /// | `GP`   | GPS     | 1 - 32                 |
+    /// | `GP`   | SBAS    | 33 - 64 (PRN 120 - 151), 152 - 158 |
+    /// | `GP`   | QZSS    | 193 - 202 (NMEA 4.10)  |
+    /// | `GL`   | GLONASS | 65 - 96                |
+    /// | `GA`   | Galileo | 1 - 36                 |
+    /// | `GB`   | BeiDou  | 1 - 63                 |
+    /// | `GQ`   | QZSS    | 1 - 10 (NMEA 4.11), 193 - 202 |
+    /// | `GI`   | NavIC   | 1 - 14                 |
+    ///
+    /// Combined (`GN`) sentences use the GPS, SBAS, GLONASS and QZSS
+    /// ranges, which do not overlap.
+    ///
+    /// # Returns
+    /// - The satellite, or `None` for unknown talkers and satellite IDs
+    pub fn from_nmea(talker: &[u8; 2], svid: u8) -> Option<Self> {
+        let sat = match (talker, svid) {
+            (b"GP" | b"GN", 1..=32) => Self::Gps(svid),
+            (b"GP" | b"GN", 33..=64) => Self::Sbas(svid + 87),
+            (b"GP" | b"GN", 152..=158) => Self::Sbas(svid),
+            (b"GP" | b"GN" | b"GQ", 193..=202) => Self::Qzss(svid - 192),
+            (b"GL" | b"GN", 65..=96) => Self::Glonass(svid - 64),
+            (b"GA", _) => Self::Galileo(svid),
+            (b"GB" | b"BD", _) => Self::Beidou(svid),
+            (b"GQ", _) => Self::Qzss(svid),
+            (b"GI", _) => Self::Navic(svid),
+            _ => return None,
+        };
+        sat.validate()
+    }
+
+    /// Get the NMEA talker ID and satellite ID of the satellite, the
+    /// inverse of [`GnssSatellite::from_nmea`]
+    ///
+    /// NMEA 2.3 has no talker IDs for Galileo, BeiDou and NavIC, and
+    /// numbers QZSS satellites with the GPS talker ID.
+    ///
+    /// # Returns
+    /// - The talker ID and satellite ID, or `None` if the satellite
+    ///   cannot be represented in the NMEA version
+    pub fn to_nmea(&self, version: NmeaVersion) -> Option<([u8; 2], u8)> {
+        let legacy = version < NmeaVersion::V411;
+        let id = match *self {
+            Self::Gps(x) => (*b"GP", x),
+            Self::Sbas(x @ 120..=151) => (*b"GP", x - 87),
+            Self::Sbas(x) => (*b"GP", x),
+            Self::Glonass(x) => (*b"GL", x + 64),
+            Self::Qzss(x) if version == NmeaVersion::V23 => (*b"GP", x + 192),
+            Self::Qzss(x) if legacy => (*b"GQ", x + 192),
+            Self::Qzss(x) => (*b"GQ", x),
+            _ if version == NmeaVersion::V23 => return None,
+            Self::Galileo(x) => (*b"GA", x),
+            Self::Beidou(x) => (*b"GB", x),
+            Self::Navic(x) => (*b"GI", x),
+        };
+        Some(id)
+    }
+
+    /// Get the NMEA system ID of the satellite (NMEA 4.10 and later)
+    pub(crate) fn nmea_system_id(&self) -> u8 {
+        match self {
+            Self::Gps(_) | Self::Sbas(_) => 1,
+            Self::Glonass(_) => 2,
+            Self::Galileo(_) => 3,
+            Self::Beidou(_) => 4,
+            Self::Qzss(_) => 5,
+            Self::Navic(_) => 6,
         }
     }
+
+    /// Get the satellite of a UBX GNSS ID and satellite ID
+    ///
+    /// # Returns
+    /// - The satellite, or `None` for unknown GNSS IDs and satellite IDs
+    pub fn from_ubx(gnss_id: u8, sv_id: u8) -> Option<Self> {
+        let sat = match gnss_id {
+            0 => Self::Gps(sv_id),
+            1 => Self::Sbas(sv_id),
+            2 => Self::Galileo(sv_id),
+            3 => Self::Beidou(sv_id),
+            5 => Self::Qzss(sv_id),
+            6 => Self::Glonass(sv_id),
+            7 => Self::Navic(sv_id),
+            _ => return None,
+        };
+        sat.validate()
+    }
+
+    /// Get the satellite of a u-blox satellite number, as in the PUBX,03
+    /// sentence.
+    ///
+    /// | System  | Satellite number          |
+    /// |---------|---------------------------|
+    /// | GPS     | 1 - 32                    |
+    /// | SBAS    | 33 - 64 (PRN 120 - 151), 152 - 158 |
+    /// | GLONASS | 65 - 96                   |
+    /// | BeiDou  | 159 - 163 (PRN 1 - 5), 401 - 463 |
+    /// | QZSS    | 193 - 202                 |
+    /// | Galileo | 211 - 246, 301 - 336      |
+    ///
+    /// # Returns
+    /// - The satellite, or `None` for unknown satellite numbers
+    pub fn from_pubx(svid: u16) -> Option<Self> {
+        // Offsets are below 256 within each range
+        let sat = match svid {
+            1..=32 => Self::Gps(svid as u8),
+            33..=64 => Self::Sbas(svid as u8 + 87),
+            65..=96 => Self::Glonass(svid as u8 - 64),
+            152..=158 => Self::Sbas(svid as u8),
+            159..=163 => Self::Beidou(svid as u8 - 158),
+            193..=202 => Self::Qzss(svid as u8 - 192),
+            211..=246 => Self::Galileo(svid as u8 - 210),
+            301..=336 => Self::Galileo((svid - 300) as u8),
+            401..=463 => Self::Beidou((svid - 400) as u8),
+            _ => return None,
+        };
+        sat.validate()
+    }
+
+    /// Parse the legacy serialized form, with the satellite number in
+    /// hexadecimal
+    fn from_legacy(s: &str) -> Option<Self> {
+        if s.len() != 3 || !s.is_ascii() {
+            return None;
+        }
+        let svid = u8::from_str_radix(&s[1..], 16).ok()?;
+        let sat = match s.as_bytes()[0] {
+            b'G' => Self::Gps(svid),
+            b'S' => Self::Sbas(svid),
+            b'E' => Self::Galileo(svid),
+            b'C' => Self::Beidou(svid),
+            b'J' => Self::Qzss(svid),
+            b'R' => Self::Glonass(svid),
+            b'I' => Self::Navic(svid),
+            _ => return None,
+        };
+        sat.validate()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -103,58 +305,46 @@ pub struct RawNmea {
 impl RawNmea {
     /// Parse a string of NMEA data into a hashmap of [`RawNmea`] data
     pub fn parse_str(data: &str) -> NmeaMsgGroup {
-        lazy_static! {
-            static ref RE: Regex = Regex::new(
-                r"\$(?P<payload>(?P<id>[A-Z]{2})(?P<kind>[A-Z]{3})\,(?P<data>.*?))\*(?P<cksum>[A-F0-9]{2})"
-            ).expect("Failed to compile regex");
-        }
-        let mut res = HashMap::new();
-        for caps in RE.captures_iter(data) {
-            let calc_cksum = caps["payload"].as_bytes().iter().fold(0, |acc, &x| acc ^ x);
-            if let Ok(cksum) = u8::from_str_radix(&caps["cksum"], 16) {
-                if calc_cksum == cksum {
-                    let id = caps["id"].as_bytes()[..2]
-                        .try_into()
-                        .expect("Failed to convert ID");
-                    let kind = caps["kind"].as_bytes()[..3]
-                        .try_into()
-                        .expect("Failed to convert kind");
-                    res.entry(kind)
-                        .and_modify(|e: &mut Vec<RawNmea>| {
-                            e.push(RawNmea {
-                                id,
-                                data: caps["data"].to_string(),
-                            })
-                        })
-                        .or_insert_with(|| {
-                            vec![RawNmea {
-                                id,
-                                data: caps["data"].to_string(),
-                            }]
-                        });
-                }
-            }
+        let mut res: HashMap<[u8; 3], Vec<RawNmea>> = HashMap::new();
+        for (id, kind, data) in Sentences::new(data) {
+            res.entry(kind).or_default().push(RawNmea {
+                id,
+                data: data.to_string(),
+            });
         }
         NmeaMsgGroup(res)
     }
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 /// A struct containing GPS information
+///
+/// Also deserializes from the layout of earlier versions, where the
+/// location was a `[latitude, longitude, altitude]` array with the
+/// altitude above mean sea level, and `msl` held the geoid separation.
 pub struct NmeaGpsInfo {
     /// Timestamp of the fix
     pub time: DateTime<Utc>,
     /// Location of the fix
-    pub loc: (f64, f64, f32),
-    /// Altitude above mean sea level
+    pub loc: Geodetic,
+    /// Altitude above mean sea level (m)
     pub msl: f32,
+    /// Geoid separation (m), the height of the geoid above the WGS-84
+    /// ellipsoid, from GGA or GNS or from a geoid model.
+    ///
+    /// `None` if not known, in which case the height above the ellipsoid
+    /// is not known either, and the height of [`NmeaGpsInfo::loc`] is `0`.
+    #[serde(default)]
+    pub separation: Option<f32>,
     /// True heading
     pub true_heading: f32,
     /// Magnetic heading
     pub mag_heading: f32,
     /// Ground speed
     pub ground_speed: f32,
-    /// Quality of the fix
+    /// Quality of the fix, as the raw GGA value; see
+    /// [`NmeaGpsInfo::fix_quality`]
     pub quality: u8,
     /// Horizontal dilution of precision
     pub hdop: f32,
@@ -164,13 +354,266 @@ pub struct NmeaGpsInfo {
     pub pdop: f32,
     /// Elevation and azimuth of satellites
     pub sat_views: HashMap<GnssSatellite, (i8, u16)>,
+    /// Date of the fix (RMC)
+    #[serde(default)]
+    pub date: Option<NaiveDate>,
+    /// Course over ground (degrees, true) (RMC)
+    #[serde(default)]
+    pub course: Option<f32>,
+    /// Magnetic variation (degrees, positive east) (RMC)
+    #[serde(default)]
+    pub mag_variation: Option<f32>,
+    /// Data validity status (RMC, or GLL if RMC is not available)
+    #[serde(default)]
+    pub valid: Option<bool>,
+    /// Positioning mode indicator (RMC, or GLL if RMC is not available)
+    #[serde(default)]
+    pub mode: Option<char>,
+    /// Navigational status indicator (RMC, NMEA 4.10 and later)
+    #[serde(default)]
+    pub nav_status: Option<char>,
+    /// Number of satellites used in the fix (GGA, or GNS if GGA does not
+    /// report it)
+    #[serde(default)]
+    pub num_sats: Option<u8>,
+    /// Positioning mode indicator of each constellation (GNS)
+    #[serde(default)]
+    pub modes: HashMap<Constellation, char>,
+    /// Pseudo-range error statistics (GST)
+    #[serde(default)]
+    pub errors: Option<PositionErrors>,
+    /// Source of the timestamp
+    #[serde(default)]
+    pub time_source: TimeSource,
+    /// Signals of satellites in view, with their SNR (GSV, NMEA 4.10
+    /// and later)
+    #[serde(default)]
+    pub signals: HashMap<GnssSatellite, Vec<SignalView>>,
+    /// Fix mode (GSA): `1` for no fix, `2` for a 2D fix, `3` for a 3D fix
+    #[serde(default)]
+    pub fix_mode: Option<u8>,
+    /// Selection of the fix mode (GSA): `M` for manual, `A` for automatic
+    #[serde(default)]
+    pub selection_mode: Option<char>,
+    /// Satellites used in the fix, from the GSA sentences of all systems
+    /// (or PUBX,03)
+    #[serde(default)]
+    pub used_in_fix: HashSet<GnssSatellite>,
+    /// Accuracy estimates of the position (PUBX,00)
+    #[serde(default)]
+    pub accuracy: Option<PositionAccuracy>,
+    /// Receiver clock and UTC time information (PUBX,04)
+    #[serde(default)]
+    pub clock: Option<ClockInfo>,
+}
+
+impl Serialize for NmeaGpsInfo {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        NmeaGpsInfo::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for NmeaGpsInfo {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(match NmeaGpsInfoRepr::deserialize(deserializer)? {
+            NmeaGpsInfoRepr::Legacy(info) => info.into(),
+            NmeaGpsInfoRepr::Current(info) => *info,
+        })
+    }
+}
+
+impl NmeaGpsInfo {
+    fn deserialize_boxed<'de, D>(deserializer: D) -> Result<Box<Self>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        NmeaGpsInfo::deserialize(deserializer).map(Box::new)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NmeaGpsInfoRepr {
+    // Tried first, as the current layout also accepts a location array
+    Legacy(LegacyNmeaGpsInfo),
+    Current(#[serde(deserialize_with = "NmeaGpsInfo::deserialize_boxed")] Box<NmeaGpsInfo>),
+}
+
+/// GPS information in the layout of earlier versions
+#[derive(Deserialize)]
+struct LegacyNmeaGpsInfo {
+    time: DateTime<Utc>,
+    /// Latitude, longitude and altitude above mean sea level
+    loc: (f64, f64, f64),
+    /// Geoid separation
+    msl: f32,
+    true_heading: f32,
+    mag_heading: f32,
+    ground_speed: f32,
+    quality: u8,
+    hdop: f32,
+    vdop: f32,
+    pdop: f32,
+    sat_views: HashMap<GnssSatellite, (i8, u16)>,
+}
+
+impl From<LegacyNmeaGpsInfo> for NmeaGpsInfo {
+    fn from(value: LegacyNmeaGpsInfo) -> Self {
+        let (lat, lon, alt) = value.loc;
+        Self {
+            time: value.time,
+            loc: Geodetic::new(lat, lon, alt + value.msl as f64),
+            msl: alt as f32,
+            separation: Some(value.msl),
+            true_heading: value.true_heading,
+            mag_heading: value.mag_heading,
+            ground_speed: value.ground_speed,
+            quality: value.quality,
+            hdop: value.hdop,
+            vdop: value.vdop,
+            pdop: value.pdop,
+            sat_views: value.sat_views,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// A signal of a satellite in view, from a GSV sentence
+pub struct SignalView {
+    /// Frequency channel of the signal
+    ///
+    /// # Note
+    /// The frequency channel of GLONASS satellites is not reported in
+    /// NMEA, and is set to `0`.
+    pub channel: GnssFreq,
+    /// Signal-to-noise ratio (dB-Hz), or `None` if the signal is not
+    /// tracked
+    pub snr: Option<u8>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+/// Source of the timestamp of a fix
+pub enum TimeSource {
+    /// Date and time from ZDA
+    #[default]
+    Zda,
+    /// Date from RMC, time of day from GGA
+    RmcGga,
+    /// Date from RMC, time of day from GLL
+    RmcGll,
+    /// Date and time from RMC
+    Rmc,
+    /// Time of the UBX-RXM-RAWX measurements
+    Rawx,
+    /// UTC time of the UBX-NAV-PVT solution
+    NavPvt,
+    /// Date and time from PUBX,04
+    Pubx,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+/// A GNSS constellation, in the order of the GNS mode indicators
+pub enum Constellation {
+    /// GPS
+    Gps,
+    /// GLONASS
+    Glonass,
+    /// Galileo
+    Galileo,
+    /// BeiDou
+    Beidou,
+    /// QZSS
+    Qzss,
+    /// NavIC (IRNSS)
+    Navic,
+}
+
+impl Constellation {
+    /// Constellations in the order of the GNS mode indicators
+    const GNS_ORDER: [Constellation; 6] = [
+        Self::Gps,
+        Self::Glonass,
+        Self::Galileo,
+        Self::Beidou,
+        Self::Qzss,
+        Self::Navic,
+    ];
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+/// Pseudo-range error statistics from a GST sentence.
+///
+/// Standard deviations are in meters.
+pub struct PositionErrors {
+    /// RMS of the standard deviation of the ranges
+    pub rms: Option<f32>,
+    /// Standard deviation of the semi-major axis of the error ellipse
+    pub semi_major: Option<f32>,
+    /// Standard deviation of the semi-minor axis of the error ellipse
+    pub semi_minor: Option<f32>,
+    /// Orientation of the semi-major axis of the error ellipse
+    /// (degrees from true north)
+    pub orientation: Option<f32>,
+    /// Standard deviation of the latitude error
+    pub lat: Option<f32>,
+    /// Standard deviation of the longitude error
+    pub lon: Option<f32>,
+    /// Standard deviation of the altitude error
+    pub alt: Option<f32>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+/// Accuracy estimates of the position, from a u-blox PUBX,00 sentence
+/// or a UBX-NAV-PVT solution.
+///
+/// Accuracies are in meters.
+pub struct PositionAccuracy {
+    /// Horizontal accuracy estimate
+    pub horizontal: Option<f32>,
+    /// Vertical accuracy estimate
+    pub vertical: Option<f32>,
+    /// Vertical velocity (m/s, positive downwards)
+    pub vertical_velocity: Option<f32>,
+    /// Age of the differential corrections (s)
+    pub diff_age: Option<f32>,
+    /// Time dilution of precision
+    pub tdop: Option<f32>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+/// Receiver clock and UTC time information from a u-blox PUBX,04
+/// sentence
+pub struct ClockInfo {
+    /// UTC time of week (s)
+    pub utc_tow: Option<f64>,
+    /// UTC week number
+    pub utc_week: Option<u16>,
+    /// Leap seconds between GPS time and UTC (s)
+    pub leap_seconds: Option<i8>,
+    /// Whether the leap seconds are the firmware default, rather than
+    /// decoded from the navigation message
+    pub leap_seconds_default: bool,
+    /// Receiver clock bias (ns)
+    pub clock_bias: Option<f64>,
+    /// Receiver clock drift (ns/s)
+    pub clock_drift: Option<f32>,
+    /// Time pulse granularity (ns), the quantization error of the time
+    /// pulse
+    pub timepulse_granularity: Option<i32>,
 }
 
 #[derive(Error, Clone, Debug)]
 /// An error type for GPS parsing
 pub enum GpsError {
     /// No fix has been acquired
-    #[error("No ZDA data, has fix been acquired?")]
+    #[error("No time or position data, has fix been acquired?")]
     NoFix,
     /// The pattern was not found
     #[error("Pattern not found")]
@@ -178,126 +621,281 @@ pub enum GpsError {
     /// Failed to parse data
     #[error("Failed to parse ZDA data: {0}")]
     ParseError(String),
+    /// A required field of a sentence is empty or missing
+    #[error("Missing {field} (field {index}) in {sentence} sentence")]
+    MissingField {
+        /// Sentence formatter
+        sentence: &'static str,
+        /// Name of the field
+        field: &'static str,
+        /// Index of the field, from the first field after the formatter
+        index: usize,
+    },
+    /// A field of a sentence could not be parsed
+    #[error("Invalid {field} (field {index}) in {sentence} sentence: {value:?}")]
+    InvalidField {
+        /// Sentence formatter
+        sentence: &'static str,
+        /// Name of the field
+        field: &'static str,
+        /// Index of the field, from the first field after the formatter
+        index: usize,
+        /// Contents of the field
+        value: String,
+    },
+    /// A satellite ID could not be parsed
+    #[error("Invalid satellite ID: {0:?}")]
+    InvalidSatellite(String),
 }
 
 impl NmeaGpsInfo {
+    /// Create a new GPS info struct from a hashmap of [`RawNmea`] data,
+    /// without a geoid model; see [`NmeaGpsInfo::create_with_geoid`]
+    pub(crate) fn create(
+        data: &mut NmeaMsgGroup,
+        process_gsv: bool,
+        rawx_time: Option<DateTime<Utc>>,
+    ) -> Result<Self, GpsError> {
+        Self::create_with_geoid(data, process_gsv, rawx_time, None)
+    }
+
     /// Create a new GPS info struct from a hashmap of [`RawNmea`] data.
     ///
+    /// The time of the fix is taken from ZDA, or else from PUBX,04.
+    /// Without either, the date from RMC is combined with the time of day
+    /// from GGA, GLL or RMC, or else the time of the RXM-RAWX measurements
+    /// is used; see [`TimeSource`]. The position is taken from GGA, or
+    /// else from GNS, RMC or PUBX,00.
+    ///
     /// # Arguments
     /// - `data`: A mutable reference to a hashmap of [`RawNmea`] data
     /// - `process_gsv`: A boolean indicating whether to process GSV data
+    /// - `rawx_time`: UTC time of the RXM-RAWX measurements, if available
+    /// - `geoid`: Geoid model for the height above the ellipsoid, if GGA
+    ///   or GNS do not report the geoid separation
     ///
     /// # Returns
     /// - A result containing the GPS info struct or a [`GpsError`]
     ///
     /// # Errors
-    /// - Returns a [`GpsError`] if the ZDA or GGA data is not found or if parsing fails
+    /// - Returns a [`GpsError`] if the time or position is not found or if parsing fails
     ///
-    pub(crate) fn create(data: &mut NmeaMsgGroup, process_gsv: bool) -> Result<Self, GpsError> {
-        let time = if let Some(mut zda) = data.0.remove(b"ZDA") {
-            let time = loop {
-                if let Some(val) = zda.pop() {
-                    if let Ok(zda) = parse_zda(&val.data) {
-                        break Some(zda);
+    pub(crate) fn create_with_geoid(
+        data: &mut NmeaMsgGroup,
+        process_gsv: bool,
+        rawx_time: Option<DateTime<Utc>>,
+        geoid: Option<&Egm96>,
+    ) -> Result<Self, GpsError> {
+        let zda = latest(data.0.remove(b"ZDA"), parse_zda);
+        let gga = latest(data.0.remove(b"GGA"), parse_gga);
+        let rmc = latest(data.0.remove(b"RMC"), parse_rmc);
+        let gll = latest(data.0.remove(b"GLL"), parse_gll);
+        let gns = latest(data.0.remove(b"GNS"), parse_gns);
+        // PUBX,03 is kept with the GSV sentences
+        let (pubx, other): (Vec<_>, Vec<_>) = data
+            .0
+            .remove(b"UBX")
+            .unwrap_or_default()
+            .into_iter()
+            .partition(|x| {
+                x.id == PROPRIETARY && (x.data.starts_with("00,") || x.data.starts_with("04,"))
+            });
+        if !other.is_empty() {
+            data.0.insert(*b"UBX", other);
+        }
+        let (pubx00, pubx04): (Vec<_>, Vec<_>) =
+            pubx.into_iter().partition(|x| x.data.starts_with("00,"));
+        let pubx00 = latest(Some(pubx00), parse_pubx00);
+        let pubx04 = latest(Some(pubx04), parse_pubx04);
+
+        let date = rmc.as_ref().ok().and_then(|rmc| rmc.date);
+        let pubx_time = pubx04
+            .as_ref()
+            .ok()
+            .and_then(|x| Some(x.date?.and_time(x.time?).and_utc()));
+        let zda = zda
+            .map(|time| (time, TimeSource::Zda))
+            .or_else(|e| pubx_time.map(|time| (time, TimeSource::Pubx)).ok_or(e));
+        let (time, time_source) = match zda {
+            Ok(time) => time,
+            Err(e) => {
+                let gga_time = gga.as_ref().ok().and_then(|x| x.time);
+                let gll_time = gll.as_ref().ok().and_then(|x| x.time);
+                let rmc_time = rmc.as_ref().ok().and_then(|x| x.time);
+                match (date, gga_time, gll_time, rmc_time, rawx_time) {
+                    (Some(date), Some(time), _, _, _) => {
+                        (date.and_time(time).and_utc(), TimeSource::RmcGga)
+                    }
+                    (Some(date), None, Some(time), _, _) => {
+                        (date.and_time(time).and_utc(), TimeSource::RmcGll)
+                    }
+                    (Some(date), None, None, Some(time), _) => {
+                        (date.and_time(time).and_utc(), TimeSource::Rmc)
                     }
-                } else {
-                    break None;
+                    (_, _, _, _, Some(time)) => (time, TimeSource::Rawx),
+                    _ => return Err(e),
                 }
-            };
-            if let Some(time) = time {
-                time
-            } else {
-                return Err(GpsError::NoFix);
             }
-        } else {
-            return Err(GpsError::NoFix);
         };
 
-        let (loc, quality, msl) = if let Some(mut zda) = data.0.remove(b"GGA") {
-            let time = loop {
-                if let Some(val) = zda.pop() {
-                    if let Ok(gga) = parse_gga(&val.data) {
-                        let loc = (
-                            parse_lat(&gga["lat"], &gga["lat_dir"])?,
-                            parse_lon(&gga["lon"], &gga["lon_dir"])?,
-                            gga["alt"]
-                                .parse::<f32>()
-                                .map_err(|_| GpsError::ParseError("Altitude".into()))?,
-                        );
-                        let quality = u8::from_str_radix(&gga["quality"], 16)
-                            .map_err(|_| GpsError::ParseError("Quality".into()))?;
-                        let msl = gga["msl"].parse::<f32>().unwrap_or_default();
-                        break Some((loc, quality, msl));
-                    }
-                } else {
-                    break None;
+        let mut info = match (gga, gns.as_ref(), rmc.as_ref(), pubx00.as_ref()) {
+            (Ok(gga), _, _, _) => {
+                let (loc, separation) = msl_location(gga.lat, gga.lon, gga.alt, gga.sep, geoid);
+                Self {
+                    loc,
+                    msl: gga.alt as f32,
+                    separation,
+                    quality: gga.quality.into(),
+                    num_sats: gga.num_sats,
+                    ..Default::default()
                 }
-            };
-            if let Some(time) = time {
-                time
-            } else {
-                return Err(GpsError::NoFix);
             }
-        } else {
-            return Err(GpsError::NoFix);
-        };
-
-        let mut info = Self {
-            time,
-            loc,
-            msl,
-            quality,
-            ..Default::default()
+            (Err(_), Ok(gns), _, _) if gns.fix().is_some() => {
+                let (lat, lon, quality) = gns.fix().unwrap_or_default();
+                let msl = gns.alt.unwrap_or_default();
+                let (loc, separation) = msl_location(lat, lon, msl, gns.sep, geoid);
+                Self {
+                    loc,
+                    msl: msl as f32,
+                    separation,
+                    quality,
+                    num_sats: gns.num_sats,
+                    ..Default::default()
+                }
+            }
+            (Err(_), _, Ok(rmc), _) if rmc.fix().is_some() => {
+                let (lat, lon, quality) = rmc.fix().unwrap_or_default();
+                Self {
+                    loc: Geodetic::new(lat, lon, 0.0),
+                    quality,
+                    ..Default::default()
+                }
+            }
+            // Height above the ellipsoid, without the geoid separation
+            (Err(_), _, _, Ok(pubx)) if pubx.fix().is_some() => {
+                let (lat, lon, height) = pubx.fix().unwrap_or_default();
+                Self {
+                    loc: Geodetic::new(lat, lon, height),
+                    quality: pubx.quality.into(),
+                    num_sats: pubx.num_sats,
+                    hdop: pubx.hdop.unwrap_or_default(),
+                    vdop: pubx.vdop.unwrap_or_default(),
+                    ..Default::default()
+                }
+            }
+            (Err(e), _, _, _) => return Err(e),
         };
+        info.time = time;
+        info.time_source = time_source;
 
+        let has_vtg = data.0.contains_key(b"VTG");
+        let has_rmc = rmc.is_ok();
         if let Some(mut vtg) = data.0.remove(b"VTG") {
             if let Some(vtg) = vtg.pop() {
                 if let Ok(vtg) = parse_vtg(&vtg.data) {
-                    info.true_heading = vtg["true_heading"].parse().unwrap_or_default();
-                    info.ground_speed = vtg["ground_speed"].parse().unwrap_or_default();
-                    info.mag_heading = vtg["mag_heading"].parse().unwrap_or_default();
+                    info.true_heading = vtg.true_heading.unwrap_or_default();
+                    info.ground_speed = vtg.speed_kmh.unwrap_or_default();
+                    info.mag_heading = vtg.mag_heading.unwrap_or_default();
                 }
             }
         }
 
-        if let Some(mut gsa) = data.0.remove(b"GSA") {
-            if let Some(gsa) = gsa.pop() {
-                if let Ok(gsa) = parse_gsa(&gsa.data) {
-                    info.pdop = gsa["pdop"].parse().unwrap_or_default();
-                    info.hdop = gsa["hdop"].parse().unwrap_or_default();
-                    info.vdop = gsa["vdop"].parse().unwrap_or_default();
-                }
+        // One GSA sentence per system, with the DOPs of the combined fix
+        for msg in data.0.remove(b"GSA").unwrap_or_default() {
+            let Ok(gsa) = parse_gsa(&msg.data) else {
+                continue;
+            };
+            info.pdop = gsa.pdop.unwrap_or_default();
+            info.hdop = gsa.hdop.unwrap_or_default();
+            info.vdop = gsa.vdop.unwrap_or_default();
+            info.fix_mode = gsa.fix_type.or(info.fix_mode);
+            info.selection_mode = gsa.mode.or(info.selection_mode);
+            info.used_in_fix.extend(
+                gsa.svids
+                    .iter()
+                    .filter_map(|&svid| gsa_satellite(&msg.id, gsa.system_id, svid)),
+            );
+        }
+
+        if let Ok(rmc) = rmc {
+            info.date = rmc.date;
+            info.course = rmc.course;
+            info.mag_variation = rmc.mag_variation;
+            info.valid = Some(rmc.valid);
+            info.mode = rmc.mode;
+            info.nav_status = rmc.nav_status;
+            if !has_vtg {
+                info.true_heading = rmc.course.unwrap_or_default();
+                info.ground_speed = rmc.speed_knots.unwrap_or_default() * KNOTS_TO_KMH;
             }
         }
 
-        if process_gsv {
-            if let Some(gsv) = data.0.remove(b"GSV") {
-                gsv.iter()
-                    .filter_map(|x| {
-                        let id = x.id;
-                        parse_gsv(&x.data).ok().map(|x| {
-                            x.into_iter().map(move |(svid, elev, az)| {
-                                (GnssSatellite::from_nmea_svid(&id, svid), (elev, az))
-                            })
-                        })
-                    })
-                    .flatten()
-                    .for_each(|(svid, (elev, az))| {
-                        info.sat_views
-                            .entry(svid)
-                            .and_modify(|e| {
-                                e.0 = elev;
-                                e.1 = az;
-                            })
-                            .or_insert((elev, az));
-                    });
+        if let Ok(gll) = gll {
+            info.valid.get_or_insert(gll.valid);
+            if info.mode.is_none() {
+                info.mode = gll.mode;
+            }
+        }
+
+        if let Ok(gns) = gns {
+            info.modes = gns.modes;
+            if info.num_sats.is_none() {
+                info.num_sats = gns.num_sats;
+            }
+        }
+
+        if let Ok(gst) = latest(data.0.remove(b"GST"), parse_gst) {
+            info.errors = Some(gst);
+        }
+
+        if let Ok(pubx) = pubx00 {
+            if !has_vtg && !has_rmc {
+                info.true_heading = pubx.course.unwrap_or_default();
+                info.ground_speed = pubx.speed_kmh.unwrap_or_default();
+            }
+            info.accuracy = Some(pubx.accuracy);
+            if info.num_sats.is_none() {
+                info.num_sats = pubx.num_sats;
             }
         }
+
+        if let Ok(pubx) = pubx04 {
+            info.clock = Some(pubx.clock);
+        }
+
+        if process_gsv {
+            info.insert_gsv(data);
+        }
         Ok(info)
     }
 
+    /// Get the location of the fix with its uncertainty from the GST
+    /// pseudo-range error statistics.
+    ///
+    /// # Returns
+    /// - Latitude and longitude (degrees) and ellipsoidal height (m), or
+    ///   `None` if the GST standard deviations are not available
+    pub fn uncertain_location(&self) -> Option<(Uncertain<f64>, Uncertain<f64>, Uncertain<f64>)> {
+        let errors = self.errors?;
+        let (lat, lon, alt) = (errors.lat?, errors.lon?, errors.alt?);
+        // Meridional and prime vertical radii of curvature
+        let sin = self.loc.lat.to_radians().sin();
+        let w = 1.0 - WGS84_E2 * sin * sin;
+        let m = WGS84_A * (1.0 - WGS84_E2) / (w * w.sqrt());
+        let n = WGS84_A / w.sqrt();
+        let cos = self.loc.lat.to_radians().cos();
+        Some((
+            Uncertain(self.loc.lat, (lat as f64 / m).to_degrees()),
+            Uncertain(self.loc.lon, (lon as f64 / (n * cos)).to_degrees()),
+            Uncertain(self.loc.height, alt as f64),
+        ))
+    }
+
     /// Insert GSV data into the GPS info struct.
     ///
+    /// Satellites in the u-blox PUBX,03 sentence that are not in the
+    /// GSV sentences are also inserted, with their use in the fix.
+    ///
     /// # Note
     /// [NmeaGpsInfo::insert_gsv] is intended to be used when NMEA
     /// data is processed partially using the [crate::parse_partial]
@@ -310,134 +908,649 @@ impl NmeaGpsInfo {
     /// - `msgs`: A [`NmeaMsgGroup`] containing GSV data.
     ///
     pub fn insert_gsv(&mut self, msgs: &mut NmeaMsgGroup) {
-        if let Some(gsv) = msgs.0.remove(b"GSV") {
-            gsv.iter()
-                .filter_map(|x| {
-                    let id = x.id;
-                    parse_gsv(&x.data).ok().map(|x| {
-                        x.into_iter().map(move |(svid, elev, az)| {
-                            (GnssSatellite::from_nmea_svid(&id, svid), (elev, az))
-                        })
-                    })
-                })
-                .flatten()
-                .for_each(|(svid, (elev, az))| {
-                    self.sat_views
-                        .entry(svid)
-                        .and_modify(|e| {
-                            e.0 = elev;
-                            e.1 = az;
-                        })
-                        .or_insert((elev, az));
-                });
+        let pubx = msgs.0.remove(b"UBX").unwrap_or_default();
+        let gsv = msgs.0.remove(b"GSV").unwrap_or_default();
+        for msg in gsv {
+            let Ok(gsv) = parse_gsv(&msg.data) else {
+                continue;
+            };
+            for sat in gsv.sats {
+                let Some(svid) = GnssSatellite::from_nmea(&msg.id, sat.svid) else {
+                    continue;
+                };
+                self.sat_views.insert(
+                    svid,
+                    (
+                        sat.elevation.unwrap_or_default(),
+                        sat.azimuth.unwrap_or_default(),
+                    ),
+                );
+                // Each signal of a satellite is reported in its own sentence
+                let Some(channel) = gsv.signal_id.and_then(|id| nmea_signal(&svid, id)) else {
+                    continue;
+                };
+                let signals = self.signals.entry(svid).or_default();
+                match signals.iter_mut().find(|x| x.channel == channel) {
+                    Some(signal) => signal.snr = sat.snr,
+                    None => signals.push(SignalView {
+                        channel,
+                        snr: sat.snr,
+                    }),
+                }
+            }
+        }
+        let pubx = pubx
+            .iter()
+            .rev()
+            .filter(|x| x.id == PROPRIETARY && x.data.starts_with("03,"))
+            .find_map(|x| parse_pubx03(&x.data).ok());
+        for sat in pubx.unwrap_or_default() {
+            let Some(svid) = GnssSatellite::from_pubx(sat.svid) else {
+                continue;
+            };
+            self.sat_views.entry(svid).or_insert((
+                sat.elevation.unwrap_or_default(),
+                sat.azimuth.unwrap_or_default(),
+            ));
+            if sat.used {
+                self.used_in_fix.insert(svid);
+            }
         }
     }
-}
 
-fn parse_lat(inp: &str, dir: &str) -> Result<f64, GpsError> {
-    lazy_static! {
-        static ref RE: Regex =
-            Regex::new(r"(?<deg>\d{2})(?<min>\d{2}\.\d{5})").expect("Failed to compile regex");
+    /// Get the quality of the fix
+    pub fn fix_quality(&self) -> FixQuality {
+        self.quality.into()
     }
-    if let Some(inp) = RE.captures(inp) {
-        let deg = inp["deg"]
-            .parse::<f64>()
-            .map_err(|_| GpsError::ParseError("Latitude degrees".into()))?;
-        let min = inp["min"]
-            .parse::<f64>()
-            .map_err(|_| GpsError::ParseError("Latitude minutes".into()))?;
-        let lat = deg + min / 60.0;
-        Ok(if dir == "S" { -lat } else { lat })
-    } else {
-        Err(GpsError::PatternNotFound)
+
+    /// Get the type of the fix, from the GSA fix mode, or else from the
+    /// quality of the fix
+    ///
+    /// # Returns
+    /// - The type of the fix, or `None` if it is not known
+    pub fn fix_type(&self) -> Option<FixMode> {
+        match (
+            self.fix_quality(),
+            self.fix_mode.and_then(FixMode::from_gsa),
+        ) {
+            (FixQuality::DeadReckoning, _) => Some(FixMode::DeadReckoning),
+            (_, Some(mode)) => Some(mode),
+            (FixQuality::Invalid, None) => Some(FixMode::NoFix),
+            _ => None,
+        }
     }
-}
 
-fn parse_lon(inp: &str, dir: &str) -> Result<f64, GpsError> {
-    lazy_static! {
-        static ref RE: Regex =
-            Regex::new(r"(?<deg>\d{3})(?<min>\d{2}\.\d{5})").expect("Failed to compile regex");
+    /// Get the SNR of a signal of a satellite in view
+    ///
+    /// # Returns
+    /// - Signal-to-noise ratio (dB-Hz), or `None` if the signal is not
+    ///   in view or not tracked
+    pub fn snr(&self, sat: &GnssSatellite, channel: &GnssFreq) -> Option<u8> {
+        self.signals
+            .get(sat)?
+            .iter()
+            .find(|x| x.channel == *channel)?
+            .snr
     }
-    if let Some(inp) = RE.captures(inp) {
-        let deg = inp["deg"]
-            .parse::<f64>()
-            .map_err(|_| GpsError::ParseError("Longitude degrees".into()))?;
-        let min = inp["min"]
-            .parse::<f64>()
-            .map_err(|_| GpsError::ParseError("Longitude minutes".into()))?;
-        let lon = deg + min / 60.0;
-        Ok(if dir == "W" { -lon } else { lon })
-    } else {
-        Err(GpsError::PatternNotFound)
+}
+
+/// Parse the most recent valid sentence of a kind.
+///
+/// # Errors
+/// - [`GpsError::NoFix`] if there are no sentences, or the error of the
+///   most recent sentence if none of them is valid
+fn latest<T>(
+    msgs: Option<Vec<RawNmea>>,
+    parse: fn(&str) -> Result<T, GpsError>,
+) -> Result<T, GpsError> {
+    let mut err = None;
+    for msg in msgs.unwrap_or_default().iter().rev() {
+        match parse(&msg.data) {
+            Ok(val) => return Ok(val),
+            Err(e) => {
+                err.get_or_insert(e);
+            }
+        }
     }
+    Err(err.unwrap_or(GpsError::NoFix))
+}
+
+/// Fix data from a GGA sentence
+#[derive(Debug, Clone, PartialEq)]
+struct Gga {
+    time: Option<NaiveTime>,
+    lat: f64,
+    lon: f64,
+    quality: FixQuality,
+    num_sats: Option<u8>,
+    hdop: Option<f32>,
+    alt: f64,
+    sep: Option<f64>,
+}
+
+/// Course and speed from a VTG sentence
+#[derive(Debug, Clone, PartialEq)]
+struct Vtg {
+    true_heading: Option<f32>,
+    mag_heading: Option<f32>,
+    speed_knots: Option<f32>,
+    speed_kmh: Option<f32>,
+    mode: Option<char>,
+}
+
+/// Dilution of precision and satellites used in the fix from a GSA sentence
+#[derive(Debug, Clone, PartialEq)]
+struct Gsa {
+    mode: Option<char>,
+    fix_type: Option<u8>,
+    svids: Vec<u8>,
+    pdop: Option<f32>,
+    hdop: Option<f32>,
+    vdop: Option<f32>,
+    system_id: Option<u8>,
+}
+
+/// A satellite in view from a GSV sentence
+#[derive(Debug, Clone, PartialEq)]
+struct GsvSat {
+    svid: u8,
+    elevation: Option<i8>,
+    azimuth: Option<u16>,
+    snr: Option<u8>,
+}
+
+/// Satellites in view from a GSV sentence
+#[derive(Debug, Clone, PartialEq)]
+struct Gsv {
+    sats: Vec<GsvSat>,
+    signal_id: Option<u8>,
+}
+
+/// Recommended minimum data from an RMC sentence
+#[derive(Debug, Clone, PartialEq)]
+struct Rmc {
+    time: Option<NaiveTime>,
+    valid: bool,
+    lat: Option<f64>,
+    lon: Option<f64>,
+    speed_knots: Option<f32>,
+    course: Option<f32>,
+    date: Option<NaiveDate>,
+    mag_variation: Option<f32>,
+    mode: Option<char>,
+    nav_status: Option<char>,
+}
+
+/// Position from a GLL sentence
+#[derive(Debug, Clone, PartialEq)]
+struct Gll {
+    lat: Option<f64>,
+    lon: Option<f64>,
+    time: Option<NaiveTime>,
+    valid: bool,
+    mode: Option<char>,
+}
+
+/// Fix data from a GNS sentence
+#[derive(Debug, Clone, PartialEq)]
+struct Gns {
+    time: Option<NaiveTime>,
+    lat: Option<f64>,
+    lon: Option<f64>,
+    modes: HashMap<Constellation, char>,
+    num_sats: Option<u8>,
+    hdop: Option<f32>,
+    alt: Option<f64>,
+    sep: Option<f64>,
+    nav_status: Option<char>,
+}
+
+/// Position and accuracy from a u-blox PUBX,00 sentence
+#[derive(Debug, Clone, PartialEq)]
+struct PubxPosition {
+    lat: Option<f64>,
+    lon: Option<f64>,
+    alt: Option<f64>,
+    quality: FixQuality,
+    speed_kmh: Option<f32>,
+    course: Option<f32>,
+    hdop: Option<f32>,
+    vdop: Option<f32>,
+    num_sats: Option<u8>,
+    accuracy: PositionAccuracy,
+}
+
+/// A satellite from a u-blox PUBX,03 sentence
+#[derive(Debug, Clone, PartialEq)]
+struct PubxSat {
+    svid: u16,
+    used: bool,
+    azimuth: Option<u16>,
+    elevation: Option<i8>,
+}
+
+/// Time and clock information from a u-blox PUBX,04 sentence
+#[derive(Debug, Clone, PartialEq)]
+struct PubxTime {
+    time: Option<NaiveTime>,
+    date: Option<NaiveDate>,
+    clock: ClockInfo,
 }
 
 fn parse_zda(inp: &str) -> Result<DateTime<Utc>, GpsError> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(
-            r"(?<hour>\d{2})(?<minute>\d{2})(?<second>\d{2}\.\d{2}),(?<day>\d{2}),(?<month>\d{2}),(?<year>\d{4})"
-        )
-        .expect("Failed to compile regex");
+    let fields = Fields::new("ZDA", inp);
+    let time = fields.time(0)?.ok_or(GpsError::MissingField {
+        sentence: "ZDA",
+        field: "time",
+        index: 0,
+    })?;
+    let day = fields.parse_required(1, "day")?;
+    let month = fields.parse_required(2, "month")?;
+    let year = fields.parse_required(3, "year")?;
+    let date = NaiveDate::from_ymd_opt(year, month, day).ok_or(fields.invalid(1, "date"))?;
+    Ok(date.and_time(time).and_utc())
+}
+
+fn parse_gga(inp: &str) -> Result<Gga, GpsError> {
+    let fields = Fields::new("GGA", inp);
+    let quality = fields.required(5, "quality")?;
+    let quality = quality
+        .parse::<u8>()
+        .map_err(|_| fields.invalid(5, "quality"))?;
+    let quality = FixQuality::from(quality);
+    if !quality.is_valid() {
+        return Err(GpsError::NoFix);
     }
-    if let Some(inp) = RE.captures(inp) {
-        let inp = format!(
-            "{}-{}-{}T{}:{}:{}0",
-            &inp["year"], &inp["month"], &inp["day"], &inp["hour"], &inp["minute"], &inp["second"]
-        );
-        #[allow(deprecated)]
-        TimeZone::datetime_from_str(&Utc, &inp, "%Y-%m-%dT%H:%M:%S%.f")
-            .map_err(|e| GpsError::ParseError(e.to_string()))
-    } else {
-        Err(GpsError::PatternNotFound)
+    let missing = |index, field| GpsError::MissingField {
+        sentence: "GGA",
+        field,
+        index,
+    };
+    Ok(Gga {
+        time: fields.time(0)?,
+        lat: fields.latitude(1)?.ok_or(missing(1, "latitude"))?,
+        lon: fields.longitude(3)?.ok_or(missing(3, "longitude"))?,
+        quality,
+        num_sats: fields.parse(6, "satellites")?,
+        hdop: fields.parse(7, "HDOP")?,
+        alt: fields.parse_required(8, "altitude")?,
+        sep: fields.parse(10, "geoid separation")?,
+    })
+}
+
+fn parse_vtg(inp: &str) -> Result<Vtg, GpsError> {
+    let fields = Fields::new("VTG", inp);
+    Ok(Vtg {
+        true_heading: fields.parse(0, "true course")?,
+        mag_heading: fields.parse(2, "magnetic course")?,
+        speed_knots: fields.parse(4, "speed (knots)")?,
+        speed_kmh: fields.parse(6, "speed (km/h)")?,
+        mode: fields.char(8, "mode")?,
+    })
+}
+
+fn parse_gsa(inp: &str) -> Result<Gsa, GpsError> {
+    let fields = Fields::new("GSA", inp);
+    let svids = (2..14)
+        .filter_map(|i| fields.parse(i, "satellite ID").transpose())
+        .collect::<Result<_, _>>()?;
+    Ok(Gsa {
+        mode: fields.char(0, "mode")?,
+        fix_type: fields.parse(1, "fix type")?,
+        svids,
+        pdop: fields.parse(14, "PDOP")?,
+        hdop: fields.parse(15, "HDOP")?,
+        vdop: fields.parse(16, "VDOP")?,
+        // NMEA 4.10
+        system_id: fields.parse(17, "system ID")?,
+    })
+}
+
+fn parse_gsv(inp: &str) -> Result<Gsv, GpsError> {
+    let fields = Fields::new("GSV", inp);
+    let blocks = fields.len().saturating_sub(3) / 4;
+    let mut sats = Vec::with_capacity(blocks);
+    for blk in 0..blocks {
+        let idx = 3 + 4 * blk;
+        let Some(svid) = fields.parse(idx, "satellite ID")? else {
+            continue;
+        };
+        sats.push(GsvSat {
+            svid,
+            elevation: fields.parse(idx + 1, "elevation")?,
+            azimuth: fields.parse(idx + 2, "azimuth")?,
+            snr: fields.parse(idx + 3, "SNR")?,
+        });
     }
+    // NMEA 4.10 signal ID, after the satellite blocks
+    let signal_id = match fields.len() {
+        n if n > 3 && (n - 3) % 4 == 1 => fields
+            .get(n - 1)
+            .map(|x| u8::from_str_radix(x, 16).map_err(|_| fields.invalid(n - 1, "signal ID")))
+            .transpose()?,
+        _ => None,
+    };
+    Ok(Gsv { sats, signal_id })
+}
+
+/// Get the location of a fix from its altitude above mean sea level
+///
+/// The height above the ellipsoid uses the geoid separation reported by
+/// the receiver, or else the geoid model.
+///
+/// # Returns
+/// - The location and the geoid separation, or a height of `0` and `None`
+///   if the geoid separation is not known
+fn msl_location(
+    lat: f64,
+    lon: f64,
+    msl: f64,
+    sep: Option<f64>,
+    geoid: Option<&Egm96>,
+) -> (Geodetic, Option<f32>) {
+    if let Some(sep) = sep {
+        return (Geodetic::new(lat, lon, msl + sep), Some(sep as f32));
+    }
+    match geoid.and_then(|geoid| Geodetic::from_msl(lat, lon, msl, geoid)) {
+        Some(loc) => (loc, Some((loc.height - msl) as f32)),
+        None => (Geodetic::new(lat, lon, 0.0), None),
+    }
+}
+
+/// Get a satellite used in the fix from a GSA sentence
+///
+/// The system is identified by the system ID (NMEA 4.10 and later), or
+/// else by the talker ID.
+fn gsa_satellite(talker: &[u8; 2], system_id: Option<u8>, svid: u8) -> Option<GnssSatellite> {
+    let talker = match system_id {
+        Some(1) => b"GP",
+        Some(2) => b"GL",
+        Some(3) => b"GA",
+        Some(4) => b"GB",
+        Some(5) => b"GQ",
+        Some(6) => b"GI",
+        Some(_) => return None,
+        None => talker,
+    };
+    GnssSatellite::from_nmea(talker, svid)
+}
+
+/// Get the frequency channel of an NMEA signal ID (NMEA 4.10 and later)
+///
+/// Signals that NMEA does not distinguish, such as the data and pilot
+/// components of Galileo E1, map to the first of them. Signal ID `0`
+/// (all signals) and unknown signals map to `None`.
+fn nmea_signal(sat: &GnssSatellite, signal_id: u8) -> Option<GnssFreq> {
+    use GnssSatellite::*;
+    let freq = match (sat, signal_id) {
+        (Gps(_) | Sbas(_), 1) => GpsFreq::L1CA.into(),
+        (Gps(_), 5) => GpsFreq::L2CM.into(),
+        (Gps(_), 6) => GpsFreq::L2CL.into(),
+        (Gps(_), 7 | 8) => GpsFreq::L5.into(),
+        (Gps(_), 9 | 0xA) => GpsFreq::L1C.into(),
+        (Glonass(_), 1) => GlonassFreq::L1OF(0).into(),
+        (Glonass(_), 3) => GlonassFreq::L2OF(0).into(),
+        (Galileo(_), 7) => GalileoFreq::E1C.into(),
+        (Galileo(_), 1) => GalileoFreq::E5aI.into(),
+        (Galileo(_), 2) => GalileoFreq::E5bI.into(),
+        (Galileo(_), 5) => GalileoFreq::E6B.into(),
+        (Beidou(_), 1) => BeidouFreq::B1I_D1.into(),
+        (Beidou(_), 3) => BeidouFreq::B1C.into(),
+        (Beidou(_), 0xB) => BeidouFreq::B2I_D1.into(),
+        (Beidou(_), 5) => BeidouFreq::B2A.into(),
+        (Beidou(_), 6) => BeidouFreq::B2b.into(),
+        (Beidou(_), 8) => BeidouFreq::B3I_D1.into(),
+        (Qzss(_), 1) => QzssFreq::L1CA.into(),
+        (Qzss(_), 4) => QzssFreq::L1S.into(),
+        (Qzss(_), 5) => QzssFreq::L2CM.into(),
+        (Qzss(_), 6) => QzssFreq::L2CL.into(),
+        (Qzss(_), 7 | 8) => QzssFreq::L5.into(),
+        (Navic(_), 1) => NavicFreq::L5A.into(),
+        _ => return None,
+    };
+    Some(freq)
+}
+
+/// Get the NMEA signal ID of a frequency channel (NMEA 4.10 and later),
+/// the inverse of [`nmea_signal`]
+pub(crate) fn nmea_signal_id(channel: &GnssFreq) -> Option<u8> {
+    use BeidouFreq::*;
+    use GalileoFreq::*;
+    let id = match channel {
+        GnssFreq::Gps(GpsFreq::L1CA) => 1,
+        GnssFreq::Gps(GpsFreq::L2CM) => 5,
+        GnssFreq::Gps(GpsFreq::L2CL) => 6,
+        GnssFreq::Gps(GpsFreq::L5) => 7,
+        GnssFreq::Gps(GpsFreq::L1C) => 9,
+        GnssFreq::Glonass(GlonassFreq::L1OF(_)) => 1,
+        GnssFreq::Glonass(GlonassFreq::L2OF(_)) => 3,
+        GnssFreq::Galileo(E1C | E1B) => 7,
+        GnssFreq::Galileo(E5aI | E5aQ) => 1,
+        GnssFreq::Galileo(E5bI | E5bQ) => 2,
+        GnssFreq::Galileo(E6B | E6C) => 5,
+        GnssFreq::Beidou(B1I_D1 | B1I_D2) => 1,
+        GnssFreq::Beidou(B1C) => 3,
+        GnssFreq::Beidou(B2A) => 5,
+        GnssFreq::Beidou(B2b) => 6,
+        GnssFreq::Beidou(B3I_D1 | B3I_D2) => 8,
+        GnssFreq::Beidou(B2I_D1 | B2I_D2) => 0xB,
+        GnssFreq::Qzss(QzssFreq::L1CA) => 1,
+        GnssFreq::Qzss(QzssFreq::L1S) => 4,
+        GnssFreq::Qzss(QzssFreq::L2CM) => 5,
+        GnssFreq::Qzss(QzssFreq::L2CL) => 6,
+        GnssFreq::Qzss(QzssFreq::L5) => 7,
+        GnssFreq::Navic(NavicFreq::L5A) => 1,
+        GnssFreq::Qzss(QzssFreq::L1CB) | GnssFreq::Unknown { .. } => return None,
+    };
+    Some(id)
 }
 
-fn parse_gga(inp: &str) -> Result<Captures, GpsError> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(
-            r"\d{6}\.\d{2},(?<lat>[\d\.]*),(?<lat_dir>[NS]),(?<lon>[\d\.]*),(?<lon_dir>[EW]),(?<quality>[0-9A-F]),(?<sat_views>\d*),[\d\.]*,(?<alt>[\-\d\.]*),M,(?<msl>[\-\d\.]*),M,(?<sep>[\-\d\.]*),"
-        ).expect("Failed to compile regex");
+impl Rmc {
+    /// Get the latitude, longitude and GGA fix quality, if the
+    /// position is valid
+    fn fix(&self) -> Option<(f64, f64, u8)> {
+        let quality = self
+            .mode
+            .map_or(FixQuality::Autonomous, FixQuality::from_mode);
+        if !self.valid || !quality.is_valid() {
+            return None;
+        }
+        Some((self.lat?, self.lon?, quality.into()))
     }
-    RE.captures(inp).ok_or(GpsError::PatternNotFound)
 }
 
-fn parse_vtg(inp: &str) -> Result<Captures, GpsError> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(
-            r"(?<true_heading>[\-\d\.]*),T,(?<mag_heading>[\-\d\.]*),M,[\d\.]*,N,(?<ground_speed>[\d\.]*),K,"
-        ).expect("Failed to compile regex");
+impl PubxPosition {
+    /// Get the latitude, longitude and height above the ellipsoid, if
+    /// the position is valid
+    fn fix(&self) -> Option<(f64, f64, f64)> {
+        if !self.quality.is_valid() {
+            return None;
+        }
+        Some((self.lat?, self.lon?, self.alt.unwrap_or_default()))
     }
-    RE.captures(inp).ok_or(GpsError::PatternNotFound)
 }
 
-fn parse_gsa(inp: &str) -> Result<Captures, GpsError> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(
-            r"[AM],\d,\d*,\d*,\d*,\d*,\d*,\d*,\d*,\d*,\d*,\d*,\d*,\d*,(?<pdop>[\d\.]*),(?<hdop>[\d\.]*),(?<vdop>[\d\.]*),"
-        ).expect("Failed to compile regex");
+impl Gns {
+    /// Get the latitude, longitude and GGA fix quality of the best
+    /// constellation mode, if the position is valid
+    fn fix(&self) -> Option<(f64, f64, u8)> {
+        // Modes from the most to the least accurate
+        const RANK: &str = "RFPDAEMS";
+        let quality = self
+            .modes
+            .values()
+            .filter_map(|m| RANK.find(*m).map(|rank| (rank, *m)))
+            .min()?
+            .1;
+        Some((self.lat?, self.lon?, FixQuality::from_mode(quality).into()))
     }
-    RE.captures(inp).ok_or(GpsError::PatternNotFound)
 }
 
-fn parse_gsv(inp: &str) -> Result<Vec<(u8, i8, u16)>, GpsError> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(r"\d*,\d*,\d*,(?<payload>[\d\,]*)[0-9A-F]")
-            .expect("Failed to compile regex");
-        static ref XTRACT: Regex =
-            Regex::new(r"(?<svid>\d*),(?<elevation>\d*),(?<azimuth>\d*),(?<snr>\d*),")
-                .expect("Failed to compile regex");
+fn parse_status(fields: &Fields, index: usize) -> Result<bool, GpsError> {
+    match fields.char(index, "status")? {
+        Some('A') => Ok(true),
+        Some('V') => Ok(false),
+        Some(_) => Err(fields.invalid(index, "status")),
+        None => Err(GpsError::MissingField {
+            sentence: fields.sentence(),
+            field: "status",
+            index,
+        }),
     }
-    let cap = RE.captures(inp).ok_or(GpsError::PatternNotFound)?;
-    Ok(XTRACT
-        .captures_iter(&cap["payload"])
-        .map(|x| {
-            (
-                x["svid"].parse::<u8>().unwrap_or_default(),
-                x["elevation"].parse::<i8>().unwrap_or_default(),
-                x["azimuth"].parse::<u16>().unwrap_or_default(),
-            )
+}
+
+fn parse_rmc(inp: &str) -> Result<Rmc, GpsError> {
+    let fields = Fields::new("RMC", inp);
+    let mag_variation = fields
+        .parse::<f32>(9, "magnetic variation")?
+        .map(
+            |var| match fields.char(10, "magnetic variation direction")? {
+                Some('E') | None => Ok(var),
+                Some('W') => Ok(-var),
+                Some(_) => Err(fields.invalid(10, "magnetic variation direction")),
+            },
+        )
+        .transpose()?;
+    Ok(Rmc {
+        time: fields.time(0)?,
+        valid: parse_status(&fields, 1)?,
+        lat: fields.latitude(2)?,
+        lon: fields.longitude(4)?,
+        speed_knots: fields.parse(6, "speed")?,
+        course: fields.parse(7, "course")?,
+        date: fields.date(8)?,
+        mag_variation,
+        mode: fields.char(11, "mode")?,
+        // NMEA 4.10
+        nav_status: fields.char(12, "navigational status")?,
+    })
+}
+
+fn parse_gll(inp: &str) -> Result<Gll, GpsError> {
+    let fields = Fields::new("GLL", inp);
+    Ok(Gll {
+        lat: fields.latitude(0)?,
+        lon: fields.longitude(2)?,
+        time: fields.time(4)?,
+        valid: parse_status(&fields, 5)?,
+        mode: fields.char(6, "mode")?,
+    })
+}
+
+fn parse_gns(inp: &str) -> Result<Gns, GpsError> {
+    let fields = Fields::new("GNS", inp);
+    let modes = fields
+        .get(5)
+        .unwrap_or_default()
+        .chars()
+        .zip(Constellation::GNS_ORDER)
+        .map(|(mode, cons)| match mode {
+            'A' | 'D' | 'E' | 'F' | 'M' | 'N' | 'P' | 'R' | 'S' => Ok((cons, mode)),
+            _ => Err(fields.invalid(5, "mode")),
         })
-        .collect::<Vec<_>>())
+        .collect::<Result<_, _>>()?;
+    Ok(Gns {
+        time: fields.time(0)?,
+        lat: fields.latitude(1)?,
+        lon: fields.longitude(3)?,
+        modes,
+        num_sats: fields.parse(6, "satellites")?,
+        hdop: fields.parse(7, "HDOP")?,
+        alt: fields.parse(8, "altitude")?,
+        sep: fields.parse(9, "geoid separation")?,
+        // NMEA 4.10
+        nav_status: fields.char(12, "navigational status")?,
+    })
+}
+
+fn parse_pubx00(inp: &str) -> Result<PubxPosition, GpsError> {
+    let fields = Fields::new("PUBX,00", inp);
+    // Navigation status, as a GGA fix quality
+    let quality = match fields.required(7, "navigation status")? {
+        "NF" | "TT" => FixQuality::Invalid,
+        "G2" | "G3" | "RK" => FixQuality::Autonomous,
+        "D2" | "D3" => FixQuality::Differential,
+        "DR" => FixQuality::DeadReckoning,
+        _ => return Err(fields.invalid(7, "navigation status")),
+    };
+    Ok(PubxPosition {
+        lat: fields.latitude(2)?,
+        lon: fields.longitude(4)?,
+        alt: fields.parse(6, "altitude")?,
+        quality,
+        speed_kmh: fields.parse(10, "speed")?,
+        course: fields.parse(11, "course")?,
+        hdop: fields.parse(14, "HDOP")?,
+        vdop: fields.parse(15, "VDOP")?,
+        num_sats: fields.parse(17, "satellites")?,
+        accuracy: PositionAccuracy {
+            horizontal: fields.parse(8, "horizontal accuracy")?,
+            vertical: fields.parse(9, "vertical accuracy")?,
+            vertical_velocity: fields.parse(12, "vertical velocity")?,
+            diff_age: fields.parse(13, "age of corrections")?,
+            tdop: fields.parse(16, "TDOP")?,
+        },
+    })
+}
+
+fn parse_pubx03(inp: &str) -> Result<Vec<PubxSat>, GpsError> {
+    let fields = Fields::new("PUBX,03", inp);
+    let count: usize = fields.parse_required(1, "satellites")?;
+    (0..count)
+        .map(|blk| {
+            let idx = 2 + 6 * blk;
+            Ok(PubxSat {
+                svid: fields.parse_required(idx, "satellite ID")?,
+                used: match fields.char(idx + 1, "status")? {
+                    Some('U') => true,
+                    Some('e' | '-') | None => false,
+                    Some(_) => return Err(fields.invalid(idx + 1, "status")),
+                },
+                azimuth: fields.parse(idx + 2, "azimuth")?,
+                elevation: fields.parse(idx + 3, "elevation")?,
+            })
+        })
+        .collect()
+}
+
+fn parse_pubx04(inp: &str) -> Result<PubxTime, GpsError> {
+    let fields = Fields::new("PUBX,04", inp);
+    // Leap seconds with a `D` suffix are the firmware default
+    let leap = fields.get(5).unwrap_or_default();
+    let (leap, leap_seconds_default) = match leap.strip_suffix('D') {
+        Some(leap) => (leap, true),
+        None => (leap, false),
+    };
+    let leap_seconds = match leap {
+        "" => None,
+        x => Some(x.parse().map_err(|_| fields.invalid(5, "leap seconds"))?),
+    };
+    Ok(PubxTime {
+        time: fields.time(1)?,
+        date: fields.date(2)?,
+        clock: ClockInfo {
+            utc_tow: fields.parse(3, "UTC time of week")?,
+            utc_week: fields.parse(4, "UTC week")?,
+            leap_seconds,
+            leap_seconds_default,
+            clock_bias: fields.parse(6, "clock bias")?,
+            clock_drift: fields.parse(7, "clock drift")?,
+            timepulse_granularity: fields.parse(8, "time pulse granularity")?,
+        },
+    })
+}
+
+fn parse_gst(inp: &str) -> Result<PositionErrors, GpsError> {
+    let fields = Fields::new("GST", inp);
+    Ok(PositionErrors {
+        rms: fields.parse(1, "range RMS")?,
+        semi_major: fields.parse(2, "semi-major axis")?,
+        semi_minor: fields.parse(3, "semi-minor axis")?,
+        orientation: fields.parse(4, "orientation")?,
+        lat: fields.parse(5, "latitude error")?,
+        lon: fields.parse(6, "longitude error")?,
+        alt: fields.parse(7, "altitude error")?,
+    })
 }
 
 mod test {
@@ -474,27 +1587,487 @@ mod test {
 22:15:15  $GNGLL,4238.96342,N,07118.97943,W,221515.00,A,D*68
 22:15:15  $GNZDA,221515.00,03,10,2024,00,00*7E";
         let mut nmea = super::RawNmea::parse_str(payload);
-        // println!("{:#?}", nmea);
-        // nmea[b"GGA"]
-        //     .iter()
-        //     .for_each(|x| println!("{:?}", super::parse_gga(&x.data)));
-        // nmea[b"ZDA"]
-        //     .iter()
-        //     .for_each(|x| println!("{:?}", super::parse_zda(&x.data)));
-        // nmea[b"VTG"]
-        //     .iter()
-        //     .for_each(|x| println!("{:?}", super::parse_vtg(&x.data)));
-        // nmea[b"GSA"]
-        //     .iter()
-        //     .for_each(|x| println!("{:?}", super::parse_gsa(&x.data)));
-        // nmea[b"GSV"].iter().for_each(|x| {
-        //     println!(
-        //         "{}: {:?}",
-        //         str::from_utf8(&x.id).unwrap_or(""),
-        //         super::parse_gsv(&x.data)
-        //     )
-        // });
-        println!("{:?}", super::NmeaGpsInfo::create(&mut nmea, false));
-        println!("{:?}", nmea);
+        assert_eq!(nmea.0[b"GSV"].len(), 18);
+        assert_eq!(nmea.0[b"GSA"].len(), 5);
+        let info = super::NmeaGpsInfo::create(&mut nmea, true, None).unwrap();
+        assert_eq!(info.time.to_rfc3339(), "2024-10-03T22:15:15+00:00");
+        assert!((info.loc.lat - (42.0 + 38.96342 / 60.0)).abs() < 1e-12);
+        assert!((info.loc.lon + 71.0 + 18.97943 / 60.0).abs() < 1e-12);
+        assert_eq!(info.msl, 36.7);
+        assert!((info.loc.height - 3.7).abs() < 1e-6);
+        assert_eq!(info.quality, 2);
+        assert_eq!(info.ground_speed, 0.086);
+        assert_eq!((info.pdop, info.hdop, info.vdop), (1.83, 1.04, 1.51));
+        // Satellites used in the fix, from all five GSA sentences
+        assert_eq!((info.fix_mode, info.selection_mode), (Some(3), Some('A')));
+        assert_eq!(info.fix_type(), Some(crate::FixMode::Fix3D));
+        assert_eq!(info.fix_quality(), crate::FixQuality::Differential);
+        assert_eq!(info.used_in_fix.len(), 19);
+        assert!(info.used_in_fix.contains(&super::GnssSatellite::Gps(27)));
+        assert!(info
+            .used_in_fix
+            .contains(&super::GnssSatellite::Glonass(14)));
+        assert!(info
+            .used_in_fix
+            .contains(&super::GnssSatellite::Galileo(29)));
+        assert!(info.used_in_fix.contains(&super::GnssSatellite::Beidou(41)));
+        assert!(!info.used_in_fix.contains(&super::GnssSatellite::Gps(9)));
+        assert_eq!(info.sat_views[&super::GnssSatellite::Glonass(14)], (65, 4));
+        assert_eq!(info.sat_views.len(), 38);
+        // Per-signal SNR
+        use crate::{BeidouFreq, GalileoFreq, GlonassFreq, GpsFreq};
+        let gps3 = super::GnssSatellite::Gps(3);
+        assert_eq!(info.signals[&gps3].len(), 2);
+        assert_eq!(info.snr(&gps3, &GpsFreq::L1CA.into()), Some(42));
+        assert_eq!(info.snr(&gps3, &GpsFreq::L2CL.into()), Some(27));
+        assert_eq!(info.snr(&gps3, &GpsFreq::L5.into()), None);
+        let glo = super::GnssSatellite::Glonass(3);
+        assert_eq!(info.snr(&glo, &GlonassFreq::L2OF(0).into()), Some(34));
+        let gal = super::GnssSatellite::Galileo(21);
+        assert_eq!(info.signals[&gal].len(), 1);
+        assert_eq!(info.snr(&gal, &GalileoFreq::E1C.into()), Some(9));
+        let bds = super::GnssSatellite::Beidou(41);
+        assert_eq!(info.snr(&bds, &BeidouFreq::B2I_D1.into()), Some(8));
+        // Satellites in view without tracked signals
+        assert!(!info.signals.contains_key(&super::GnssSatellite::Gps(9)));
+        // RMC and GLL are consumed
+        assert!(!nmea.0.contains_key(b"RMC"));
+        assert!(!nmea.0.contains_key(b"GLL"));
+        assert_eq!(info.date, chrono::NaiveDate::from_ymd_opt(2024, 10, 3));
+        assert_eq!((info.valid, info.mode), (Some(true), Some('D')));
+        assert_eq!((info.course, info.nav_status), (None, Some('V')));
+        assert_eq!(info.num_sats, Some(12));
+        assert!(info.uncertain_location().is_none());
+    }
+
+    #[test]
+    fn parse_satellites() {
+        use super::*;
+        // NMEA numbering
+        let nmea = |talker, svid| GnssSatellite::from_nmea(talker, svid);
+        assert_eq!(nmea(b"GP", 26), Some(GnssSatellite::Gps(26)));
+        assert_eq!(nmea(b"GP", 44), Some(GnssSatellite::Sbas(131)));
+        assert_eq!(nmea(b"GN", 155), Some(GnssSatellite::Sbas(155)));
+        assert_eq!(nmea(b"GL", 65), Some(GnssSatellite::Glonass(1)));
+        assert_eq!(nmea(b"GN", 96), Some(GnssSatellite::Glonass(32)));
+        assert_eq!(nmea(b"GQ", 3), Some(GnssSatellite::Qzss(3)));
+        assert_eq!(nmea(b"GQ", 195), Some(GnssSatellite::Qzss(3)));
+        assert_eq!(nmea(b"GB", 41), Some(GnssSatellite::Beidou(41)));
+        assert_eq!(nmea(b"GL", 5), None);
+        assert_eq!(nmea(b"GL", 255), None);
+        assert_eq!(nmea(b"GA", 0), None);
+        assert_eq!(nmea(b"GI", 5), Some(GnssSatellite::Navic(5)));
+        assert_eq!(nmea(b"GI", 15), None);
+        // UBX numbering
+        assert_eq!(
+            GnssSatellite::from_ubx(1, 123),
+            Some(GnssSatellite::Sbas(123))
+        );
+        assert_eq!(GnssSatellite::from_ubx(6, 255), None);
+        assert_eq!(GnssSatellite::from_ubx(7, 5), Some(GnssSatellite::Navic(5)));
+        assert_eq!(GnssSatellite::from_ubx(4, 5), None);
+        // u-blox numbering
+        assert_eq!(GnssSatellite::from_pubx(44), Some(GnssSatellite::Sbas(131)));
+        assert_eq!(
+            GnssSatellite::from_pubx(160),
+            Some(GnssSatellite::Beidou(2))
+        );
+        assert_eq!(
+            GnssSatellite::from_pubx(211),
+            Some(GnssSatellite::Galileo(1))
+        );
+        assert_eq!(
+            GnssSatellite::from_pubx(336),
+            Some(GnssSatellite::Galileo(36))
+        );
+        assert_eq!(
+            GnssSatellite::from_pubx(463),
+            Some(GnssSatellite::Beidou(63))
+        );
+        assert_eq!(GnssSatellite::from_pubx(255), None);
+        assert_eq!(GnssSatellite::from_pubx(337), None);
+        // RINEX IDs
+        assert_eq!(GnssSatellite::Gps(26).to_string(), "G26");
+        assert_eq!(GnssSatellite::Sbas(123).to_string(), "S23");
+        assert_eq!(GnssSatellite::Glonass(5).to_string(), "R05");
+        assert_eq!(
+            "C07".parse::<GnssSatellite>().unwrap(),
+            GnssSatellite::Beidou(7)
+        );
+        assert_eq!(
+            "G 5".parse::<GnssSatellite>().unwrap(),
+            GnssSatellite::Gps(5)
+        );
+        assert_eq!(
+            "S58".parse::<GnssSatellite>().unwrap(),
+            GnssSatellite::Sbas(158)
+        );
+        assert!("G33".parse::<GnssSatellite>().is_err());
+        assert!("X01".parse::<GnssSatellite>().is_err());
+        assert!("G".parse::<GnssSatellite>().is_err());
+        assert!("G-1".parse::<GnssSatellite>().is_err());
+        // Serialization, with the legacy hexadecimal form
+        let de = |s: &str| serde_json::from_str::<GnssSatellite>(&format!("{:?}", s));
+        assert_eq!(de("G10").unwrap(), GnssSatellite::Gps(16));
+        assert_eq!(de("E11").unwrap(), GnssSatellite::Galileo(17));
+        assert_eq!(de("G1A").unwrap(), GnssSatellite::Gps(26));
+        assert_eq!(de("S83").unwrap(), GnssSatellite::Sbas(131));
+        assert!(de("G2F").is_err());
+        assert_eq!(de("G016").unwrap(), GnssSatellite::Gps(16));
+        assert_eq!(
+            serde_json::to_string(&GnssSatellite::Sbas(131)).unwrap(),
+            "\"S031\""
+        );
+        // Recorded satellites in view, keyed by the legacy form
+        let views: HashMap<GnssSatellite, (i8, u16)> =
+            serde_json::from_str(r#"{"G10": [45, 120], "R05": [10, 300]}"#).unwrap();
+        assert_eq!(views[&GnssSatellite::Gps(16)], (45, 120));
+        assert_eq!(views[&GnssSatellite::Glonass(5)], (10, 300));
+        let json = serde_json::to_string(&views).unwrap();
+        assert!(json.contains("\"G016\""), "{}", json);
+        assert_eq!(
+            serde_json::from_str::<HashMap<GnssSatellite, (i8, u16)>>(&json).unwrap(),
+            views
+        );
+        let sats = [
+            GnssSatellite::Gps(1),
+            GnssSatellite::Sbas(158),
+            GnssSatellite::Galileo(36),
+            GnssSatellite::Beidou(63),
+            GnssSatellite::Qzss(10),
+            GnssSatellite::Glonass(24),
+            GnssSatellite::Navic(14),
+        ];
+        for sat in sats {
+            let json = serde_json::to_string(&sat).unwrap();
+            assert_eq!(serde_json::from_str::<GnssSatellite>(&json).unwrap(), sat);
+        }
+        // NMEA signal IDs
+        let (gps, beidou) = (GnssSatellite::Gps(5), GnssSatellite::Beidou(30));
+        assert_eq!(nmea_signal(&gps, 9), Some(GpsFreq::L1C.into()));
+        assert_eq!(nmea_signal(&gps, 0xA), Some(GpsFreq::L1C.into()));
+        assert_eq!(nmea_signal(&beidou, 6), Some(BeidouFreq::B2b.into()));
+        for (sat, channel) in [
+            (gps, GpsFreq::L1C.into()),
+            (gps, GpsFreq::L5.into()),
+            (beidou, BeidouFreq::B2b.into()),
+            (beidou, BeidouFreq::B2I_D1.into()),
+        ] {
+            let id = nmea_signal_id(&channel).unwrap();
+            assert_eq!(nmea_signal(&sat, id), Some(channel));
+        }
+    }
+
+    #[test]
+    fn parse_sentences() {
+        use super::*;
+        let rmc =
+            parse_rmc("083559.00,A,4717.11437,N,00833.91522,E,0.004,77.52,091202,3.5,W,A").unwrap();
+        assert_eq!(rmc.date, NaiveDate::from_ymd_opt(2002, 12, 9));
+        assert_eq!((rmc.course, rmc.mag_variation), (Some(77.52), Some(-3.5)));
+        assert_eq!(
+            (rmc.valid, rmc.mode, rmc.nav_status),
+            (true, Some('A'), None)
+        );
+        assert!(matches!(
+            parse_rmc("083559.00,X,,,,,,,,,,N,V"),
+            Err(GpsError::InvalidField {
+                field: "status",
+                index: 1,
+                ..
+            })
+        ));
+        let gll = parse_gll("4717.11364,N,00833.91565,E,092321.00,V,N").unwrap();
+        assert!(!gll.valid);
+        assert!((gll.lon.unwrap() - (8.0 + 33.91565 / 60.0)).abs() < 1e-12);
+        let gns =
+            parse_gns("091547.00,5114.50897,N,00012.28663,W,AANN,10,0.83,111.1,45.6,,,V").unwrap();
+        assert_eq!(gns.modes.len(), 4);
+        assert_eq!(gns.modes[&Constellation::Glonass], 'A');
+        assert_eq!(gns.modes[&Constellation::Beidou], 'N');
+        assert_eq!((gns.num_sats, gns.nav_status), (Some(10), Some('V')));
+        assert!(parse_gns("091547.00,,,,,AX,10,0.83,,,,,V").is_err());
+
+        // Uncertainty of the location from GST
+        let payload = "$GNGGA,082242.00,4717.11399,N,00833.91590,E,1,08,1.01,499.6,M,48.0,M,,*40
+$GNZDA,082242.00,09,12,2002,00,00*7C
+$GNGST,082242.00,1.2,0.7,0.5,45.0,1.5,3.0,4.5*71
+";
+        let mut nmea = RawNmea::parse_str(payload);
+        let info = NmeaGpsInfo::create(&mut nmea, false, None).unwrap();
+        let errors = info.errors.unwrap();
+        assert_eq!((errors.rms, errors.orientation), (Some(1.2), Some(45.0)));
+        let (lat, lon, height) = info.uncertain_location().unwrap();
+        assert_eq!(lat.0, info.loc.lat);
+        // One arc-second of latitude is about 30.9 m
+        assert!(
+            (lat.1 * 3600.0 - 1.5 / 30.9).abs() < 1e-3,
+            "{}",
+            lat.1 * 3600.0
+        );
+        assert!(
+            (lon.1 * 3600.0 - 3.0 / 21.0).abs() < 1e-3,
+            "{}",
+            lon.1 * 3600.0
+        );
+        assert!((height.0 - 547.6).abs() < 1e-4);
+        assert_eq!(height.1, 4.5);
+    }
+
+    #[test]
+    fn parse_fields() {
+        use super::*;
+        // Variable precision and empty optional fields
+        let gga = parse_gga("221515.5,4238.9634213,S,07118.98,E,1,,,36.71,M,,M,,").unwrap();
+        assert_eq!(gga.time, NaiveTime::from_hms_milli_opt(22, 15, 15, 500));
+        assert!((gga.lat + 42.0 + 38.963_421_3 / 60.0).abs() < 1e-12);
+        assert!((gga.lon - 71.0 - 18.98 / 60.0).abs() < 1e-12);
+        assert_eq!((gga.num_sats, gga.hdop, gga.sep), (None, None, None));
+        assert_eq!(gga.alt, 36.71);
+        // No fix
+        assert!(matches!(
+            parse_gga("221515.00,,,,,0,00,99.99,,,,,,"),
+            Err(GpsError::NoFix)
+        ));
+        // Per-field errors
+        assert!(matches!(
+            parse_gga("221515.00,4238.96342,N,,W,1,12,1.04,36.7,M,-33.0,M,,"),
+            Err(GpsError::MissingField {
+                field: "longitude",
+                index: 3,
+                ..
+            })
+        ));
+        let err =
+            parse_gga("221515.00,4238.96342,N,07118.97943,W,1,12,1.04,3x,M,-33.0,M,,").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid altitude (field 8) in GGA sentence: \"3x\""
+        );
+        assert!(parse_zda("221515,03,13,2024,00,00").is_err());
+        assert_eq!(
+            parse_zda("221515.1,03,10,2024,,").unwrap().to_rfc3339(),
+            "2024-10-03T22:15:15.100+00:00"
+        );
+        // NMEA 4.10 system ID and signal ID
+        let gsa = parse_gsa("A,3,68,78,79,67,,,,,,,,,1.83,1.04,1.51,2").unwrap();
+        assert_eq!(gsa.svids, vec![68, 78, 79, 67]);
+        assert_eq!(
+            (gsa.mode, gsa.fix_type, gsa.system_id),
+            (Some('A'), Some(3), Some(2))
+        );
+        let gsa = parse_gsa("A,1,,,,,,,,,,,,,99.99,99.99,99.99").unwrap();
+        assert_eq!((gsa.svids.len(), gsa.system_id), (0, None));
+        let gsv = parse_gsv("1,1,02,09,16,316,,28,,,,B").unwrap();
+        assert_eq!(
+            gsv.sats,
+            vec![
+                GsvSat {
+                    svid: 9,
+                    elevation: Some(16),
+                    azimuth: Some(316),
+                    snr: None
+                },
+                GsvSat {
+                    svid: 28,
+                    elevation: None,
+                    azimuth: None,
+                    snr: None
+                }
+            ]
+        );
+        assert_eq!(gsv.signal_id, Some(0xB));
+        let gsv = parse_gsv("3,3,10,44,23,237,44,46,15,247,33").unwrap();
+        assert_eq!((gsv.sats.len(), gsv.signal_id), (2, None));
+        let vtg = parse_vtg(",T,,M,0.046,N,0.086,K,D").unwrap();
+        assert_eq!(vtg.true_heading, None);
+        assert_eq!((vtg.speed_knots, vtg.speed_kmh), (Some(0.046), Some(0.086)));
+        assert_eq!(vtg.mode, Some('D'));
+    }
+
+    #[test]
+    fn parse_fallbacks() {
+        use super::*;
+        let sentences = |body: &[&str]| {
+            let data: String = body
+                .iter()
+                .map(|x| {
+                    let cksum = x.bytes().fold(0, |acc, x| acc ^ x);
+                    format!("${}*{:02X}\r\n", x, cksum)
+                })
+                .collect();
+            RawNmea::parse_str(&data)
+        };
+        let gga = "GNGGA,221515.50,4238.96342,N,07118.97943,W,2,12,1.04,36.7,M,-33.0,M,,";
+        let rmc = "GNRMC,221515.00,A,4238.96342,N,07118.97943,W,0.5,90.0,031024,,,R,V";
+        let gll = "GNGLL,4238.96342,N,07118.97943,W,221515.25,A,D";
+        let gns = "GNGNS,221515.00,4238.96342,N,07118.97943,W,FRNN,09,0.9,40.0,-33.0,,,V";
+
+        // RMC date with GGA time
+        let info = NmeaGpsInfo::create(&mut sentences(&[rmc, gll, gga]), false, None).unwrap();
+        assert_eq!(info.time_source, TimeSource::RmcGga);
+        assert_eq!(info.time.to_rfc3339(), "2024-10-03T22:15:15.500+00:00");
+        assert_eq!(info.quality, 2);
+        // RMC date with GLL time
+        let info = NmeaGpsInfo::create(&mut sentences(&[rmc, gll]), false, None).unwrap();
+        assert_eq!(info.time_source, TimeSource::RmcGll);
+        assert_eq!(info.time.to_rfc3339(), "2024-10-03T22:15:15.250+00:00");
+        // Position from RMC, without altitude
+        assert_eq!(info.fix_quality(), FixQuality::RtkFixed);
+        assert_eq!(info.fix_type(), None);
+        assert!((info.loc.lat - (42.0 + 38.96342 / 60.0)).abs() < 1e-12);
+        assert_eq!((info.msl, info.loc.height), (0.0, 0.0));
+        assert_eq!((info.true_heading, info.ground_speed), (90.0, 0.926));
+        // Position from GNS, with the best constellation mode
+        let info = NmeaGpsInfo::create(&mut sentences(&[gns, rmc]), false, None).unwrap();
+        assert_eq!(info.time_source, TimeSource::Rmc);
+        assert_eq!((info.quality, info.num_sats, info.msl), (4, Some(9), 40.0));
+        assert!((info.loc.height - 7.0).abs() < 1e-9);
+        assert_eq!(info.modes[&Constellation::Gps], 'F');
+        // Without the geoid separation, the height is not known
+        let gga_msl = gga.replace(",-33.0,M,", ",,M,");
+        let info = NmeaGpsInfo::create(&mut sentences(&[rmc, &gga_msl]), false, None).unwrap();
+        assert_eq!(
+            (info.msl, info.separation, info.loc.height),
+            (36.7, None, 0.0)
+        );
+        let out = crate::NmeaEncoder::default().gga(&info);
+        assert!(out.contains(",36.7,M,,M,"), "{}", out);
+        // or is taken from the geoid model
+        let geoid = Egm96::new([-90.0, 90.0, 0.0, 360.0, 90.0, 180.0], vec![-30.0; 9]).unwrap();
+        let info = NmeaGpsInfo::create_with_geoid(
+            &mut sentences(&[rmc, &gga_msl]),
+            false,
+            None,
+            Some(&geoid),
+        )
+        .unwrap();
+        assert_eq!(info.separation, Some(-30.0));
+        assert!((info.loc.height - 6.7).abs() < 1e-9);
+        let info =
+            NmeaGpsInfo::create_with_geoid(&mut sentences(&[rmc, gga]), false, None, Some(&geoid))
+                .unwrap();
+        assert_eq!(info.separation, Some(-33.0));
+        // Decimal GGA fix quality
+        let gga10 = gga.replace(",W,2,", ",W,10,");
+        let info = NmeaGpsInfo::create(&mut sentences(&[rmc, &gga10]), false, None).unwrap();
+        assert_eq!(info.fix_quality(), FixQuality::Unknown(10));
+        let out = crate::NmeaEncoder::default().gga(&info);
+        assert!(out.contains(",W,10,12,"), "{}", out);
+        let hex = gga.replace(",W,2,", ",W,A,");
+        assert!(parse_gga(&hex["GNGGA,".len()..]).is_err());
+        // Time of the RAWX measurements
+        let rawx = DateTime::from_timestamp(1_727_993_715, 0);
+        let info = NmeaGpsInfo::create(&mut sentences(&[gga]), false, rawx).unwrap();
+        assert_eq!(
+            (info.time_source, Some(info.time)),
+            (TimeSource::Rawx, rawx)
+        );
+        // No time
+        assert!(matches!(
+            NmeaGpsInfo::create(&mut sentences(&[gga, gns]), false, None),
+            Err(GpsError::NoFix)
+        ));
+        // No position
+        let invalid = "GNRMC,221515.00,V,,,,,,,031024,,,N,V";
+        assert!(matches!(
+            NmeaGpsInfo::create(&mut sentences(&[invalid]), false, rawx),
+            Err(GpsError::NoFix)
+        ));
+    }
+
+    #[test]
+    fn parse_pubx() {
+        use super::*;
+        let sentences = |body: &[&str]| {
+            let data: String = body
+                .iter()
+                .map(|x| {
+                    let cksum = x.bytes().fold(0, |acc, x| acc ^ x);
+                    format!("${}*{:02X}\r\n", x, cksum)
+                })
+                .collect();
+            RawNmea::parse_str(&data)
+        };
+        let pubx00 = "PUBX,00,081350.00,4717.113210,N,00833.915187,E,546.589,G3,2.1,2.0,0.007,77.52,0.007,,0.92,1.19,0.77,9,0,0";
+        let pubx03 = "PUBX,03,04,23,-,,,45,010,29,e,,,46,013,08,U,067,31,42,025,10,U,195,33,46,026";
+        let pubx04 = "PUBX,04,073731.00,091202,113851.00,1196,15D,1930035,-2660.664,43,";
+        let gga = "GNGGA,073731.00,4717.11399,N,00833.91590,E,1,08,1.01,499.6,M,48.0,M,,";
+
+        // Position from PUBX,00, time from PUBX,04
+        let mut nmea = sentences(&[pubx00, pubx03, pubx04]);
+        assert_eq!(nmea.0[b"UBX"].len(), 3);
+        let info = NmeaGpsInfo::create(&mut nmea, false, None).unwrap();
+        assert_eq!(info.time_source, TimeSource::Pubx);
+        assert_eq!(info.time.to_rfc3339(), "2002-12-09T07:37:31+00:00");
+        assert!((info.loc.lat - (47.0 + 17.113_21 / 60.0)).abs() < 1e-12);
+        assert!((info.loc.height - 546.589).abs() < 1e-9);
+        assert_eq!((info.quality, info.num_sats), (1, Some(9)));
+        assert_eq!(info.fix_quality(), FixQuality::Autonomous);
+        assert_eq!((info.hdop, info.vdop), (0.92, 1.19));
+        assert_eq!((info.true_heading, info.ground_speed), (77.52, 0.007));
+        let accuracy = info.accuracy.unwrap();
+        assert_eq!(
+            (accuracy.horizontal, accuracy.vertical),
+            (Some(2.1), Some(2.0))
+        );
+        assert_eq!((accuracy.diff_age, accuracy.tdop), (None, Some(0.77)));
+        let clock = info.clock.unwrap();
+        assert_eq!(
+            (clock.leap_seconds, clock.leap_seconds_default),
+            (Some(15), true)
+        );
+        assert_eq!(
+            (clock.utc_tow, clock.utc_week),
+            (Some(113_851.0), Some(1196))
+        );
+        assert_eq!(clock.clock_bias, Some(1_930_035.0));
+        assert_eq!(clock.clock_drift, Some(-2660.664));
+        assert_eq!(clock.timepulse_granularity, Some(43));
+        // Satellite status from PUBX,03
+        assert_eq!(nmea.0[b"UBX"].len(), 1);
+        let mut info = info;
+        info.insert_gsv(&mut nmea);
+        assert_eq!(info.sat_views.len(), 4);
+        assert_eq!(info.sat_views[&GnssSatellite::Gps(8)], (31, 67));
+        assert_eq!(info.sat_views[&GnssSatellite::Gps(23)], (0, 0));
+        assert_eq!(info.used_in_fix.len(), 2);
+        assert!(!info.used_in_fix.contains(&GnssSatellite::Gps(29)));
+
+        // GGA takes precedence over PUBX,00
+        let info =
+            NmeaGpsInfo::create(&mut sentences(&[pubx00, pubx04, gga]), false, None).unwrap();
+        assert_eq!((info.msl, info.num_sats), (499.6, Some(8)));
+        assert!(info.accuracy.is_some());
+        // No fix
+        let pubx00 = "PUBX,00,081350.00,,,,,0.000,NF,5303302,3750001,0.000,0.00,0.000,,99.99,99.99,99.99,0,0,0";
+        assert!(matches!(
+            NmeaGpsInfo::create(&mut sentences(&[pubx00, pubx04]), false, None),
+            Err(GpsError::NoFix)
+        ));
+        assert!(parse_pubx00("00,081350.00,,,,,0.000,XX").is_err());
+        assert!(parse_pubx04("04,073731.00,091202,113851.00,1196,1x,,,").is_err());
+    }
+
+    #[test]
+    fn parse_legacy() {
+        use super::*;
+        // Record of earlier versions: GGA altitude in the location, and
+        // the geoid separation as `msl`
+        let legacy = r#"{"time":"2024-10-03T22:15:15Z","loc":[42.649390333,-71.316323833,36.7],
+            "msl":-33.0,"true_heading":90.0,"mag_heading":0.0,"ground_speed":0.086,
+            "quality":2,"hdop":1.04,"vdop":1.51,"pdop":1.83,"sat_views":{"G10":[45,120]}}"#;
+        let info: NmeaGpsInfo = serde_json::from_str(legacy).unwrap();
+        assert_eq!(info.time.to_rfc3339(), "2024-10-03T22:15:15+00:00");
+        assert_eq!((info.loc.lat, info.loc.lon), (42.649390333, -71.316323833));
+        assert!((info.loc.height - 3.7).abs() < 1e-9);
+        assert_eq!((info.msl, info.quality, info.pdop), (36.7, 2, 1.83));
+        assert_eq!(info.sat_views[&GnssSatellite::Gps(16)], (45, 120));
+        // Records of this version are read back unchanged
+        let json = serde_json::to_string(&info).unwrap();
+        assert!(json.contains(r#""loc":{"lat":"#), "{}", json);
+        let back: NmeaGpsInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!((back.loc, back.msl), (info.loc, info.msl));
+        assert_eq!(back.sat_views, info.sat_views);
     }
 }
@@ -0,0 +1,460 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use chrono::{NaiveTime, Timelike};
+use serde::{Deserialize, Serialize};
+
+use crate::{nmea::nmea_signal_id, GnssSatellite, NmeaGpsInfo};
+
+/// Satellite listed in a GSV sentence: NMEA SV ID, satellite and SNR
+type GsvEntry<'a> = (u8, &'a GnssSatellite, Option<u8>);
+
+/// Conversion from km/h to knots
+const KMH_TO_KNOTS: f32 = 1.0 / 1.852;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+/// Version of the NMEA standard produced by the [`NmeaEncoder`]
+pub enum NmeaVersion {
+    /// NMEA 2.3: a single GSA sentence, and no Galileo, BeiDou or NavIC
+    /// satellites
+    V23,
+    /// NMEA 4.10: GSA sentences per system with the system ID, GSV
+    /// sentences per signal, and the RMC navigational status
+    V410,
+    /// NMEA 4.11: NMEA 4.10 with QZSS satellites numbered from 1
+    #[default]
+    V411,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// Encoder of NMEA sentences from decoded GPS information.
+///
+/// Produces GGA, RMC, ZDA, VTG, GSA, GSV and GST sentences from an
+/// [`NmeaGpsInfo`], which can be converted from a
+/// [`crate::UbxGpsInfo`] or a [`crate::UbxNavPvt`] solution. Sentences
+/// end in `\r\n`.
+///
+/// # Example
+/// ```
+/// use ublox_gps_tec::{NmeaEncoder, NmeaGpsInfo, NmeaVersion};
+///
+/// let encoder = NmeaEncoder::new(*b"GP", NmeaVersion::V23);
+/// let zda = encoder.zda(&NmeaGpsInfo::default());
+/// assert_eq!(zda, "$GPZDA,000000.00,01,01,1970,00,00*69\r\n");
+/// ```
+pub struct NmeaEncoder {
+    /// Talker ID of the sentences of the combined solution, such as
+    /// `GN` or `GP`. GSV sentences use the talker ID of each system.
+    pub talker: [u8; 2],
+    /// Version of the NMEA standard
+    pub version: NmeaVersion,
+}
+
+impl Default for NmeaEncoder {
+    fn default() -> Self {
+        Self {
+            talker: *b"GN",
+            version: NmeaVersion::default(),
+        }
+    }
+}
+
+impl NmeaEncoder {
+    /// Create a new NMEA encoder
+    ///
+    /// # Arguments
+    /// - `talker`: Talker ID of the sentences of the combined solution
+    /// - `version`: Version of the NMEA standard
+    pub fn new(talker: [u8; 2], version: NmeaVersion) -> Self {
+        Self { talker, version }
+    }
+
+    /// Encode all sentences of an epoch, in the order of u-blox
+    /// receivers: RMC, VTG, GGA, GSA, GSV, GST and ZDA.
+    ///
+    /// The GST sentence is omitted if the position errors are not known.
+    pub fn encode(&self, info: &NmeaGpsInfo) -> String {
+        let mut out = self.rmc(info);
+        out += &self.vtg(info);
+        out += &self.gga(info);
+        self.gsa(info).iter().for_each(|x| out += x);
+        self.gsv(info).iter().for_each(|x| out += x);
+        if let Some(gst) = self.gst(info) {
+            out += &gst;
+        }
+        out += &self.zda(info);
+        out
+    }
+
+    /// Encode a GGA sentence (fix data)
+    pub fn gga(&self, info: &NmeaGpsInfo) -> String {
+        let fix = info.fix_quality().is_valid();
+        let fields = [
+            time(info.time.time()),
+            opt(fix.then(|| latitude(info.loc.lat))),
+            opt(fix.then(|| longitude(info.loc.lon))),
+            format!("{}", info.quality),
+            opt(info.num_sats.map(|x| format!("{:02}", x))),
+            format!("{:.2}", info.hdop),
+            opt(fix.then(|| format!("{:.1},M", info.msl))),
+            // Height of the geoid above the ellipsoid
+            opt(fix.then(|| {
+                let separation = info.separation.map(|x| format!("{:.1}", x));
+                format!("{},M", opt(separation))
+            })),
+            String::new(),
+            String::new(),
+        ];
+        self.sentence(&self.talker, "GGA", &fields)
+    }
+
+    /// Encode an RMC sentence (recommended minimum data)
+    pub fn rmc(&self, info: &NmeaGpsInfo) -> String {
+        let fix = info.fix_quality().is_valid();
+        let valid = info.valid.unwrap_or(fix);
+        let (mag_variation, mag_direction) = match info.mag_variation {
+            Some(x) => (
+                format!("{:.1}", x.abs()),
+                if x < 0.0 { "W" } else { "E" }.into(),
+            ),
+            None => (String::new(), String::new()),
+        };
+        let mut fields = vec![
+            time(info.time.time()),
+            if valid { "A" } else { "V" }.into(),
+            opt(fix.then(|| latitude(info.loc.lat))),
+            opt(fix.then(|| longitude(info.loc.lon))),
+            format!("{:.3}", info.ground_speed * KMH_TO_KNOTS),
+            opt(info.course.map(|x| format!("{:.2}", x))),
+            info.date
+                .unwrap_or(info.time.date_naive())
+                .format("%d%m%y")
+                .to_string(),
+            mag_variation,
+            mag_direction,
+            info.mode.unwrap_or(info.fix_quality().mode()).into(),
+        ];
+        if self.version >= NmeaVersion::V410 {
+            fields.push(info.nav_status.unwrap_or('V').into());
+        }
+        self.sentence(&self.talker, "RMC", &fields)
+    }
+
+    /// Encode a ZDA sentence (time and date)
+    pub fn zda(&self, info: &NmeaGpsInfo) -> String {
+        let fields = [
+            time(info.time.time()),
+            info.time.format("%d,%m,%Y").to_string(),
+            "00".into(),
+            "00".into(),
+        ];
+        self.sentence(&self.talker, "ZDA", &fields)
+    }
+
+    /// Encode a VTG sentence (course and speed over ground)
+    pub fn vtg(&self, info: &NmeaGpsInfo) -> String {
+        let course = info.course;
+        // Magnetic course from the true course and the magnetic variation
+        let magnetic = course
+            .zip(info.mag_variation)
+            .map(|(c, v)| (c - v).rem_euclid(360.0));
+        let fields = [
+            opt(course.map(|x| format!("{:.2},T", x))),
+            opt(magnetic.map(|x| format!("{:.2},M", x))),
+            format!("{:.3},N", info.ground_speed * KMH_TO_KNOTS),
+            format!("{:.3},K", info.ground_speed),
+            info.mode.unwrap_or(info.fix_quality().mode()).into(),
+        ];
+        self.sentence(&self.talker, "VTG", &fields)
+    }
+
+    /// Encode the GSA sentences (DOP and satellites used in the fix)
+    ///
+    /// NMEA 4.10 and later have GSA sentences for each system, with the
+    /// DOPs of the combined solution. Sentences hold up to twelve
+    /// satellites, and are repeated if more are used. NMEA 2.3 has a
+    /// single GSA sentence, with the first twelve satellites.
+    pub fn gsa(&self, info: &NmeaGpsInfo) -> Vec<String> {
+        let mode = info.selection_mode.unwrap_or('A');
+        let fix_mode = info.fix_type().map_or(3, |x| x.gsa());
+        // Satellites used in the fix, by system
+        let mut systems: BTreeMap<u8, Vec<u8>> = BTreeMap::new();
+        for sat in &info.used_in_fix {
+            let Some((_, svid)) = sat.to_nmea(self.version) else {
+                continue;
+            };
+            let system = match self.version {
+                NmeaVersion::V23 => 0,
+                _ => sat.nmea_system_id(),
+            };
+            systems.entry(system).or_default().push(svid);
+        }
+        if systems.is_empty() {
+            systems.insert(1, Vec::new());
+        }
+        let mut res = Vec::new();
+        for (system, mut svids) in systems {
+            svids.sort_unstable();
+            if self.version == NmeaVersion::V23 {
+                svids.truncate(12);
+            }
+            for chunk in svids.chunks(12).chain(svids.is_empty().then_some(&[][..])) {
+                let mut fields = vec![mode.into(), fix_mode.to_string()];
+                fields.extend((0..12).map(|i| opt(chunk.get(i).map(|x| format!("{:02}", x)))));
+                fields.push(format!("{:.2}", info.pdop));
+                fields.push(format!("{:.2}", info.hdop));
+                fields.push(format!("{:.2}", info.vdop));
+                if self.version >= NmeaVersion::V410 {
+                    fields.push(format!("{:X}", system));
+                }
+                res.push(self.sentence(&self.talker, "GSA", &fields));
+            }
+        }
+        res
+    }
+
+    /// Encode the GSV sentences (satellites in view)
+    ///
+    /// Sentences use the talker ID of each system. NMEA 4.10 and later
+    /// have sentences for each signal, and list satellites in view
+    /// without tracked signals under signal ID `0`. NMEA 2.3 has one
+    /// set of sentences per system, with the highest SNR of each
+    /// satellite.
+    pub fn gsv(&self, info: &NmeaGpsInfo) -> Vec<String> {
+        // Satellites in view with their SNR, by talker ID and signal ID
+        let mut groups: BTreeMap<([u8; 2], u8), Vec<GsvEntry>> = BTreeMap::new();
+        for sat in info.sat_views.keys() {
+            let Some((talker, svid)) = sat.to_nmea(self.version) else {
+                continue;
+            };
+            // Highest SNR of each signal ID
+            let mut signals: BTreeMap<u8, Option<u8>> = BTreeMap::new();
+            for signal in info.signals.get(sat).into_iter().flatten() {
+                let id = match self.version {
+                    NmeaVersion::V23 => Some(0),
+                    _ => nmea_signal_id(&signal.channel),
+                };
+                if let Some(id) = id {
+                    let snr = signals.entry(id).or_default();
+                    *snr = (*snr).max(signal.snr);
+                }
+            }
+            if signals.is_empty() {
+                signals.insert(0, None);
+            }
+            for (id, snr) in signals {
+                groups
+                    .entry((talker, id))
+                    .or_default()
+                    .push((svid, sat, snr));
+            }
+        }
+        let mut res = Vec::new();
+        for ((talker, signal_id), mut sats) in groups {
+            sats.sort_unstable_by_key(|x| x.0);
+            let count = sats.len().div_ceil(4).max(1);
+            for (i, chunk) in sats
+                .chunks(4)
+                .chain(sats.is_empty().then_some(&[][..]))
+                .enumerate()
+            {
+                let mut fields = vec![
+                    count.to_string(),
+                    (i + 1).to_string(),
+                    format!("{:02}", sats.len()),
+                ];
+                for (svid, sat, snr) in chunk {
+                    let (elevation, azimuth) = info.sat_views[*sat];
+                    fields.push(format!("{:02}", svid));
+                    fields.push(opt((elevation >= 0).then(|| format!("{:02}", elevation))));
+                    fields.push(format!("{:03}", azimuth));
+                    fields.push(opt(snr.map(|x| format!("{:02}", x))));
+                }
+                if self.version >= NmeaVersion::V410 {
+                    fields.push(format!("{:X}", signal_id));
+                }
+                res.push(self.sentence(&talker, "GSV", &fields));
+            }
+        }
+        res
+    }
+
+    /// Encode a GST sentence (pseudo-range error statistics)
+    ///
+    /// # Returns
+    /// - The sentence, or `None` if the position errors are not known
+    pub fn gst(&self, info: &NmeaGpsInfo) -> Option<String> {
+        let errors = info.errors?;
+        let val = |x: Option<f32>| opt(x.map(|x| format!("{:.1}", x)));
+        let fields = [
+            time(info.time.time()),
+            val(errors.rms),
+            val(errors.semi_major),
+            val(errors.semi_minor),
+            val(errors.orientation),
+            val(errors.lat),
+            val(errors.lon),
+            val(errors.alt),
+        ];
+        Some(self.sentence(&self.talker, "GST", &fields))
+    }
+
+    /// Assemble a sentence, with its checksum
+    fn sentence(&self, talker: &[u8; 2], kind: &str, fields: &[String]) -> String {
+        let mut payload = String::from_utf8_lossy(talker).into_owned();
+        payload += kind;
+        for field in fields {
+            payload.push(',');
+            payload += field;
+        }
+        let cksum = payload.bytes().fold(0, |acc, x| acc ^ x);
+        let mut out = String::with_capacity(payload.len() + 6);
+        let _ = write!(out, "${}*{:02X}\r\n", payload, cksum);
+        out
+    }
+}
+
+/// Format an optional field, which is empty if it is not available
+fn opt(val: Option<String>) -> String {
+    val.unwrap_or_default()
+}
+
+/// Format a UTC time of day (`hhmmss.ss`)
+fn time(time: NaiveTime) -> String {
+    // Leap seconds are represented as a second of 60
+    let (sec, nano) = match time.nanosecond() {
+        n if n >= 1_000_000_000 => (60, n - 1_000_000_000),
+        n => (time.second(), n),
+    };
+    format!(
+        "{:02}{:02}{:02}.{:02}",
+        time.hour(),
+        time.minute(),
+        sec,
+        nano / 10_000_000
+    )
+}
+
+/// Format a latitude (`ddmm.mmmmm,N`)
+fn latitude(lat: f64) -> String {
+    let (deg, min) = degrees_minutes(lat);
+    format!(
+        "{:02}{:08.5},{}",
+        deg,
+        min,
+        if lat < 0.0 { 'S' } else { 'N' }
+    )
+}
+
+/// Format a longitude (`dddmm.mmmmm,E`)
+fn longitude(lon: f64) -> String {
+    let (deg, min) = degrees_minutes(lon);
+    format!(
+        "{:03}{:08.5},{}",
+        deg,
+        min,
+        if lon < 0.0 { 'W' } else { 'E' }
+    )
+}
+
+/// Split an angle into whole degrees and minutes, rounded to five
+/// decimals of a minute
+fn degrees_minutes(val: f64) -> (u32, f64) {
+    let min = (val.abs() * 60.0 * 1e5).round() / 1e5;
+    let deg = (min / 60.0).floor();
+    (deg as u32, min - deg * 60.0)
+}
+
+mod test {
+    #[test]
+    fn test_encoder() {
+        use super::*;
+        use crate::{nmea::RawNmea, GpsFreq};
+        let payload = "$GNRMC,221515.00,A,4238.96342,N,07118.97943,W,0.046,,031024,,,D,V*0D
+$GNVTG,,T,,M,0.046,N,0.086,K,D*34
+$GNGGA,221515.00,4238.96342,N,07118.97943,W,2,12,1.04,36.7,M,-33.0,M,,0131*41
+$GNGSA,A,3,03,27,46,44,31,26,04,16,,,,,1.83,1.04,1.51,1*0A
+$GNGSA,A,3,68,78,79,67,,,,,,,,,1.83,1.04,1.51,2*06
+$GNGSA,A,3,21,29,19,,,,,,,,,,1.83,1.04,1.51,3*09
+$GNGSA,A,3,25,23,41,32,,,,,,,,,1.83,1.04,1.51,4*0C
+$GPGSV,3,1,10,03,26,248,42,04,48,306,17,16,68,221,41,26,72,052,18,1*61
+$GPGSV,3,2,10,27,18,171,36,29,16,041,11,31,62,067,22,32,00,145,12,1*66
+$GPGSV,3,3,10,44,23,237,44,46,15,247,33,1*65
+$GPGSV,1,1,03,03,26,248,27,04,48,306,16,27,18,171,36,6*58
+$GPGSV,1,1,02,09,16,316,,28,30,090,,0*6D
+$GLGSV,2,1,05,67,20,174,38,68,63,216,41,78,65,004,21,79,41,266,37,1*79
+$GLGSV,2,2,05,86,05,011,20,1*44
+$GAGSV,1,1,03,19,74,181,35,21,78,057,09,29,30,147,20,7*4A
+$GNZDA,221515.00,03,10,2024,00,00*7E
+";
+        let info = NmeaGpsInfo::create(&mut RawNmea::parse_str(payload), true, None).unwrap();
+        let encoder = NmeaEncoder::default();
+        // Sentences as output by the receiver
+        let gga = encoder.gga(&info);
+        assert!(gga.starts_with(
+            "$GNGGA,221515.00,4238.96342,N,07118.97943,W,2,12,1.04,36.7,M,-33.0,M,,*"
+        ));
+        assert_eq!(
+            encoder.rmc(&info),
+            "$GNRMC,221515.00,A,4238.96342,N,07118.97943,W,0.046,,031024,,,D,V*0D\r\n"
+        );
+        assert_eq!(
+            encoder.zda(&info),
+            "$GNZDA,221515.00,03,10,2024,00,00*7E\r\n"
+        );
+        let gsa = encoder.gsa(&info);
+        assert_eq!(gsa.len(), 4);
+        assert_eq!(
+            gsa[1],
+            "$GNGSA,A,3,67,68,78,79,,,,,,,,,1.83,1.04,1.51,2*06\r\n"
+        );
+        assert!(encoder.gst(&info).is_none());
+
+        // Round trip through the parser
+        let out = encoder.encode(&info);
+        let mut msgs = RawNmea::parse_str(&out);
+        assert_eq!(msgs.0[b"GSV"].len(), 8);
+        let back = NmeaGpsInfo::create(&mut msgs, true, None).unwrap();
+        assert_eq!(back.time, info.time);
+        assert!((back.loc.lat - info.loc.lat).abs() < 1e-9);
+        assert!((back.loc.lon - info.loc.lon).abs() < 1e-9);
+        assert_eq!((back.quality, back.num_sats), (info.quality, info.num_sats));
+        assert_eq!(
+            (back.pdop, back.hdop, back.vdop),
+            (info.pdop, info.hdop, info.vdop)
+        );
+        assert_eq!(back.sat_views, info.sat_views);
+        assert_eq!(back.used_in_fix, info.used_in_fix);
+        let gps3 = GnssSatellite::Gps(3);
+        assert_eq!(back.snr(&gps3, &GpsFreq::L2CL.into()), Some(27));
+        assert_eq!(back.signals.len(), info.signals.len());
+
+        // NMEA 4.10, with the GSA system ID
+        let encoder = NmeaEncoder::new(*b"GN", NmeaVersion::V410);
+        assert!(encoder.gsa(&info)[2].ends_with(",1.83,1.04,1.51,3*09\r\n"));
+        let mut msgs = RawNmea::parse_str(&encoder.encode(&info));
+        let back = NmeaGpsInfo::create(&mut msgs, true, None).unwrap();
+        assert!(back.used_in_fix.contains(&GnssSatellite::Galileo(21)));
+        assert!(back.used_in_fix.contains(&GnssSatellite::Beidou(25)));
+        assert_eq!(back.used_in_fix, info.used_in_fix);
+
+        // NMEA 2.3, without Galileo and signal IDs
+        let encoder = NmeaEncoder::new(*b"GP", NmeaVersion::V23);
+        let gsa = encoder.gsa(&info);
+        assert_eq!(gsa.len(), 1);
+        assert!(gsa[0].starts_with("$GPGSA,A,3,03,04,16,26,27,31,44,46,67,68,78,79,1.83,"));
+        // More than twelve satellites used
+        let mut more = info.clone();
+        more.used_in_fix
+            .extend([1, 2, 5, 6, 7].map(GnssSatellite::Gps));
+        let gsa = encoder.gsa(&more);
+        assert_eq!(gsa.len(), 1);
+        assert!(gsa[0].starts_with("$GPGSA,A,3,01,02,03,04,05,06,07,16,26,27,31,44,1.83,"));
+        let gsa = NmeaEncoder::default().gsa(&more);
+        assert_eq!(gsa.len(), 5);
+        assert!(gsa[1].starts_with("$GNGSA,A,3,46,,,,,,,,,,,,1.83,"));
+        let gsv = encoder.gsv(&info);
+        assert!(gsv.iter().all(|x| !x.starts_with("$GA")));
+        assert!(gsv[0].starts_with("$GLGSV,2,1,05,67,20,174,38,"));
+        assert!(encoder.rmc(&info).contains(",031024,,,D*"));
+    }
+}
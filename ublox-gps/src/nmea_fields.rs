@@ -0,0 +1,336 @@
+use std::str::FromStr;
+
+use chrono::{NaiveDate, NaiveTime};
+
+use crate::GpsError;
+
+/// Talker ID of proprietary sentences
+pub(crate) const PROPRIETARY: [u8; 2] = *b"P ";
+
+/// Iterator over the checksum-verified sentences in a buffer of NMEA data.
+///
+/// Yields the talker ID, the sentence formatter and the data fields
+/// (without the leading comma) of each sentence, borrowing from the buffer.
+/// Proprietary sentences (`$P` and a three-letter manufacturer code, such
+/// as `$PUBX`) are yielded with the talker ID [`PROPRIETARY`] and the
+/// manufacturer code as the formatter. Sentences with an invalid checksum,
+/// or split over multiple lines, are skipped.
+pub(crate) struct Sentences<'a> {
+    data: &'a str,
+}
+
+impl<'a> Sentences<'a> {
+    pub(crate) fn new(data: &'a str) -> Self {
+        Self { data }
+    }
+}
+
+impl<'a> Iterator for Sentences<'a> {
+    type Item = ([u8; 2], [u8; 3], &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let start = self.data.find('$')?;
+            let rest = &self.data[start + 1..];
+            // Resume the search at the next character, so that a stray '$'
+            // does not hide the sentence that follows it
+            self.data = rest;
+            let Some(end) = rest.find(['*', '$', '\r', '\n']) else {
+                continue;
+            };
+            let payload = &rest[..end];
+            let bytes = payload.as_bytes();
+            let address = bytes.iter().position(|&x| x == b',');
+            if rest.as_bytes()[end] != b'*'
+                || !bytes[..address.unwrap_or(0)]
+                    .iter()
+                    .all(u8::is_ascii_uppercase)
+            {
+                continue;
+            }
+            let (id, kind) = match (address, bytes) {
+                (Some(5), _) => ([bytes[0], bytes[1]], [bytes[2], bytes[3], bytes[4]]),
+                (Some(4), [b'P', ..]) => (PROPRIETARY, [bytes[1], bytes[2], bytes[3]]),
+                _ => continue,
+            };
+            let Some(cksum) = rest
+                .get(end + 1..end + 3)
+                .and_then(|x| u8::from_str_radix(x, 16).ok())
+            else {
+                continue;
+            };
+            if bytes.iter().fold(0, |acc, &x| acc ^ x) != cksum {
+                continue;
+            }
+            self.data = &rest[end + 3..];
+            return Some((id, kind, &payload[address.unwrap_or_default() + 1..]));
+        }
+    }
+}
+
+/// Comma-separated data fields of an NMEA sentence.
+///
+/// Fields borrow from the sentence, and are indexed from `0` for the first
+/// field after the sentence formatter. Empty and missing fields are treated
+/// alike, so that sentences from older NMEA versions, which lack the trailing
+/// fields added in NMEA 4.10 and 4.11, parse as if those fields were empty.
+pub(crate) struct Fields<'a> {
+    sentence: &'static str,
+    fields: Vec<&'a str>,
+}
+
+impl<'a> Fields<'a> {
+    /// Split the data of a sentence into fields
+    ///
+    /// # Arguments
+    /// - `sentence`: Sentence formatter, for error reporting
+    /// - `data`: Data of the sentence, without the formatter and checksum
+    pub(crate) fn new(sentence: &'static str, data: &'a str) -> Self {
+        Self {
+            sentence,
+            fields: data.split(',').collect(),
+        }
+    }
+
+    /// Get the sentence formatter
+    pub(crate) fn sentence(&self) -> &'static str {
+        self.sentence
+    }
+
+    /// Get the number of fields, including empty fields
+    pub(crate) fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Get a field, or `None` if it is empty or missing
+    pub(crate) fn get(&self, index: usize) -> Option<&'a str> {
+        self.fields
+            .get(index)
+            .map(|x| x.trim())
+            .filter(|x| !x.is_empty())
+    }
+
+    /// Get a field that must be present
+    pub(crate) fn required(&self, index: usize, field: &'static str) -> Result<&'a str, GpsError> {
+        self.get(index).ok_or(GpsError::MissingField {
+            sentence: self.sentence,
+            field,
+            index,
+        })
+    }
+
+    /// Get the error for an invalid field
+    pub(crate) fn invalid(&self, index: usize, field: &'static str) -> GpsError {
+        GpsError::InvalidField {
+            sentence: self.sentence,
+            field,
+            index,
+            value: self.get(index).unwrap_or_default().into(),
+        }
+    }
+
+    /// Parse an optional field
+    pub(crate) fn parse<T: FromStr>(
+        &self,
+        index: usize,
+        field: &'static str,
+    ) -> Result<Option<T>, GpsError> {
+        self.get(index)
+            .map(|x| x.parse().map_err(|_| self.invalid(index, field)))
+            .transpose()
+    }
+
+    /// Parse a field that must be present
+    pub(crate) fn parse_required<T: FromStr>(
+        &self,
+        index: usize,
+        field: &'static str,
+    ) -> Result<T, GpsError> {
+        self.required(index, field)?
+            .parse()
+            .map_err(|_| self.invalid(index, field))
+    }
+
+    /// Parse an optional single character field
+    pub(crate) fn char(&self, index: usize, field: &'static str) -> Result<Option<char>, GpsError> {
+        match self.get(index) {
+            None => Ok(None),
+            Some(x) if x.len() == 1 => Ok(x.chars().next()),
+            Some(_) => Err(self.invalid(index, field)),
+        }
+    }
+
+    /// Parse an optional UTC time of day field (`hhmmss[.s...]`), with any
+    /// number of decimals
+    pub(crate) fn time(&self, index: usize) -> Result<Option<NaiveTime>, GpsError> {
+        let Some(inp) = self.get(index) else {
+            return Ok(None);
+        };
+        let err = || self.invalid(index, "time");
+        let (hms, frac) = inp.split_once('.').unwrap_or((inp, ""));
+        if hms.len() != 6 || !hms.bytes().all(|x| x.is_ascii_digit()) {
+            return Err(err());
+        }
+        let hour = hms[0..2].parse().map_err(|_| err())?;
+        let min = hms[2..4].parse().map_err(|_| err())?;
+        let sec: u32 = hms[4..6].parse().map_err(|_| err())?;
+        let nano = fraction_nanos(frac).ok_or_else(err)?;
+        // Leap seconds are represented as a second of 60
+        let (sec, nano) = if sec == 60 {
+            (59, nano + 1_000_000_000)
+        } else {
+            (sec, nano)
+        };
+        NaiveTime::from_hms_nano_opt(hour, min, sec, nano)
+            .map(Some)
+            .ok_or_else(err)
+    }
+
+    /// Parse an optional date field (`ddmmyy`). Two-digit years are in
+    /// the range 1980 - 2079.
+    pub(crate) fn date(&self, index: usize) -> Result<Option<NaiveDate>, GpsError> {
+        let Some(inp) = self.get(index) else {
+            return Ok(None);
+        };
+        let err = || self.invalid(index, "date");
+        if inp.len() != 6 || !inp.bytes().all(|x| x.is_ascii_digit()) {
+            return Err(err());
+        }
+        let day = inp[0..2].parse().map_err(|_| err())?;
+        let month = inp[2..4].parse().map_err(|_| err())?;
+        let year: i32 = inp[4..6].parse().map_err(|_| err())?;
+        let year = if year < 80 { 2000 + year } else { 1900 + year };
+        NaiveDate::from_ymd_opt(year, month, day)
+            .map(Some)
+            .ok_or_else(err)
+    }
+
+    /// Parse an optional latitude (`ddmm.mmm...`) at `index`, with the
+    /// hemisphere (`N`/`S`) at `index + 1`, into degrees
+    pub(crate) fn latitude(&self, index: usize) -> Result<Option<f64>, GpsError> {
+        self.coordinate(index, "latitude", 90.0, ('N', 'S'))
+    }
+
+    /// Parse an optional longitude (`dddmm.mmm...`) at `index`, with the
+    /// hemisphere (`E`/`W`) at `index + 1`, into degrees
+    pub(crate) fn longitude(&self, index: usize) -> Result<Option<f64>, GpsError> {
+        self.coordinate(index, "longitude", 180.0, ('E', 'W'))
+    }
+
+    fn coordinate(
+        &self,
+        index: usize,
+        field: &'static str,
+        max: f64,
+        (pos, neg): (char, char),
+    ) -> Result<Option<f64>, GpsError> {
+        let Some(inp) = self.get(index) else {
+            return Ok(None);
+        };
+        let err = || self.invalid(index, field);
+        let dot = inp.find('.').unwrap_or(inp.len());
+        if dot < 3 || !inp[..dot].bytes().all(|x| x.is_ascii_digit()) {
+            return Err(err());
+        }
+        let deg: f64 = inp[..dot - 2].parse().map_err(|_| err())?;
+        let min: f64 = inp[dot - 2..].parse().map_err(|_| err())?;
+        let val = deg + min / 60.0;
+        if min >= 60.0 || val > max {
+            return Err(err());
+        }
+        match self.char(index + 1, "hemisphere")? {
+            Some(x) if x == pos => Ok(Some(val)),
+            Some(x) if x == neg => Ok(Some(-val)),
+            Some(_) => Err(self.invalid(index + 1, "hemisphere")),
+            None => Err(GpsError::MissingField {
+                sentence: self.sentence,
+                field: "hemisphere",
+                index: index + 1,
+            }),
+        }
+    }
+}
+
+/// Convert the decimal digits of a fraction of a second to nanoseconds
+fn fraction_nanos(frac: &str) -> Option<u32> {
+    if !frac.bytes().all(|x| x.is_ascii_digit()) {
+        return None;
+    }
+    let digits = &frac[..frac.len().min(9)];
+    if digits.is_empty() {
+        return Some(0);
+    }
+    let val: u32 = digits.parse().ok()?;
+    Some(val * 10u32.pow(9 - digits.len() as u32))
+}
+
+mod test {
+    #[test]
+    fn test_fields() {
+        use super::*;
+        let sentences: Vec<_> = Sentences::new(
+            "junk $GNZDA,221515.00,03,10,2024,00,00*7E\r\n$GPXXX,1*00\n$GN$GNGLL,4238.96342,N,07118.97943,W,221515.00,A,D*68\n$PUBX,00,,,,,,,NF*17\n$XUBX,00*3B",
+        )
+        .collect();
+        assert_eq!(sentences.len(), 3);
+        assert_eq!(sentences[0].0, *b"GN");
+        assert_eq!(sentences[0].1, *b"ZDA");
+        assert_eq!(sentences[0].2, "221515.00,03,10,2024,00,00");
+        assert_eq!(sentences[1].1, *b"GLL");
+        assert_eq!(sentences[2], (PROPRIETARY, *b"UBX", "00,,,,,,,NF"));
+
+        let fields = Fields::new(
+            "GGA",
+            "2215,4238.9,N,07118.9794321,W,, ,x,5960.0,N,4238.1,Q",
+        );
+        assert_eq!(fields.len(), 12);
+        assert_eq!(fields.get(5), None);
+        assert_eq!(fields.get(6), None);
+        assert_eq!(fields.get(20), None);
+        assert!((fields.latitude(1).unwrap().unwrap() - (42.0 + 38.9 / 60.0)).abs() < 1e-12);
+        let lon = fields.longitude(3).unwrap().unwrap();
+        assert!((lon + 71.0 + 18.979_432_1 / 60.0).abs() < 1e-12);
+        assert_eq!(fields.latitude(5).unwrap(), None);
+        assert!(matches!(
+            fields.latitude(8),
+            Err(GpsError::InvalidField {
+                index: 8,
+                field: "latitude",
+                ..
+            })
+        ));
+        assert!(matches!(
+            fields.latitude(10),
+            Err(GpsError::InvalidField {
+                index: 11,
+                field: "hemisphere",
+                ..
+            })
+        ));
+        assert!(matches!(
+            fields.required(6, "quality"),
+            Err(GpsError::MissingField { index: 6, .. })
+        ));
+        assert_eq!(fields.parse::<u8>(5, "satellites").unwrap(), None);
+        assert!(fields.parse::<u8>(7, "satellites").is_err());
+        assert!(fields.time(0).is_err());
+
+        let fields = Fields::new(
+            "RMC",
+            "221515,221515.5,221515.123456789012,235960.25,031024,310224",
+        );
+        let time = |h, m, s, n| NaiveTime::from_hms_nano_opt(h, m, s, n).unwrap();
+        assert_eq!(fields.time(0).unwrap(), Some(time(22, 15, 15, 0)));
+        assert_eq!(fields.time(1).unwrap(), Some(time(22, 15, 15, 500_000_000)));
+        assert_eq!(fields.time(2).unwrap(), Some(time(22, 15, 15, 123_456_789)));
+        assert_eq!(
+            fields.time(3).unwrap(),
+            Some(time(23, 59, 59, 1_250_000_000))
+        );
+        assert_eq!(
+            fields.date(4).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 10, 3)
+        );
+        assert!(fields.date(5).is_err());
+    }
+}
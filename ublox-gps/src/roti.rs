@@ -0,0 +1,290 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{geo::Geodetic, GnssFreq, GnssSatellite, TecInfo};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Configuration for the rate of TEC (ROT) and ROTI computation
+pub struct RotiConfig {
+    /// Length of the ROTI window. Windows are aligned to multiples
+    /// of this length since the UNIX epoch.
+    pub window: Duration,
+    /// Minimum number of ROT samples in a window to compute ROTI
+    pub min_samples: usize,
+    /// Maximum gap between consecutive TEC samples before the
+    /// satellite arc is considered broken
+    pub max_gap: Duration,
+    /// Maximum absolute ROT (TECU/min). Larger values are treated
+    /// as cycle slips, and break the satellite arc.
+    pub max_rot: Option<f64>,
+}
+
+impl Default for RotiConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(300),
+            min_samples: 10,
+            max_gap: Duration::from_secs(60),
+            max_rot: Some(10.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Rate of TEC and ROTI over one window for a single satellite
+/// and signal pair.
+pub struct RotiData {
+    source: GnssSatellite,
+    channels: (GnssFreq, GnssFreq),
+    pointing: (u16, i8),
+    rot: Vec<(DateTime<Utc>, f64)>,
+    roti: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Rate of TEC index (ROTI) for all satellites over one window
+pub struct RotiInfo {
+    timestamp: DateTime<Utc>,
+    window: Duration,
+    location: Geodetic,
+    roti: Vec<RotiData>,
+}
+
+/// Get the start of the window containing `tstamp`, for windows
+/// aligned to multiples of `window` since the UNIX epoch.
+pub(crate) fn window_start(tstamp: DateTime<Utc>, window: Duration) -> DateTime<Utc> {
+    let len = window.as_nanos().max(1) as i64;
+    let ns = tstamp.timestamp_nanos_opt().unwrap_or_default();
+    DateTime::from_timestamp_nanos(ns - ns.rem_euclid(len))
+}
+
+#[derive(Debug, Clone)]
+struct RotArc {
+    last: (DateTime<Utc>, f64),
+    pointing: (u16, i8),
+    rot: Vec<(DateTime<Utc>, f64)>,
+}
+
+#[derive(Debug, Clone)]
+/// Streaming rate of TEC (ROT) and ROTI estimator.
+///
+/// ROT is computed from consecutive phase TEC samples along each
+/// continuous satellite arc, and ROTI is the standard deviation of
+/// ROT over fixed-length windows.
+pub struct RotiEstimator {
+    config: RotiConfig,
+    arcs: HashMap<(GnssSatellite, (GnssFreq, GnssFreq)), RotArc>,
+    window_start: Option<DateTime<Utc>>,
+    location: Geodetic,
+}
+
+impl RotiEstimator {
+    /// Create a new ROTI estimator
+    pub fn new(config: RotiConfig) -> Self {
+        Self {
+            config,
+            arcs: HashMap::new(),
+            window_start: None,
+            location: Default::default(),
+        }
+    }
+
+    /// Get the estimator configuration
+    pub fn config(&self) -> &RotiConfig {
+        &self.config
+    }
+
+    /// Add a TEC sample to the estimator.
+    ///
+    /// # Returns
+    /// - ROTI for the previous window, if this sample starts a new window
+    ///   and the previous window has enough samples.
+    pub fn push(&mut self, tec: &TecInfo) -> Option<RotiInfo> {
+        let tstamp = tec.timestamp();
+        let window = window_start(tstamp, self.config.window);
+        let res = match self.window_start {
+            Some(start) if start != window => self.flush(),
+            _ => None,
+        };
+        self.window_start = Some(window);
+        self.location = tec.location();
+        for data in tec.tec() {
+            let Some(ptec) = data.phase_tec() else {
+                continue;
+            };
+            let value = ptec.value();
+            let pointing = (data.azimuth(), data.elevation());
+            let key = (data.source(), data.channels());
+            let arc = self.arcs.entry(key).or_insert_with(|| RotArc {
+                last: (tstamp, value),
+                pointing,
+                rot: Vec::new(),
+            });
+            if let Ok(dt) = (tstamp - arc.last.0).to_std() {
+                if !dt.is_zero() && dt <= self.config.max_gap {
+                    let rot = (value - arc.last.1) / dt.as_secs_f64() * 60.0;
+                    if self.config.max_rot.is_none_or(|max| rot.abs() <= max) {
+                        arc.rot.push((tstamp, rot));
+                    }
+                }
+            }
+            arc.last = (tstamp, value);
+            arc.pointing = pointing;
+        }
+        res
+    }
+
+    /// Compute ROTI for the current window, and reset the estimator.
+    pub fn finish(&mut self) -> Option<RotiInfo> {
+        let res = self.flush();
+        self.arcs.clear();
+        self.window_start = None;
+        res
+    }
+
+    /// Compute ROTI over a series of TEC samples.
+    pub fn from_series<'a, I: IntoIterator<Item = &'a TecInfo>>(
+        series: I,
+        config: RotiConfig,
+    ) -> Vec<RotiInfo> {
+        let mut est = Self::new(config);
+        let mut res: Vec<_> = series.into_iter().filter_map(|x| est.push(x)).collect();
+        res.extend(est.finish());
+        res
+    }
+
+    fn flush(&mut self) -> Option<RotiInfo> {
+        let start = self.window_start?;
+        // Forget arcs that have not been updated recently
+        let max_gap = self.config.max_gap;
+        self.arcs.retain(|_, arc| {
+            (start - arc.last.0)
+                .to_std()
+                .map_or(true, |dt| dt <= max_gap)
+        });
+        let mut roti = Vec::new();
+        for ((source, channels), arc) in self.arcs.iter_mut() {
+            let rot = std::mem::take(&mut arc.rot);
+            if rot.len() < self.config.min_samples.max(2) {
+                continue;
+            }
+            let n = rot.len() as f64;
+            let mean = rot.iter().map(|(_, x)| x).sum::<f64>() / n;
+            let var = rot.iter().map(|(_, x)| (x - mean).powi(2)).sum::<f64>() / n;
+            roti.push(RotiData {
+                source: *source,
+                channels: *channels,
+                pointing: arc.pointing,
+                rot,
+                roti: var.sqrt(),
+            });
+        }
+        if roti.is_empty() {
+            return None;
+        }
+        roti.sort_by_key(|a| (a.source, a.channels));
+        Some(RotiInfo {
+            timestamp: start,
+            window: self.config.window,
+            location: self.location,
+            roti,
+        })
+    }
+}
+
+impl RotiInfo {
+    /// Get the start of the ROTI window
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    /// Get the length of the ROTI window
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Get the location of the receiver
+    pub fn location(&self) -> Geodetic {
+        self.location
+    }
+
+    /// Get the ROTI information
+    pub fn roti(&self) -> &Vec<RotiData> {
+        &self.roti
+    }
+}
+
+impl RotiData {
+    /// Get the satellite source of the ROTI data
+    pub fn source(&self) -> GnssSatellite {
+        self.source
+    }
+
+    /// Get the carrier frequency channels used to compute TEC
+    pub fn channels(&self) -> (GnssFreq, GnssFreq) {
+        self.channels
+    }
+
+    /// Get the azimuth of the source satellite at the end of the window
+    pub fn azimuth(&self) -> u16 {
+        self.pointing.0
+    }
+
+    /// Get the elevation of the source satellite at the end of the window
+    pub fn elevation(&self) -> i8 {
+        self.pointing.1
+    }
+
+    /// Get the rate of TEC samples (TECU/min)
+    pub fn rot(&self) -> &Vec<(DateTime<Utc>, f64)> {
+        &self.rot
+    }
+
+    /// Get the rate of TEC index (TECU/min)
+    pub fn roti(&self) -> f64 {
+        self.roti
+    }
+}
+
+mod test {
+    #[test]
+    fn test_roti() {
+        use super::*;
+        use crate::{tec::TecData, GpsFreq, Uncertain};
+        use chrono::TimeDelta;
+        let start = DateTime::from_timestamp(1_700_000_200, 0).unwrap();
+        let series = (0..600)
+            .map(|i| {
+                // 0.1 TECU/s ramp with a +-0.01 TECU alternating perturbation
+                let value = 0.1 * i as f64 + if i % 2 == 0 { 0.01 } else { -0.01 };
+                TecInfo {
+                    timestamp: start + TimeDelta::seconds(i),
+                    location: Default::default(),
+                    tec: vec![TecData {
+                        source: GnssSatellite::Gps(1),
+                        pointing: (90, 45),
+                        channels: (GpsFreq::L1CA.into(), GpsFreq::L2CL.into()),
+                        phase_tec: Some(Uncertain::new(value, 0.0)),
+                        range_tec: None,
+                        trk_stat: Default::default(),
+                        modeled_tec: None,
+                    }],
+                }
+            })
+            .collect::<Vec<_>>();
+        let res = RotiEstimator::from_series(&series, RotiConfig::default());
+        // The series starts 100 s into a 5-minute window
+        assert_eq!(res.len(), 3);
+        assert_eq!(res[0].roti()[0].rot().len(), 199);
+        assert_eq!(res[1].roti()[0].rot().len(), 300);
+        assert_eq!(res[2].roti()[0].rot().len(), 100);
+        for info in res {
+            let data = &info.roti()[0];
+            let mean = data.rot().iter().map(|x| x.1).sum::<f64>() / data.rot().len() as f64;
+            assert!((mean - 6.0).abs() < 0.01);
+            assert!((data.roti() - 1.2).abs() < 0.01);
+        }
+    }
+}
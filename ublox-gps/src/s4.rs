@@ -0,0 +1,374 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{geo::Geodetic, roti::window_start, GnssFreq, GnssSatellite, UbxGpsInfo};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Configuration for the amplitude scintillation index (S4) computation
+pub struct S4Config {
+    /// Length of the S4 window. Windows are aligned to multiples
+    /// of this length since the UNIX epoch.
+    pub window: Duration,
+    /// Minimum number of carrier-to-noise ratio samples in a window
+    /// to compute S4
+    pub min_samples: usize,
+    /// Minimum satellite elevation (degrees). Satellites below this
+    /// elevation, or with unknown elevation, are ignored.
+    pub min_elevation: Option<i8>,
+    /// Remove a linear trend from the signal intensity before
+    /// computing S4
+    pub detrend: bool,
+    /// S4 above which a satellite is flagged as scintillating
+    pub threshold: f64,
+}
+
+impl Default for S4Config {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(60),
+            min_samples: 100,
+            min_elevation: Some(20),
+            detrend: true,
+            threshold: 0.3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Amplitude scintillation index over one window for a single
+/// satellite and signal.
+pub struct S4Data {
+    source: GnssSatellite,
+    channel: GnssFreq,
+    pointing: (u16, i8),
+    samples: usize,
+    mean_cn0: f64,
+    s4_total: f64,
+    s4_noise: f64,
+    s4: f64,
+    exceeds: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Amplitude scintillation index (S4) for all satellites over one window
+pub struct S4Info {
+    timestamp: DateTime<Utc>,
+    window: Duration,
+    location: Geodetic,
+    s4: Vec<S4Data>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Cn0Series {
+    pointing: (u16, i8),
+    samples: Vec<(DateTime<Utc>, f64)>,
+}
+
+#[derive(Debug, Clone)]
+/// Streaming amplitude scintillation index (S4) estimator.
+///
+/// S4 is computed from the carrier-to-noise ratios of UBX-RXM-RAWX
+/// measurements as the normalized standard deviation of the signal
+/// intensity over fixed-length windows. The contribution of thermal
+/// noise is removed using the C/N0-based correction of
+/// Van Dierendonck et al. (1993).
+pub struct S4Estimator {
+    config: S4Config,
+    series: HashMap<(GnssSatellite, GnssFreq), Cn0Series>,
+    window_start: Option<DateTime<Utc>>,
+    location: Geodetic,
+}
+
+impl S4Estimator {
+    /// Create a new S4 estimator
+    pub fn new(config: S4Config) -> Self {
+        Self {
+            config,
+            series: HashMap::new(),
+            window_start: None,
+            location: Default::default(),
+        }
+    }
+
+    /// Get the estimator configuration
+    pub fn config(&self) -> &S4Config {
+        &self.config
+    }
+
+    /// Add an epoch of measurements to the estimator.
+    ///
+    /// # Returns
+    /// - S4 for the previous window, if this epoch starts a new window
+    ///   and the previous window has enough samples.
+    pub fn push(&mut self, info: &UbxGpsInfo) -> Option<S4Info> {
+        let tstamp = info.timestamp();
+        let window = window_start(tstamp, self.config.window);
+        let res = match self.window_start {
+            Some(start) if start != window => self.flush(),
+            _ => None,
+        };
+        self.window_start = Some(window);
+        self.location = info.location();
+        for (sat, ch) in info.carrier_phase() {
+            if let Some(min) = self.config.min_elevation {
+                if ch.elevation < min {
+                    continue;
+                }
+            }
+            for m in ch.meas.iter() {
+                if m.carrier_snr == 0 {
+                    continue;
+                }
+                let series = self.series.entry((*sat, m.channel)).or_default();
+                series.pointing = (ch.azimuth, ch.elevation);
+                series.samples.push((tstamp, m.carrier_snr as f64));
+            }
+        }
+        res
+    }
+
+    /// Compute S4 for the current window, and reset the estimator.
+    pub fn finish(&mut self) -> Option<S4Info> {
+        let res = self.flush();
+        self.window_start = None;
+        res
+    }
+
+    /// Compute S4 over a series of measurement epochs.
+    pub fn from_series<'a, I: IntoIterator<Item = &'a UbxGpsInfo>>(
+        series: I,
+        config: S4Config,
+    ) -> Vec<S4Info> {
+        let mut est = Self::new(config);
+        let mut res: Vec<_> = series.into_iter().filter_map(|x| est.push(x)).collect();
+        res.extend(est.finish());
+        res
+    }
+
+    fn flush(&mut self) -> Option<S4Info> {
+        let start = self.window_start?;
+        let mut s4 = Vec::new();
+        for ((source, channel), series) in self.series.drain() {
+            if series.samples.len() < self.config.min_samples.max(2) {
+                continue;
+            }
+            let (mean_cn0, s4_total, s4_noise) = s4_index(&series.samples, self.config.detrend);
+            let s4_corr = (s4_total * s4_total - s4_noise * s4_noise).max(0.0).sqrt();
+            s4.push(S4Data {
+                source,
+                channel,
+                pointing: series.pointing,
+                samples: series.samples.len(),
+                mean_cn0,
+                s4_total,
+                s4_noise,
+                s4: s4_corr,
+                exceeds: s4_corr > self.config.threshold,
+            });
+        }
+        if s4.is_empty() {
+            return None;
+        }
+        s4.sort_by_key(|a| (a.source, a.channel));
+        Some(S4Info {
+            timestamp: start,
+            window: self.config.window,
+            location: self.location,
+            s4,
+        })
+    }
+}
+
+/// Compute the total S4 and the thermal noise S4 from a series
+/// of carrier-to-noise ratios (dB-Hz).
+///
+/// # Returns
+/// - A tuple of (mean C/N0 in dB-Hz, total S4, noise S4)
+fn s4_index(samples: &[(DateTime<Utc>, f64)], detrend: bool) -> (f64, f64, f64) {
+    let n = samples.len() as f64;
+    let t0 = samples[0].0;
+    let intensity = samples
+        .iter()
+        .map(|(t, cn0)| {
+            let t = (*t - t0).num_nanoseconds().unwrap_or_default() as f64 * 1e-9;
+            (t, 10f64.powf(cn0 / 10.0))
+        })
+        .collect::<Vec<_>>();
+    let mean_si = intensity.iter().map(|(_, si)| si).sum::<f64>() / n;
+    let normalized = if detrend {
+        // Least-squares linear trend of the intensity
+        let mean_t = intensity.iter().map(|(t, _)| t).sum::<f64>() / n;
+        let (stt, sts) = intensity.iter().fold((0.0, 0.0), |(stt, sts), (t, si)| {
+            (
+                stt + (t - mean_t) * (t - mean_t),
+                sts + (t - mean_t) * (si - mean_si),
+            )
+        });
+        let slope = if stt > 0.0 { sts / stt } else { 0.0 };
+        intensity
+            .iter()
+            .map(|(t, si)| si / (mean_si + slope * (t - mean_t)))
+            .collect::<Vec<_>>()
+    } else {
+        intensity.iter().map(|(_, si)| si / mean_si).collect()
+    };
+    let mean = normalized.iter().sum::<f64>() / n;
+    let mean_sq = normalized.iter().map(|x| x * x).sum::<f64>() / n;
+    let s4_total = ((mean_sq - mean * mean) / (mean * mean)).max(0.0).sqrt();
+    let mean_cn0 = samples.iter().map(|(_, cn0)| cn0).sum::<f64>() / n;
+    let snr = 10f64.powf(mean_cn0 / 10.0);
+    let s4_noise = ((100.0 / snr) * (1.0 + 500.0 / (19.0 * snr))).sqrt();
+    (mean_cn0, s4_total, s4_noise)
+}
+
+impl S4Info {
+    /// Get the start of the S4 window
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    /// Get the length of the S4 window
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Get the location of the receiver
+    pub fn location(&self) -> Geodetic {
+        self.location
+    }
+
+    /// Get the S4 information
+    pub fn s4(&self) -> &Vec<S4Data> {
+        &self.s4
+    }
+
+    /// Get the S4 information of signals exceeding the threshold
+    pub fn exceeding(&self) -> impl Iterator<Item = &S4Data> {
+        self.s4.iter().filter(|x| x.exceeds)
+    }
+}
+
+impl S4Data {
+    /// Get the satellite source of the S4 data
+    pub fn source(&self) -> GnssSatellite {
+        self.source
+    }
+
+    /// Get the carrier frequency channel of the S4 data
+    pub fn channel(&self) -> GnssFreq {
+        self.channel
+    }
+
+    /// Get the azimuth of the source satellite at the end of the window
+    pub fn azimuth(&self) -> u16 {
+        self.pointing.0
+    }
+
+    /// Get the elevation of the source satellite at the end of the window
+    pub fn elevation(&self) -> i8 {
+        self.pointing.1
+    }
+
+    /// Get the number of samples in the window
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// Get the mean carrier-to-noise ratio (dB-Hz)
+    pub fn mean_cn0(&self) -> f64 {
+        self.mean_cn0
+    }
+
+    /// Get the total S4, including the thermal noise contribution
+    pub fn s4_total(&self) -> f64 {
+        self.s4_total
+    }
+
+    /// Get the thermal noise contribution to S4
+    pub fn s4_noise(&self) -> f64 {
+        self.s4_noise
+    }
+
+    /// Get the noise-corrected S4
+    pub fn s4(&self) -> f64 {
+        self.s4
+    }
+
+    /// Check if the noise-corrected S4 exceeds the configured threshold
+    pub fn exceeds_threshold(&self) -> bool {
+        self.exceeds
+    }
+}
+
+mod test {
+    #[test]
+    fn test_s4() {
+        use super::*;
+        use crate::{ubx::UbxRxmRawx, CarrierMeas, GpsFreq, NmeaGpsInfo, NmeaMsgGroup};
+        use chrono::TimeDelta;
+        let start = DateTime::from_timestamp(1_700_000_040, 0).unwrap();
+        let epochs = (0..1200)
+            .map(|i| {
+                let time = start + TimeDelta::milliseconds(100 * i);
+                let mut nmea = NmeaGpsInfo {
+                    time,
+                    ..Default::default()
+                };
+                let gps = GnssSatellite::Gps(1);
+                let glo = GnssSatellite::Glonass(1);
+                nmea.sat_views.insert(gps, (45, 90));
+                nmea.sat_views.insert(glo, (45, 180));
+                let meas = |channel: GnssFreq, carrier_snr| CarrierMeas {
+                    channel,
+                    pseudo_range: None,
+                    carrier_phase: None,
+                    doppler: (0.0, 0.0),
+                    locktime: 0,
+                    carrier_snr,
+                    trk_stat: Default::default(),
+                };
+                let rxm = UbxRxmRawx {
+                    timestamp: time,
+                    rcv_tow: 0.0,
+                    week: 0,
+                    leap_second: 0,
+                    receiver_status: Default::default(),
+                    version: 1,
+                    meas: [
+                        // Quiet satellite
+                        (gps, vec![meas(GpsFreq::L1CA.into(), 45)]),
+                        // Intensity alternating by a factor of 4
+                        (
+                            glo,
+                            vec![meas(
+                                crate::GlonassFreq::L1OF(0).into(),
+                                if i % 2 == 0 { 36 } else { 42 },
+                            )],
+                        ),
+                    ]
+                    .into_iter()
+                    .collect(),
+                };
+                UbxGpsInfo::new(nmea, Some(rxm), NmeaMsgGroup(Default::default()))
+            })
+            .collect::<Vec<_>>();
+        let res = S4Estimator::from_series(&epochs, S4Config::default());
+        assert_eq!(res.len(), 2);
+        for info in res {
+            assert_eq!(info.s4().len(), 2);
+            let quiet = &info.s4()[0];
+            assert_eq!(quiet.samples(), 600);
+            assert!(quiet.s4_total() < 1e-6);
+            assert_eq!(quiet.s4(), 0.0);
+            assert!(!quiet.exceeds_threshold());
+            let noisy = &info.s4()[1];
+            // Intensities of 1 and 4 have a normalized standard deviation of 0.6
+            assert!((noisy.s4_total() - 0.6).abs() < 0.01);
+            assert!(noisy.s4() < noisy.s4_total());
+            assert!(noisy.exceeds_threshold());
+            assert_eq!(info.exceeding().count(), 1);
+        }
+    }
+}
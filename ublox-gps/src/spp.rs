@@ -0,0 +1,803 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ephemeris::{Ephemeris, SatState},
+    geo::{Ecef, Enu, Geodetic},
+    iono::{GPS_L1, SPEED_OF_LIGHT},
+    nav::NavStore,
+    nmea::GnssSatellite,
+    stats::{invert, wls},
+    tec::MIN_FREQ_SEPARATION,
+    time::{GnssTime, TimeScale},
+    tropo::{day_of_year, Troposphere},
+    ubx::{CarrierMeas, Frequency, UbxGpsInfo, UbxRxmRawx},
+    uncertain::Uncertain,
+};
+
+/// BeiDou B1I carrier frequency, the reference of the TGD1 group delay (Hz)
+const BDS_B1I: f64 = 1561.098e6;
+/// Receiver positions closer than this to the center of the Earth are not
+/// yet converged (m)
+const MIN_RADIUS: f64 = 1e6;
+/// Pseudo-range error at zenith added to the receiver estimate (m)
+const ZENITH_ERROR: f64 = 0.3;
+/// Time scales that can be estimated, in the order of the clock parameters
+const SCALES: [TimeScale; 3] = [TimeScale::Gpst, TimeScale::Gst, TimeScale::Bdt];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+/// Ionospheric correction of the pseudo-ranges
+pub enum IonoCorrection {
+    /// No correction
+    None,
+    /// Ionosphere-free combination of dual-frequency pseudo-ranges where
+    /// available, and the broadcast Klobuchar model otherwise
+    #[default]
+    Auto,
+    /// Ionosphere-free combination of dual-frequency pseudo-ranges only,
+    /// single-frequency satellites are excluded
+    DualFrequency,
+    /// Broadcast Klobuchar model on the primary signal
+    Klobuchar,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Configuration for the single point positioning solution
+pub struct SppConfig {
+    /// Elevation mask (degrees)
+    pub elevation_mask: f64,
+    /// Ionospheric correction
+    pub iono: IonoCorrection,
+    /// Tropospheric correction, if any
+    pub troposphere: Option<Troposphere>,
+    /// Maximum number of least squares iterations
+    pub max_iter: usize,
+}
+
+impl Default for SppConfig {
+    fn default() -> Self {
+        Self {
+            elevation_mask: 10.0,
+            iono: IonoCorrection::Auto,
+            troposphere: Some(Troposphere::default()),
+            max_iter: 10,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+/// Dilution of precision
+pub struct Dop {
+    /// Geometric dilution of precision
+    pub gdop: f64,
+    /// Position dilution of precision
+    pub pdop: f64,
+    /// Horizontal dilution of precision
+    pub hdop: f64,
+    /// Vertical dilution of precision
+    pub vdop: f64,
+    /// Time dilution of precision, of the first receiver clock
+    pub tdop: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Single point positioning solution
+pub struct SppSolution {
+    time: GnssTime,
+    position: Ecef,
+    covariance: Vec<Vec<f64>>,
+    clock_bias: HashMap<TimeScale, f64>,
+    dop: Dop,
+    satellites: Vec<GnssSatellite>,
+    residuals: Vec<f64>,
+}
+
+impl SppSolution {
+    /// Get the receiver time of the solution
+    pub fn time(&self) -> GnssTime {
+        self.time
+    }
+
+    /// Get the receiver position (ECEF)
+    pub fn position(&self) -> Ecef {
+        self.position
+    }
+
+    /// Get the receiver location
+    pub fn location(&self) -> Geodetic {
+        self.position.to_geodetic()
+    }
+
+    /// Get the covariance of the receiver position (ECEF, m²)
+    pub fn covariance(&self) -> &Vec<Vec<f64>> {
+        &self.covariance
+    }
+
+    /// Get the receiver clock bias (s) with respect to a time scale.
+    /// QZSS satellites share the GPS time scale.
+    pub fn clock_bias(&self, scale: TimeScale) -> Option<f64> {
+        self.clock_bias.get(&scale).copied()
+    }
+
+    /// Get the receiver clock biases (s) with respect to all estimated
+    /// time scales
+    pub fn clock_biases(&self) -> &HashMap<TimeScale, f64> {
+        &self.clock_bias
+    }
+
+    /// Get the dilution of precision
+    pub fn dop(&self) -> Dop {
+        self.dop
+    }
+
+    /// Get the satellites used in the solution
+    pub fn satellites(&self) -> &Vec<GnssSatellite> {
+        &self.satellites
+    }
+
+    /// Get the post-fit pseudo-range residuals (m), in the order of
+    /// [`SppSolution::satellites`]
+    pub fn residuals(&self) -> &Vec<f64> {
+        &self.residuals
+    }
+
+    /// Get the offset of the solution from a reference location, in the
+    /// local frame of the reference
+    pub fn offset_from(&self, reference: &Geodetic) -> Enu {
+        reference.enu(&self.position)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Doppler velocity and receiver clock drift solution
+pub struct VelocitySolution {
+    time: GnssTime,
+    location: Geodetic,
+    velocity: Ecef,
+    covariance: Vec<Vec<f64>>,
+    clock_drift: Uncertain<f64>,
+    satellites: Vec<GnssSatellite>,
+    residuals: Vec<f64>,
+}
+
+impl VelocitySolution {
+    /// Get the receiver time of the solution
+    pub fn time(&self) -> GnssTime {
+        self.time
+    }
+
+    /// Get the receiver location used for the solution
+    pub fn location(&self) -> Geodetic {
+        self.location
+    }
+
+    /// Get the receiver velocity (ECEF, m/s)
+    pub fn velocity(&self) -> Ecef {
+        self.velocity
+    }
+
+    /// Get the covariance of the receiver velocity (ECEF, m²/s²)
+    pub fn covariance(&self) -> &Vec<Vec<f64>> {
+        &self.covariance
+    }
+
+    /// Get the receiver velocity in the local frame (m/s)
+    pub fn enu(&self) -> Enu {
+        self.location
+            .enu(&(self.location.to_ecef() + self.velocity))
+    }
+
+    /// Get the covariance of the receiver velocity in the local frame
+    /// (m²/s²)
+    pub fn enu_covariance(&self) -> [[f64; 3]; 3] {
+        let rot = enu_rotation(&self.location);
+        let mut cov = [[0.0; 3]; 3];
+        for (k, row) in cov.iter_mut().enumerate() {
+            for (l, c) in row.iter_mut().enumerate() {
+                *c = (0..3)
+                    .flat_map(|i| (0..3).map(move |j| (i, j)))
+                    .map(|(i, j)| rot[k][i] * self.covariance[i][j] * rot[l][j])
+                    .sum();
+            }
+        }
+        cov
+    }
+
+    /// Get the horizontal speed (m/s)
+    pub fn ground_speed(&self) -> Uncertain<f64> {
+        let enu = self.enu();
+        let cov = self.enu_covariance();
+        let speed = enu.east.hypot(enu.north);
+        let var = if speed > 0.0 {
+            (enu.east.powi(2) * cov[0][0]
+                + enu.north.powi(2) * cov[1][1]
+                + 2.0 * enu.east * enu.north * cov[0][1])
+                / (speed * speed)
+        } else {
+            0.5 * (cov[0][0] + cov[1][1])
+        };
+        Uncertain(speed, var.max(0.0).sqrt())
+    }
+
+    /// Get the course over ground (degrees east of true north)
+    pub fn true_heading(&self) -> f64 {
+        let enu = self.enu();
+        enu.east.atan2(enu.north).to_degrees().rem_euclid(360.0)
+    }
+
+    /// Get the vertical speed, positive upwards (m/s)
+    pub fn vertical_speed(&self) -> Uncertain<f64> {
+        Uncertain(self.enu().up, self.enu_covariance()[2][2].max(0.0).sqrt())
+    }
+
+    /// Get the receiver clock drift (s/s)
+    pub fn clock_drift(&self) -> Uncertain<f64> {
+        self.clock_drift
+    }
+
+    /// Get the satellites used in the solution
+    pub fn satellites(&self) -> &Vec<GnssSatellite> {
+        &self.satellites
+    }
+
+    /// Get the post-fit range rate residuals (m/s), one per Doppler
+    /// measurement, in the order of [`VelocitySolution::satellites`]
+    pub fn residuals(&self) -> &Vec<f64> {
+        &self.residuals
+    }
+}
+
+/// Get the rotation from ECEF to the local ENU frame at a location
+fn enu_rotation(loc: &Geodetic) -> [[f64; 3]; 3] {
+    let (lat, lon) = (loc.lat.to_radians(), loc.lon.to_radians());
+    [
+        [-lon.sin(), lon.cos(), 0.0],
+        [-lat.sin() * lon.cos(), -lat.sin() * lon.sin(), lat.cos()],
+        [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()],
+    ]
+}
+
+/// A pseudo-range observation with the satellite state at transmission
+struct Observation<'a> {
+    sat: GnssSatellite,
+    eph: &'a Ephemeris,
+    scale: TimeScale,
+    state: SatState,
+    /// Pseudo-range corrected for the satellite clock and group delay (m)
+    range: f64,
+    /// Pseudo-range standard deviation (m)
+    std: f64,
+    /// Carrier frequency of the single-frequency observation (Hz), or
+    /// `None` for ionosphere-free combinations
+    freq: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+/// Single point positioning from pseudo-ranges and broadcast ephemerides.
+///
+/// Estimates the receiver position and one receiver clock bias per time
+/// scale (GPS and QZSS, Galileo, BeiDou) by iterated weighted least squares.
+/// GLONASS and SBAS satellites are not used.
+pub struct Spp {
+    config: SppConfig,
+}
+
+impl Spp {
+    /// Create a new single point positioning solver
+    pub fn new(config: SppConfig) -> Self {
+        Self { config }
+    }
+
+    /// Get the configuration
+    pub fn config(&self) -> &SppConfig {
+        &self.config
+    }
+
+    /// Solve for the receiver position from combined GPS info.
+    ///
+    /// # Errors
+    /// - If the receiver time is not available, or there are not enough
+    ///   satellites with ephemerides for a solution
+    pub fn solve(&self, info: &UbxGpsInfo, nav: &NavStore) -> Result<SppSolution, &'static str> {
+        let time = info.gnss_time().ok_or("Receiver time is not available")?;
+        self.solve_meas(
+            time,
+            info.carrier_phase()
+                .iter()
+                .map(|(sat, path)| (*sat, path.meas.as_slice())),
+            nav,
+        )
+    }
+
+    /// Solve for the receiver position from raw measurements.
+    ///
+    /// # Errors
+    /// - If the receiver time is not available, or there are not enough
+    ///   satellites with ephemerides for a solution
+    pub fn solve_rawx(
+        &self,
+        rawx: &UbxRxmRawx,
+        nav: &NavStore,
+    ) -> Result<SppSolution, &'static str> {
+        let time = rawx.gnss_time().ok_or("Receiver time is not available")?;
+        self.solve_meas(
+            time,
+            rawx.meas.iter().map(|(sat, meas)| (*sat, meas.as_slice())),
+            nav,
+        )
+    }
+
+    /// Solve for the receiver velocity and clock drift from the Doppler
+    /// measurements in combined GPS info.
+    ///
+    /// # Arguments
+    /// - `info`: Combined GPS info
+    /// - `nav`: Navigation data
+    /// - `location`: Receiver location, such as [`UbxGpsInfo::location`]
+    ///   or [`SppSolution::location`]
+    ///
+    /// # Errors
+    /// - If the receiver time is not available, or there are not enough
+    ///   satellites with ephemerides for a solution
+    pub fn velocity(
+        &self,
+        info: &UbxGpsInfo,
+        nav: &NavStore,
+        location: &Geodetic,
+    ) -> Result<VelocitySolution, &'static str> {
+        let time = info.gnss_time().ok_or("Receiver time is not available")?;
+        self.velocity_meas(
+            time,
+            info.carrier_phase()
+                .iter()
+                .map(|(sat, path)| (*sat, path.meas.as_slice())),
+            nav,
+            location,
+        )
+    }
+
+    /// Solve for the receiver velocity and clock drift from the Doppler
+    /// measurements in raw measurements.
+    ///
+    /// # Errors
+    /// - If the receiver time is not available, or there are not enough
+    ///   satellites with ephemerides for a solution
+    pub fn velocity_rawx(
+        &self,
+        rawx: &UbxRxmRawx,
+        nav: &NavStore,
+        location: &Geodetic,
+    ) -> Result<VelocitySolution, &'static str> {
+        let time = rawx.gnss_time().ok_or("Receiver time is not available")?;
+        self.velocity_meas(
+            time,
+            rawx.meas.iter().map(|(sat, meas)| (*sat, meas.as_slice())),
+            nav,
+            location,
+        )
+    }
+
+    fn velocity_meas<'a, I: IntoIterator<Item = (GnssSatellite, &'a [CarrierMeas])>>(
+        &self,
+        time: GnssTime,
+        meas: I,
+        nav: &NavStore,
+        location: &Geodetic,
+    ) -> Result<VelocitySolution, &'static str> {
+        let pos = location.to_ecef();
+        let mut sats: Vec<(GnssSatellite, &[CarrierMeas])> = meas.into_iter().collect();
+        sats.sort_by_key(|(sat, _)| *sat);
+        let mut design = Vec::new();
+        let mut obs = Vec::new();
+        let mut satellites = Vec::new();
+        for (sat, meas) in sats {
+            let Some(eph) = nav.ephemeris(&sat) else {
+                continue;
+            };
+            if !eph.is_healthy() {
+                continue;
+            }
+            // Time of transmission from the pseudo-range, or the nominal travel time
+            let travel = meas
+                .iter()
+                .find_map(|m| m.pseudo_range)
+                .map(|(pr, _)| pr / SPEED_OF_LIGHT)
+                .unwrap_or(0.075);
+            let tx = time - chrono::TimeDelta::nanoseconds((travel * 1e9) as i64);
+            let Some(state) = eph.state(tx) else {
+                continue;
+            };
+            let (sp, sv) = (state.position, state.velocity);
+            let enu = location.enu(&sp);
+            if enu.elevation() < self.config.elevation_mask {
+                continue;
+            }
+            let los = sp - pos;
+            let range = los.norm();
+            let e = [los.x / range, los.y / range, los.z / range];
+            let we = eph.earth_rotation();
+            for m in meas {
+                let (doppler, std) = (m.doppler.0 as f64, m.doppler.1 as f64);
+                if doppler == 0.0 || !(std.is_finite() && std > 0.0) || !m.channel.is_known() {
+                    continue;
+                }
+                let wavelength = SPEED_OF_LIGHT / m.channel.get_freq();
+                // Positive Doppler for an approaching satellite
+                let rate = -doppler * wavelength;
+                // Range rate of a static receiver, with the Earth rotation correction
+                let model = e[0] * sv.x
+                    + e[1] * sv.y
+                    + e[2] * sv.z
+                    + we * (sv.x * pos.y - sv.y * pos.x) / SPEED_OF_LIGHT
+                    - SPEED_OF_LIGHT * state.clock_drift;
+                design.push(vec![
+                    -e[0] - we * sp.y / SPEED_OF_LIGHT,
+                    -e[1] + we * sp.x / SPEED_OF_LIGHT,
+                    -e[2],
+                    1.0,
+                ]);
+                obs.push(Uncertain(rate - model, std * wavelength));
+                satellites.push(sat);
+            }
+        }
+        if obs.len() < 4 {
+            return Err("Not enough satellites for a solution");
+        }
+        let ls = wls(&design, &obs).ok_or("Singular geometry")?;
+        let p = ls.params();
+        Ok(VelocitySolution {
+            time,
+            location: *location,
+            velocity: Ecef::new(p[0].0, p[1].0, p[2].0),
+            covariance: ls.covariance()[..3]
+                .iter()
+                .map(|row| row[..3].to_vec())
+                .collect(),
+            clock_drift: Uncertain(p[3].0 / SPEED_OF_LIGHT, p[3].1 / SPEED_OF_LIGHT),
+            satellites,
+            residuals: ls.residuals().clone(),
+        })
+    }
+
+    /// Select the pseudo-range of a satellite, and compute the satellite
+    /// state at the time of transmission
+    fn observation<'a>(
+        &self,
+        time: GnssTime,
+        sat: GnssSatellite,
+        meas: &[CarrierMeas],
+        nav: &'a NavStore,
+    ) -> Option<Observation<'a>> {
+        let eph = nav.ephemeris(&sat)?;
+        let scale = eph.time_scale()?;
+        if !eph.is_healthy() {
+            return None;
+        }
+        let mut ranges = meas
+            .iter()
+            .filter(|m| m.channel.is_known())
+            .filter_map(|m| Some((m.channel.get_freq(), m.pseudo_range?)));
+        let (f1, (p1, s1)) = ranges.next()?;
+        let dual = match self.config.iono {
+            IonoCorrection::Auto | IonoCorrection::DualFrequency => {
+                ranges.find(|(f2, _)| f1 - f2 >= MIN_FREQ_SEPARATION)
+            }
+            _ => None,
+        };
+        if dual.is_none() && self.config.iono == IonoCorrection::DualFrequency {
+            return None;
+        }
+        // Satellite clock at the time of transmission
+        let tx = time - chrono::TimeDelta::nanoseconds((p1 / SPEED_OF_LIGHT * 1e9) as i64);
+        let dts = eph.clock_bias(tx)?;
+        let state = eph.state(tx - chrono::TimeDelta::nanoseconds((dts * 1e9) as i64))?;
+        let (range, std, freq) = match dual {
+            Some((f2, (p2, s2))) => {
+                let (a, b) = (f1 * f1, f2 * f2);
+                let (c1, c2) = (a / (a - b), -b / (a - b));
+                let std = (c1 * s1 as f64).hypot(c2 * s2 as f64);
+                (c1 * p1 + c2 * p2, std, None)
+            }
+            None => {
+                let fref = match sat {
+                    GnssSatellite::Beidou(_) => BDS_B1I,
+                    _ => GPS_L1,
+                };
+                let tgd = eph.tgd * (fref / f1).powi(2);
+                (p1 - SPEED_OF_LIGHT * tgd, s1 as f64, Some(f1))
+            }
+        };
+        Some(Observation {
+            sat,
+            eph,
+            scale,
+            range: range + SPEED_OF_LIGHT * state.clock_bias,
+            state,
+            std,
+            freq,
+        })
+    }
+
+    fn solve_meas<'a, I: IntoIterator<Item = (GnssSatellite, &'a [CarrierMeas])>>(
+        &self,
+        time: GnssTime,
+        meas: I,
+        nav: &NavStore,
+    ) -> Result<SppSolution, &'static str> {
+        let mut obs: Vec<Observation> = meas
+            .into_iter()
+            .filter_map(|(sat, meas)| self.observation(time, sat, meas, nav))
+            .collect();
+        obs.sort_by_key(|o| o.sat);
+        let scales: Vec<TimeScale> = SCALES
+            .into_iter()
+            .filter(|s| obs.iter().any(|o| o.scale == *s))
+            .collect();
+        let klobuchar = match self.config.iono {
+            IonoCorrection::Auto | IonoCorrection::Klobuchar => nav.klobuchar(),
+            _ => None,
+        };
+        let utc = time.to_utc(&Default::default());
+        let mut x = vec![0.0; 3 + scales.len()];
+        let mut result = None;
+        for _ in 0..self.config.max_iter {
+            let pos = Ecef::new(x[0], x[1], x[2]);
+            let converged = pos.norm() > MIN_RADIUS;
+            let loc = pos.to_geodetic();
+            let mut design = Vec::with_capacity(obs.len());
+            let mut resid = Vec::with_capacity(obs.len());
+            let mut used = Vec::with_capacity(obs.len());
+            for o in obs.iter() {
+                let sp = o.state.position;
+                let we = o.eph.earth_rotation();
+                let range = sp.distance(&pos) + we * (sp.x * pos.y - sp.y * pos.x) / SPEED_OF_LIGHT;
+                let mut std = o.std;
+                let mut model = range;
+                if converged {
+                    let enu = loc.enu(&sp);
+                    let (az, el) = (enu.azimuth(), enu.elevation());
+                    if el < self.config.elevation_mask {
+                        continue;
+                    }
+                    std = std.hypot(ZENITH_ERROR / el.to_radians().sin());
+                    if let Some(tropo) = self.config.troposphere.as_ref() {
+                        model += tropo.slant_delay(&loc, day_of_year(utc), el);
+                    }
+                    if let (Some(freq), Some(klob)) = (o.freq, klobuchar.as_ref()) {
+                        let (delay, _) = klob.l1_delay(utc, loc, az, el);
+                        model += delay * (GPS_L1 / freq).powi(2);
+                    }
+                }
+                let clk = scales.iter().position(|s| *s == o.scale).unwrap_or(0);
+                model += x[3 + clk];
+                let mut row = vec![0.0; x.len()];
+                row[0] = (pos.x - sp.x) / range;
+                row[1] = (pos.y - sp.y) / range;
+                row[2] = (pos.z - sp.z) / range;
+                row[3 + clk] = 1.0;
+                design.push(row);
+                resid.push(Uncertain(o.range - model, std));
+                used.push(o.sat);
+            }
+            if design.len() < x.len() {
+                return Err("Not enough satellites for a solution");
+            }
+            let ls = wls(&design, &resid).ok_or("Singular geometry")?;
+            x.iter_mut().zip(ls.params()).for_each(|(x, dx)| *x += dx.0);
+            let step = ls.params()[..3].iter().map(|dx| dx.0 * dx.0).sum::<f64>();
+            let done = converged && step < 1e-8;
+            result = Some((design, used, ls));
+            if done {
+                break;
+            }
+        }
+        let (design, satellites, ls) = result.ok_or("No iterations")?;
+        let position = Ecef::new(x[0], x[1], x[2]);
+        // Unweighted cofactor matrix for the dilution of precision
+        let n = x.len();
+        let mut ata = vec![vec![0.0; n]; n];
+        for row in design.iter() {
+            for (a, ri) in ata.iter_mut().zip(row) {
+                a.iter_mut().zip(row).for_each(|(a, rj)| *a += ri * rj);
+            }
+        }
+        let q = invert(&ata).ok_or("Singular geometry")?;
+        let rot = enu_rotation(&position.to_geodetic());
+        let q_enu = |k: usize| {
+            (0..3)
+                .flat_map(|i| (0..3).map(move |j| (i, j)))
+                .map(|(i, j)| rot[k][i] * q[i][j] * rot[k][j])
+                .sum::<f64>()
+        };
+        let (qe, qn, qu) = (q_enu(0), q_enu(1), q_enu(2));
+        let dop = Dop {
+            gdop: (0..n).map(|i| q[i][i]).sum::<f64>().sqrt(),
+            pdop: (qe + qn + qu).sqrt(),
+            hdop: (qe + qn).sqrt(),
+            vdop: qu.sqrt(),
+            tdop: q[3][3].sqrt(),
+        };
+        Ok(SppSolution {
+            time,
+            position,
+            covariance: ls.covariance()[..3]
+                .iter()
+                .map(|row| row[..3].to_vec())
+                .collect(),
+            clock_bias: scales
+                .iter()
+                .enumerate()
+                .map(|(i, s)| (*s, x[3 + i] / SPEED_OF_LIGHT))
+                .collect(),
+            dop,
+            satellites,
+            residuals: ls.residuals().clone(),
+        })
+    }
+}
+
+mod test {
+    #[test]
+    fn test_spp() {
+        use super::*;
+        use crate::ubx::Frequency;
+        use crate::{GalileoFreq, GnssFreq, GpsFreq, TrkStat};
+        let rx = Geodetic::new(40.0, -105.0, 1600.0);
+        let rx_ecef = rx.to_ecef();
+        let (dtr_gps, dtr_gal) = (1e-4, 1e-4 + 3e-8);
+        // Receiver moving at 25 m/s to the south-east while climbing, with a clock drift
+        let vel = Enu::new(20.0, -15.0, 2.0).to_ecef(&rx) - rx_ecef;
+        let drift = 5e-8;
+        let time = GnssTime::from_week_tow(TimeScale::Gpst, 2300, 345_700.0).unwrap();
+        // Circular orbits in six GPS planes and three Galileo planes
+        let orbit = |satellite, sqrt_a: f64, incl: f64, omega0: f64, m0: f64| Ephemeris {
+            satellite,
+            iode: 1,
+            week: 0,
+            health: 0,
+            toe: 345_600.0,
+            toc: 345_600.0,
+            sqrt_a,
+            e: 0.0,
+            i0: incl.to_radians(),
+            omega0: omega0.to_radians(),
+            omega: 0.0,
+            m0: m0.to_radians(),
+            delta_n: 0.0,
+            idot: 0.0,
+            omega_dot: 0.0,
+            cuc: 0.0,
+            cus: 0.0,
+            crc: 0.0,
+            crs: 0.0,
+            cic: 0.0,
+            cis: 0.0,
+            af0: 2e-5,
+            af1: 0.0,
+            af2: 0.0,
+            tgd: 0.0,
+        };
+        let mut nav = NavStore::new();
+        for i in 0..24u8 {
+            let (plane, slot) = ((i / 4) as f64, (i % 4) as f64);
+            let sat = GnssSatellite::Gps(i + 1);
+            nav.insert_ephemeris(orbit(
+                sat,
+                5153.7,
+                55.0,
+                60.0 * plane,
+                90.0 * slot + 15.0 * plane,
+            ));
+        }
+        for i in 0..24u8 {
+            let (plane, slot) = ((i / 8) as f64, (i % 8) as f64);
+            let sat = GnssSatellite::Galileo(i + 1);
+            nav.insert_ephemeris(orbit(
+                sat,
+                5440.6,
+                56.0,
+                120.0 * plane,
+                45.0 * slot + 15.0 * plane,
+            ));
+        }
+        // Error-free pseudo-ranges of the visible satellites
+        let meas = |channel: GnssFreq, pr: f64, rate: f64| CarrierMeas {
+            channel,
+            pseudo_range: Some((pr, 0.5)),
+            carrier_phase: None,
+            doppler: ((-rate * channel.get_freq() / SPEED_OF_LIGHT) as f32, 0.1),
+            locktime: 0,
+            carrier_snr: 40,
+            trk_stat: TrkStat::from(0x1),
+        };
+        let mut rawx = UbxRxmRawx {
+            timestamp: time.to_utc(&Default::default()),
+            rcv_tow: 345_700.0 + dtr_gps,
+            week: 2300,
+            leap_second: 18,
+            receiver_status: Default::default(),
+            version: 1,
+            meas: HashMap::new(),
+        };
+        for (sat, eph) in nav.ephemerides() {
+            let mut tau = 0.07;
+            let mut state = None;
+            for _ in 0..5 {
+                let st = eph
+                    .state(time - chrono::TimeDelta::nanoseconds((tau * 1e9) as i64))
+                    .unwrap();
+                let sp = st.position;
+                let range = sp.distance(&rx_ecef)
+                    + eph.earth_rotation() * (sp.x * rx_ecef.y - sp.y * rx_ecef.x) / SPEED_OF_LIGHT;
+                tau = range / SPEED_OF_LIGHT;
+                state = Some((st, range));
+            }
+            let (st, range) = state.unwrap();
+            if rx.enu(&st.position).elevation() < 15.0 {
+                continue;
+            }
+            // Range rate of a moving receiver
+            let (sp, sv, rp, rv) = (st.position, st.velocity, rx_ecef, vel);
+            let los = sp - rp;
+            let rate = ((sv.x - rv.x) * los.x + (sv.y - rv.y) * los.y + (sv.z - rv.z) * los.z)
+                / los.norm()
+                + eph.earth_rotation() * (sv.x * rp.y + sp.x * rv.y - sv.y * rp.x - sp.y * rv.x)
+                    / SPEED_OF_LIGHT
+                + SPEED_OF_LIGHT * (drift - st.clock_drift);
+            let m = match sat {
+                GnssSatellite::Gps(_) => {
+                    let pr = range + SPEED_OF_LIGHT * (dtr_gps - st.clock_bias);
+                    vec![
+                        meas(GpsFreq::L1CA.into(), pr, rate),
+                        meas(GpsFreq::L2CL.into(), pr, rate),
+                    ]
+                }
+                _ => {
+                    let pr = range + SPEED_OF_LIGHT * (dtr_gal - st.clock_bias);
+                    vec![meas(GalileoFreq::E1C.into(), pr, rate)]
+                }
+            };
+            rawx.meas.insert(*sat, m);
+        }
+
+        let spp = Spp::new(SppConfig {
+            troposphere: None,
+            ..Default::default()
+        });
+        let sol = spp.solve_rawx(&rawx, &nav).unwrap();
+        assert_eq!(sol.satellites().len(), rawx.meas.len());
+        assert!(sol.offset_from(&rx).range() < 1e-3);
+        assert!((sol.clock_bias(TimeScale::Gpst).unwrap() - dtr_gps).abs() < 1e-11);
+        assert!((sol.clock_bias(TimeScale::Gst).unwrap() - dtr_gal).abs() < 1e-11);
+        assert!(sol.residuals().iter().all(|r| r.abs() < 1e-3));
+        let dop = sol.dop();
+        assert!(dop.hdop > 0.0 && dop.vdop > 0.0 && dop.gdop > dop.pdop);
+        assert!((dop.pdop.powi(2) - dop.hdop.powi(2) - dop.vdop.powi(2)).abs() < 1e-9);
+
+        // Doppler velocity, within the rounding of the Doppler measurements
+        let sol = spp.velocity_rawx(&rawx, &nav, &sol.location()).unwrap();
+        let enu = sol.enu();
+        assert!((enu.east - 20.0).abs() < 1e-3);
+        assert!((enu.north + 15.0).abs() < 1e-3);
+        assert!((enu.up - 2.0).abs() < 1e-3);
+        assert!((sol.ground_speed().0 - 25.0).abs() < 1e-3);
+        assert!(sol.ground_speed().1 > 0.0);
+        assert!((sol.true_heading() - 126.87).abs() < 1e-2);
+        assert!((sol.clock_drift().0 - drift).abs() < 1e-11);
+
+        // Only dual-frequency satellites
+        let spp = Spp::new(SppConfig {
+            troposphere: None,
+            iono: IonoCorrection::DualFrequency,
+            ..Default::default()
+        });
+        let sol = spp.solve_rawx(&rawx, &nav).unwrap();
+        assert!(sol.clock_bias(TimeScale::Gst).is_none());
+        assert!(sol
+            .satellites()
+            .iter()
+            .all(|s| matches!(s, GnssSatellite::Gps(_))));
+        assert!(sol.offset_from(&rx).range() < 1e-3);
+    }
+}
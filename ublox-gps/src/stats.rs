@@ -0,0 +1,582 @@
+//! Weighted statistics, robust estimators and time-series utilities for
+//! uncertain values
+
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{roti::window_start, uncertain::Uncertain};
+
+/// Scale factor between the median absolute deviation and the standard
+/// deviation of normally distributed data
+const MAD_SCALE: f64 = 1.482_602_218_505_602;
+
+/// Get the weights of a set of values.
+///
+/// Values are weighted by their inverse variance, and values with zero or
+/// non-finite uncertainty are ignored. If no value has a valid uncertainty,
+/// all finite values are weighted equally.
+///
+/// # Returns
+/// - The weights, and whether they are inverse variances
+fn weights(values: &[Uncertain<f64>]) -> (Vec<f64>, bool) {
+    let valid = |x: &Uncertain<f64>| x.1.is_finite() && x.1 > 0.0 && x.0.is_finite();
+    if values.iter().any(valid) {
+        let w = values
+            .iter()
+            .map(|x| if valid(x) { 1.0 / (x.1 * x.1) } else { 0.0 })
+            .collect();
+        (w, true)
+    } else {
+        let w = values
+            .iter()
+            .map(|x| if x.0.is_finite() { 1.0 } else { 0.0 })
+            .collect();
+        (w, false)
+    }
+}
+
+/// Get the inverse-variance weighted mean of a set of values.
+///
+/// Values with zero or non-finite uncertainty are ignored. If no value has
+/// a valid uncertainty, returns the arithmetic mean and its standard error.
+pub fn weighted_mean(values: &[Uncertain<f64>]) -> Option<Uncertain<f64>> {
+    let (w, weighted) = weights(values);
+    let n = w.iter().filter(|w| **w > 0.0).count();
+    let wsum: f64 = w.iter().sum();
+    if n == 0 {
+        return None;
+    }
+    let mean = values.iter().zip(&w).map(|(x, w)| w * x.0).sum::<f64>() / wsum;
+    let err = if weighted {
+        wsum.sqrt().recip()
+    } else if n > 1 {
+        let var = values
+            .iter()
+            .zip(&w)
+            .map(|(x, w)| w * (x.0 - mean).powi(2))
+            .sum::<f64>()
+            / (n - 1) as f64;
+        (var / n as f64).sqrt()
+    } else {
+        0.0
+    };
+    Some(Uncertain(mean, err))
+}
+
+/// Get the weighted standard deviation of a set of values about their
+/// weighted mean.
+pub fn weighted_std(values: &[Uncertain<f64>]) -> Option<f64> {
+    let (w, _) = weights(values);
+    let wsum: f64 = w.iter().sum();
+    if w.iter().filter(|w| **w > 0.0).count() < 2 {
+        return None;
+    }
+    let mean = values.iter().zip(&w).map(|(x, w)| w * x.0).sum::<f64>() / wsum;
+    let var = values
+        .iter()
+        .zip(&w)
+        .map(|(x, w)| w * (x.0 - mean).powi(2))
+        .sum::<f64>()
+        / wsum;
+    Some(var.sqrt())
+}
+
+/// Get the median of a set of values, ignoring non-finite values.
+pub fn median(values: &[f64]) -> Option<f64> {
+    let mut v: Vec<f64> = values.iter().copied().filter(|x| x.is_finite()).collect();
+    if v.is_empty() {
+        return None;
+    }
+    v.sort_by(f64::total_cmp);
+    let n = v.len();
+    Some(if n % 2 == 1 {
+        v[n / 2]
+    } else {
+        0.5 * (v[n / 2 - 1] + v[n / 2])
+    })
+}
+
+/// Get the median absolute deviation of a set of values, scaled to be
+/// a consistent estimator of the standard deviation of normal data.
+pub fn mad(values: &[f64]) -> Option<f64> {
+    let med = median(values)?;
+    let dev: Vec<f64> = values.iter().map(|x| (x - med).abs()).collect();
+    Some(MAD_SCALE * median(&dev)?)
+}
+
+/// Reject outliers further than `k` scaled median absolute deviations
+/// from the median.
+///
+/// # Returns
+/// - A mask of the values that are kept
+pub fn mad_clip(values: &[f64], k: f64) -> Vec<bool> {
+    match (median(values), mad(values)) {
+        (Some(med), Some(mad)) => values
+            .iter()
+            .map(|x| x.is_finite() && (x - med).abs() <= k * mad)
+            .collect(),
+        _ => vec![false; values.len()],
+    }
+}
+
+/// Iteratively reject outliers further than `k` weighted standard
+/// deviations from the weighted mean.
+///
+/// # Arguments
+/// - `values`: The values
+/// - `k`: Rejection threshold, in standard deviations
+/// - `max_iter`: Maximum number of rejection passes
+///
+/// # Returns
+/// - A mask of the values that are kept
+pub fn sigma_clip(values: &[Uncertain<f64>], k: f64, max_iter: usize) -> Vec<bool> {
+    let mut keep: Vec<bool> = values.iter().map(|x| x.0.is_finite()).collect();
+    for _ in 0..max_iter {
+        let kept: Vec<Uncertain<f64>> = values
+            .iter()
+            .zip(&keep)
+            .filter(|(_, k)| **k)
+            .map(|(x, _)| *x)
+            .collect();
+        let (Some(mean), Some(std)) = (weighted_mean(&kept), weighted_std(&kept)) else {
+            break;
+        };
+        let mut changed = false;
+        for (x, keep) in values.iter().zip(keep.iter_mut()) {
+            if *keep && (x.0 - mean.0).abs() > k * std {
+                *keep = false;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    keep
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Result of a weighted least squares fit
+pub struct LeastSquares {
+    params: Vec<Uncertain<f64>>,
+    covariance: Vec<Vec<f64>>,
+    residuals: Vec<f64>,
+    chi2: f64,
+    dof: usize,
+}
+
+impl LeastSquares {
+    /// Get the estimated parameters and their uncertainties
+    pub fn params(&self) -> &Vec<Uncertain<f64>> {
+        &self.params
+    }
+
+    /// Get the covariance matrix of the parameters
+    pub fn covariance(&self) -> &Vec<Vec<f64>> {
+        &self.covariance
+    }
+
+    /// Get the residuals of the observations (observed - fitted)
+    pub fn residuals(&self) -> &Vec<f64> {
+        &self.residuals
+    }
+
+    /// Get the weighted sum of squared residuals
+    pub fn chi2(&self) -> f64 {
+        self.chi2
+    }
+
+    /// Get the number of degrees of freedom
+    pub fn dof(&self) -> usize {
+        self.dof
+    }
+
+    /// Get the reduced chi-squared (chi-squared per degree of freedom)
+    pub fn reduced_chi2(&self) -> f64 {
+        self.chi2 / self.dof as f64
+    }
+}
+
+/// Invert a symmetric positive definite matrix
+pub(crate) fn invert(m: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+    let n = m.len();
+    let mut a: Vec<Vec<f64>> = m
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let mut row = row.clone();
+            row.extend((0..n).map(|j| if i == j { 1.0 } else { 0.0 }));
+            row
+        })
+        .collect();
+    // Gauss-Jordan elimination with partial pivoting
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() < 1e-300 {
+            return None;
+        }
+        a.swap(col, pivot);
+        let p = a[col][col];
+        a[col].iter_mut().for_each(|x| *x /= p);
+        let prow = a[col].clone();
+        for (i, row) in a.iter_mut().enumerate() {
+            let f = row[col];
+            if i != col && f != 0.0 {
+                row.iter_mut().zip(&prow).for_each(|(x, p)| *x -= f * p);
+            }
+        }
+    }
+    Some(a.into_iter().map(|row| row[n..].to_vec()).collect())
+}
+
+/// Weighted least squares solution of `design * params = observations`.
+///
+/// Observations are weighted by their inverse variance. If no observation
+/// has a valid uncertainty, all observations are weighted equally and the
+/// parameter covariance is scaled by the residual variance.
+///
+/// # Arguments
+/// - `design`: Design matrix, one row per observation
+/// - `obs`: Observations
+pub fn wls(design: &[Vec<f64>], obs: &[Uncertain<f64>]) -> Option<LeastSquares> {
+    let m = design.first()?.len();
+    if design.len() != obs.len() || design.iter().any(|row| row.len() != m) {
+        return None;
+    }
+    let (w, weighted) = weights(obs);
+    let n = w.iter().filter(|w| **w > 0.0).count();
+    if n < m {
+        return None;
+    }
+    let mut ata = vec![vec![0.0; m]; m];
+    let mut atb = vec![0.0; m];
+    for ((row, y), w) in design.iter().zip(obs).zip(&w) {
+        if *w == 0.0 {
+            continue;
+        }
+        for i in 0..m {
+            atb[i] += w * row[i] * y.0;
+            for j in 0..m {
+                ata[i][j] += w * row[i] * row[j];
+            }
+        }
+    }
+    let mut covariance = invert(&ata)?;
+    let x: Vec<f64> = covariance
+        .iter()
+        .map(|row| row.iter().zip(&atb).map(|(a, b)| a * b).sum())
+        .collect();
+    let residuals: Vec<f64> = design
+        .iter()
+        .zip(obs)
+        .map(|(row, y)| y.0 - row.iter().zip(&x).map(|(a, b)| a * b).sum::<f64>())
+        .collect();
+    let chi2 = residuals.iter().zip(&w).map(|(r, w)| w * r * r).sum();
+    let dof = n - m;
+    if !weighted {
+        let scale = if dof > 0 { chi2 / dof as f64 } else { 0.0 };
+        covariance.iter_mut().flatten().for_each(|x| *x *= scale);
+    }
+    let params = x
+        .iter()
+        .enumerate()
+        .map(|(i, x)| Uncertain(*x, covariance[i][i].max(0.0).sqrt()))
+        .collect();
+    Some(LeastSquares {
+        params,
+        covariance,
+        residuals,
+        chi2,
+        dof,
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A polynomial fitted by weighted least squares.
+///
+/// The polynomial is expressed in the normalized variable
+/// `u = (x - offset) / scale` for numerical stability.
+pub struct PolyFit {
+    offset: f64,
+    scale: f64,
+    fit: LeastSquares,
+}
+
+impl PolyFit {
+    /// Fit a polynomial of the given degree.
+    pub fn new(x: &[f64], y: &[Uncertain<f64>], degree: usize) -> Option<Self> {
+        if x.len() != y.len() || x.is_empty() {
+            return None;
+        }
+        let (lo, hi) = x
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), x| {
+                (lo.min(*x), hi.max(*x))
+            });
+        let offset = 0.5 * (lo + hi);
+        let scale = if hi > lo { 0.5 * (hi - lo) } else { 1.0 };
+        let design: Vec<Vec<f64>> = x
+            .iter()
+            .map(|x| {
+                let u = (x - offset) / scale;
+                (0..=degree).map(|k| u.powi(k as i32)).collect()
+            })
+            .collect();
+        Some(Self {
+            offset,
+            scale,
+            fit: wls(&design, y)?,
+        })
+    }
+
+    /// Get the offset of the normalized variable
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+
+    /// Get the scale of the normalized variable
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Get the coefficients in the normalized variable, in increasing order
+    pub fn coeffs(&self) -> &Vec<Uncertain<f64>> {
+        self.fit.params()
+    }
+
+    /// Get the least squares solution
+    pub fn fit(&self) -> &LeastSquares {
+        &self.fit
+    }
+
+    /// Evaluate the polynomial
+    pub fn eval(&self, x: f64) -> f64 {
+        let u = (x - self.offset) / self.scale;
+        self.coeffs().iter().rev().fold(0.0, |acc, c| acc * u + c.0)
+    }
+}
+
+/// Remove a polynomial trend of the given degree from a set of values.
+/// Degree 1 removes a linear trend.
+///
+/// # Returns
+/// - The residuals of the values about the trend, with their original
+///   uncertainties
+pub fn detrend(x: &[f64], y: &[Uncertain<f64>], degree: usize) -> Option<Vec<Uncertain<f64>>> {
+    let fit = PolyFit::new(x, y, degree)?;
+    Some(
+        x.iter()
+            .zip(y)
+            .map(|(x, y)| Uncertain(y.0 - fit.eval(*x), y.1))
+            .collect(),
+    )
+}
+
+/// Seconds elapsed since `t0`
+fn seconds(t: DateTime<Utc>, t0: DateTime<Utc>) -> f64 {
+    (t - t0).num_nanoseconds().unwrap_or(i64::MAX) as f64 * 1e-9
+}
+
+/// Remove a polynomial trend of the given degree from a time series.
+pub fn detrend_series(
+    series: &[(DateTime<Utc>, Uncertain<f64>)],
+    degree: usize,
+) -> Option<Vec<(DateTime<Utc>, Uncertain<f64>)>> {
+    let t0 = series.first()?.0;
+    let x: Vec<f64> = series.iter().map(|(t, _)| seconds(*t, t0)).collect();
+    let y: Vec<Uncertain<f64>> = series.iter().map(|(_, y)| *y).collect();
+    let res = detrend(&x, &y, degree)?;
+    Some(series.iter().map(|(t, _)| *t).zip(res).collect())
+}
+
+/// Resample a time series onto a regular time grid by averaging.
+///
+/// Samples are grouped into bins of length `step`, aligned to multiples of
+/// `step` since the UNIX epoch, and each non-empty bin is replaced by the
+/// weighted mean of its samples, labelled with the start of the bin.
+pub fn resample(
+    series: &[(DateTime<Utc>, Uncertain<f64>)],
+    step: Duration,
+) -> Vec<(DateTime<Utc>, Uncertain<f64>)> {
+    let mut series = series.to_vec();
+    series.sort_by_key(|(t, _)| *t);
+    series
+        .chunk_by(|a, b| window_start(a.0, step) == window_start(b.0, step))
+        .filter_map(|bin| {
+            let values: Vec<Uncertain<f64>> = bin.iter().map(|(_, y)| *y).collect();
+            Some((window_start(bin[0].0, step), weighted_mean(&values)?))
+        })
+        .collect()
+}
+
+/// Linearly interpolate a time series at the given times.
+///
+/// # Arguments
+/// - `series`: Time series, sorted by time
+/// - `times`: Times at which to interpolate
+/// - `max_gap`: Maximum gap between the bracketing samples
+///
+/// # Returns
+/// - The interpolated values, or `None` for times outside the series or
+///   within a gap
+pub fn interpolate(
+    series: &[(DateTime<Utc>, Uncertain<f64>)],
+    times: &[DateTime<Utc>],
+    max_gap: Duration,
+) -> Vec<Option<Uncertain<f64>>> {
+    let max_gap = TimeDelta::from_std(max_gap).unwrap_or(TimeDelta::max_value());
+    times
+        .iter()
+        .map(|t| {
+            let idx = series.partition_point(|(ts, _)| ts < t);
+            match (idx.checked_sub(1).map(|i| series[i]), series.get(idx)) {
+                (_, Some((t1, y1))) if t1 == t => Some(*y1),
+                (Some((t0, y0)), Some((t1, y1))) if *t1 - t0 <= max_gap => {
+                    let w = seconds(*t, t0) / seconds(*t1, t0);
+                    Some(Uncertain(
+                        (1.0 - w) * y0.0 + w * y1.0,
+                        ((1.0 - w) * y0.1).hypot(w * y1.1),
+                    ))
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Get a regular time grid from `start` (inclusive) to `end` (exclusive).
+pub fn time_grid(start: DateTime<Utc>, end: DateTime<Utc>, step: Duration) -> Vec<DateTime<Utc>> {
+    let Ok(step) = TimeDelta::from_std(step) else {
+        return Vec::new();
+    };
+    if step <= TimeDelta::zero() {
+        return Vec::new();
+    }
+    std::iter::successors(Some(start), |t| Some(*t + step))
+        .take_while(|t| *t < end)
+        .collect()
+}
+
+/// Get the weighted standard deviation of a time series over windows of
+/// length `window`, aligned to multiples of `window` since the UNIX epoch.
+///
+/// # Returns
+/// - The start of each window with at least two samples, and the standard
+///   deviation of its samples
+pub fn windowed_std(
+    series: &[(DateTime<Utc>, Uncertain<f64>)],
+    window: Duration,
+) -> Vec<(DateTime<Utc>, f64)> {
+    let mut series = series.to_vec();
+    series.sort_by_key(|(t, _)| *t);
+    series
+        .chunk_by(|a, b| window_start(a.0, window) == window_start(b.0, window))
+        .filter_map(|bin| {
+            let values: Vec<Uncertain<f64>> = bin.iter().map(|(_, y)| *y).collect();
+            Some((window_start(bin[0].0, window), weighted_std(&values)?))
+        })
+        .collect()
+}
+
+mod test {
+    #[test]
+    fn test_stats() {
+        use super::*;
+        // Weighted mean and standard deviation
+        let v = [
+            Uncertain(1.0, 1.0),
+            Uncertain(3.0, 1.0),
+            Uncertain(10.0, 0.0),
+        ];
+        let mean = weighted_mean(&v).unwrap();
+        assert_eq!(mean.0, 2.0);
+        assert!((mean.1 - 0.5f64.sqrt()).abs() < 1e-12);
+        assert_eq!(weighted_std(&v), Some(1.0));
+        let mean = weighted_mean(&[Uncertain(1.0, 0.0), Uncertain(3.0, 0.0)]).unwrap();
+        assert_eq!((mean.0, mean.1), (2.0, 1.0));
+        assert!(weighted_mean(&[]).is_none());
+
+        // Robust statistics
+        let x = [1.0, 2.0, 3.0, 4.0, 100.0];
+        assert_eq!(median(&x), Some(3.0));
+        assert!((mad(&x).unwrap() - MAD_SCALE).abs() < 1e-12);
+        assert_eq!(mad_clip(&x, 3.0), [true, true, true, true, false]);
+        let mut v: Vec<Uncertain<f64>> = (0..20)
+            .map(|i| Uncertain(if i % 2 == 0 { 1.0 } else { -1.0 }, 0.0))
+            .collect();
+        v.push(Uncertain(50.0, 0.0));
+        let keep = sigma_clip(&v, 3.0, 5);
+        assert_eq!(keep.iter().filter(|k| !**k).count(), 1);
+        assert!(!keep[20]);
+
+        // Polynomial fits
+        let x: Vec<f64> = (0..50).map(|i| i as f64 * 60.0).collect();
+        let y: Vec<Uncertain<f64>> = x
+            .iter()
+            .map(|x| Uncertain(2.0 + 0.01 * x - 1e-6 * x * x, 0.1))
+            .collect();
+        let fit = PolyFit::new(&x, &y, 2).unwrap();
+        assert!((fit.eval(600.0) - y[10].0).abs() < 1e-9);
+        assert!(fit.fit().chi2() < 1e-12);
+        assert_eq!(fit.fit().dof(), 47);
+        let res = detrend(&x, &y, 2).unwrap();
+        assert!(res.iter().all(|r| r.0.abs() < 1e-9 && r.1 == 0.1));
+        let res = detrend(&x, &y, 1).unwrap();
+        assert!(res.iter().any(|r| r.0.abs() > 0.1));
+
+        // Weighted least squares, y = a + b x with known uncertainties
+        let design = vec![vec![1.0, 0.0], vec![1.0, 1.0], vec![1.0, 2.0]];
+        let obs = [
+            Uncertain(1.0, 0.5),
+            Uncertain(3.0, 0.5),
+            Uncertain(5.0, 0.5),
+        ];
+        let ls = wls(&design, &obs).unwrap();
+        assert!((ls.params()[0].0 - 1.0).abs() < 1e-12);
+        assert!((ls.params()[1].0 - 2.0).abs() < 1e-12);
+        // var(b) = sigma^2 / sum((x - mean x)^2) = 0.25 / 2
+        assert!((ls.params()[1].1 - 0.125f64.sqrt()).abs() < 1e-12);
+        assert!(wls(&design[..1], &obs[..1]).is_none());
+    }
+
+    #[test]
+    fn test_series() {
+        use super::*;
+        let t0 = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let series: Vec<_> = (0..10)
+            .map(|i| {
+                let t = t0 + TimeDelta::seconds(15 * i);
+                (t, Uncertain(i as f64, 1.0))
+            })
+            .collect();
+        // 1-minute bins, starting 20 s into a minute
+        let res = resample(&series, Duration::from_secs(60));
+        assert_eq!(res.len(), 3);
+        assert_eq!(
+            res[0].0,
+            DateTime::from_timestamp(1_699_999_980, 0).unwrap()
+        );
+        assert_eq!(res[0].1 .0, 1.0);
+        assert_eq!(res[0].1 .1, 3f64.sqrt().recip());
+        assert_eq!(res[1].1 .0, 4.5);
+
+        let grid = time_grid(t0, t0 + TimeDelta::seconds(150), Duration::from_secs(10));
+        assert_eq!(grid.len(), 15);
+        let res = interpolate(&series, &grid, Duration::from_secs(20));
+        assert_eq!(res[0].map(|v| (v.0, v.1)), Some((0.0, 1.0)));
+        let v = res[1].unwrap();
+        assert!((v.0 - 10.0 / 15.0).abs() < 1e-12);
+        assert!(v.1 < 1.0);
+        assert!(res[14].is_none());
+        assert!(interpolate(&series, &grid, Duration::from_secs(10))[1].is_none());
+
+        let std = windowed_std(&series, Duration::from_secs(60));
+        assert_eq!(std.len(), 3);
+        assert!((std[1].1 - 1.25f64.sqrt()).abs() < 1e-12);
+
+        let res = detrend_series(&series, 1).unwrap();
+        assert!(res.iter().all(|(_, r)| r.0.abs() < 1e-9));
+    }
+}
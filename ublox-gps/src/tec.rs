@@ -2,16 +2,162 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    geo::Geodetic,
+    iono::IonoModel,
     ubx::{Frequency, TrkStat},
     uncertain::Uncertain,
-    GnssFreq, GnssSatellite, UbxGpsInfo,
+    units::GeometryFree,
+    BeidouFreq, CarrierMeas, GalileoFreq, GlonassFreq, GnssFreq, GnssSatellite, GpsFreq, QzssFreq,
+    SatPathInfo, UbxGpsInfo,
 };
 
-fn factor(f1: f64, f2: f64) -> f64 {
-    const K: f64 = 1e-16 / 40.308;
-    let a = f1 * f1;
-    let b = f2 * f2;
-    K * a * b / (a - b)
+/// Minimum separation between the carrier frequencies of a signal pair (Hz)
+///
+/// Signals closer than this are treated as being on the same frequency
+/// (e.g. GPS L2C-L and L2C-M), for which the geometry-free combination
+/// is undefined.
+pub(crate) const MIN_FREQ_SEPARATION: f64 = 1e6;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+/// Strategy for choosing the signal pairs used to compute TEC
+pub enum PairSelection {
+    /// Use only the first pair in the preference list that is available
+    #[default]
+    Preferred,
+    /// Use every available pair in the preference list
+    Listed,
+    /// Use every available pair of signals on distinct frequencies
+    All,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Policy for pairing carrier signals in multi-frequency TEC computation.
+///
+/// The policy holds a list of preferred signal pairs, ordered by preference.
+/// The list may contain pairs from any number of constellations; for each
+/// satellite, only the pairs of its own constellation are considered.
+/// Pairs of signals on the same carrier frequency are always rejected.
+///
+/// GLONASS pairs match irrespective of the frequency channel number, i.e.
+/// `(L1OF(0), L2OF(0))` matches the L1OF/L2OF pair of every GLONASS satellite.
+pub struct SignalPairPolicy {
+    pairs: Vec<(GnssFreq, GnssFreq)>,
+    selection: PairSelection,
+}
+
+impl Default for SignalPairPolicy {
+    fn default() -> Self {
+        use BeidouFreq::*;
+        use GalileoFreq::*;
+        Self {
+            pairs: vec![
+                (GpsFreq::L1CA.into(), GpsFreq::L2CL.into()),
+                (GpsFreq::L1CA.into(), GpsFreq::L5.into()),
+                (GpsFreq::L1CA.into(), GpsFreq::L2CM.into()),
+                (E1C.into(), E5aQ.into()),
+                (E1C.into(), E5bQ.into()),
+                (E1B.into(), E5aI.into()),
+                (E1B.into(), E5bI.into()),
+                (B1I_D1.into(), B2A.into()),
+                (B1I_D1.into(), B2I_D1.into()),
+                (B1I_D2.into(), B2I_D2.into()),
+                (GlonassFreq::L1OF(0).into(), GlonassFreq::L2OF(0).into()),
+                (QzssFreq::L1CA.into(), QzssFreq::L2CL.into()),
+                (QzssFreq::L1CA.into(), QzssFreq::L5.into()),
+                (QzssFreq::L1CA.into(), QzssFreq::L2CM.into()),
+            ],
+            selection: PairSelection::Preferred,
+        }
+    }
+}
+
+impl SignalPairPolicy {
+    /// Create a new signal pair policy.
+    ///
+    /// # Arguments
+    /// - `pairs`: Signal pairs, in order of preference
+    /// - `selection`: Strategy for choosing among the available pairs
+    pub fn new(pairs: Vec<(GnssFreq, GnssFreq)>, selection: PairSelection) -> Self {
+        Self { pairs, selection }
+    }
+
+    /// Set the pair selection strategy
+    pub fn with_selection(mut self, selection: PairSelection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Get the preferred signal pairs
+    pub fn pairs(&self) -> &[(GnssFreq, GnssFreq)] {
+        &self.pairs
+    }
+
+    /// Get the pair selection strategy
+    pub fn selection(&self) -> PairSelection {
+        self.selection
+    }
+
+    /// Select signal pairs from the measurements of a single satellite.
+    ///
+    /// Each returned pair is ordered such that the first measurement
+    /// is on the higher carrier frequency.
+    pub fn select<'a>(&self, meas: &'a [CarrierMeas]) -> Vec<(&'a CarrierMeas, &'a CarrierMeas)> {
+        let mut res = Vec::new();
+        match self.selection {
+            PairSelection::Preferred | PairSelection::Listed => {
+                for (a, b) in self.pairs.iter() {
+                    let m0 = meas.iter().find(|m| same_signal(a, &m.channel));
+                    let m1 = meas.iter().find(|m| same_signal(b, &m.channel));
+                    if let (Some(m0), Some(m1)) = (m0, m1) {
+                        if let Some(pair) = order_pair(m0, m1) {
+                            res.push(pair);
+                            if self.selection == PairSelection::Preferred {
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+            PairSelection::All => {
+                for (i, m0) in meas.iter().enumerate() {
+                    for m1 in meas.iter().skip(i + 1) {
+                        if let Some(pair) = order_pair(m0, m1) {
+                            res.push(pair);
+                        }
+                    }
+                }
+            }
+        }
+        res
+    }
+}
+
+/// Check if a measured channel matches a signal in the pair list,
+/// ignoring the GLONASS frequency channel number.
+fn same_signal(pattern: &GnssFreq, channel: &GnssFreq) -> bool {
+    match (pattern, channel) {
+        (GnssFreq::Glonass(a), GnssFreq::Glonass(b)) => {
+            std::mem::discriminant(a) == std::mem::discriminant(b)
+        }
+        _ => pattern == channel,
+    }
+}
+
+/// Order a pair of measurements by descending frequency, rejecting
+/// pairs on the same carrier frequency or with an unknown signal.
+fn order_pair<'a>(
+    m0: &'a CarrierMeas,
+    m1: &'a CarrierMeas,
+) -> Option<(&'a CarrierMeas, &'a CarrierMeas)> {
+    let f0 = m0.channel.get_freq();
+    let f1 = m1.channel.get_freq();
+    if !m0.channel.is_known() || !m1.channel.is_known() || (f0 - f1).abs() < MIN_FREQ_SEPARATION {
+        None
+    } else if f0 > f1 {
+        Some((m0, m1))
+    } else {
+        Some((m1, m0))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,12 +165,14 @@ fn factor(f1: f64, f2: f64) -> f64 {
 /// from carrier phase measurements of dual-frequency
 /// GNSS receivers.
 pub struct TecData {
-    source: GnssSatellite,
-    pointing: (u16, i8),
-    channels: (GnssFreq, GnssFreq),
-    phase_tec: Option<Uncertain<f64>>,
-    range_tec: Option<Uncertain<f64>>,
-    trk_stat: (TrkStat, TrkStat),
+    pub(crate) source: GnssSatellite,
+    pub(crate) pointing: (u16, i8),
+    pub(crate) channels: (GnssFreq, GnssFreq),
+    pub(crate) phase_tec: Option<Uncertain<f64>>,
+    pub(crate) range_tec: Option<Uncertain<f64>>,
+    pub(crate) trk_stat: (TrkStat, TrkStat),
+    #[serde(default)]
+    pub(crate) modeled_tec: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,75 +180,39 @@ pub struct TecData {
 /// derived from carrier phase measurements
 /// of dual-frequency GNSS receivers in [`UbxGpsInfo`].
 pub struct TecInfo {
-    timestamp: DateTime<Utc>,
-    location: (f64, f64, f32),
-    tec: Vec<TecData>,
+    pub(crate) timestamp: DateTime<Utc>,
+    pub(crate) location: Geodetic,
+    pub(crate) tec: Vec<TecData>,
 }
 
 impl TecInfo {
     /// Assimilate carrier phase measurements from a [`UbxGpsInfo`] object
     /// to extract Total Electron Content information.
+    ///
+    /// Uses the default [`SignalPairPolicy`].
     pub fn assimilate(src: &UbxGpsInfo) -> Option<Self> {
+        Self::assimilate_with(src, &SignalPairPolicy::default())
+    }
+
+    /// Assimilate carrier phase measurements from a [`UbxGpsInfo`] object
+    /// to extract Total Electron Content information, pairing signals
+    /// according to the given [`SignalPairPolicy`].
+    pub fn assimilate_with(src: &UbxGpsInfo, policy: &SignalPairPolicy) -> Option<Self> {
         let timestamp = src.timestamp();
         let location = src.location();
         let mut tec = Vec::new();
         for (sat, ch) in src.carrier_phase() {
-            if ch.meas.len() < 2 {
-                continue;
-            }
-            let m0 = &ch.meas[0];
-            let m1 = &ch.meas[1];
-            let f0 = m0.channel.get_freq();
-            let f1 = m1.channel.get_freq();
-            let fac = factor(f0, f1);
-            let fac = Uncertain::new(fac, 0.0);
-            const SPEED_OF_LIGHT: f64 = 299_792_458.0;
-            let phase_tec = if let Some((m0_phase, m0_perr)) = m0.carrier_phase {
-                if let Some((m1_phase, m1_perr)) = m1.carrier_phase {
-                    let m0_phase = Uncertain::new(m0_phase, m0_perr as _);
-                    let m1_phase = Uncertain::new(m1_phase, m1_perr as _);
-                    let phase_tec = ((m0_phase * (SPEED_OF_LIGHT / f0).into())
-                        - (m1_phase * (SPEED_OF_LIGHT / f1).into()))
-                        * fac;
-                    Some(phase_tec)
-                } else {
-                    None
+            for (m0, m1) in policy.select(&ch.meas) {
+                if let Some(data) = TecData::from_pair(*sat, ch, m0, m1) {
+                    tec.push(data);
                 }
-            } else {
-                None
-            };
-            let range_tec = if let Some((m0_range, m0_rerr)) = m0.pseudo_range {
-                if let Some((m1_range, m1_rerr)) = m1.pseudo_range {
-                    let m0_range = Uncertain::new(m0_range, m0_rerr as _);
-                    let m1_range = Uncertain::new(m1_range, m1_rerr as _);
-                    let range_tec = (m1_range - m0_range) * fac;
-                    Some(range_tec)
-                } else {
-                    None
-                }
-            } else {
-                None
-            };
-            if phase_tec.is_none() && range_tec.is_none() {
-                continue;
             }
-            let pointing = (ch.azimuth, ch.elevation);
-            let channels = (m0.channel, m1.channel);
-            let trk_stat = (m0.trk_stat, m1.trk_stat);
-            tec.push(TecData {
-                source: *sat,
-                pointing,
-                channels,
-                phase_tec,
-                range_tec,
-                trk_stat,
-            });
         }
 
         if tec.is_empty() {
             None
         } else {
-            tec.sort_by(|a, b| a.source.cmp(&b.source));
+            tec.sort_by_key(|a| a.source);
             Some(TecInfo {
                 timestamp,
                 location,
@@ -115,8 +227,7 @@ impl TecInfo {
     }
 
     /// Get the location of the TEC information
-    /// in (latitude, longitude, altitude) format
-    pub fn location(&self) -> (f64, f64, f32) {
+    pub fn location(&self) -> Geodetic {
         self.location
     }
 
@@ -124,9 +235,62 @@ impl TecInfo {
     pub fn tec(&self) -> &Vec<TecData> {
         &self.tec
     }
+
+    /// Compute the modeled slant TEC of each sample using an ionospheric
+    /// model, for comparison with the measured TEC.
+    ///
+    /// Samples with unknown satellite elevation are left without a modeled TEC.
+    pub fn apply_model<M: IonoModel + ?Sized>(&mut self, model: &M) {
+        for data in self.tec.iter_mut() {
+            data.modeled_tec = if data.pointing.1 < 0 {
+                None
+            } else {
+                model.slant_tec(
+                    self.timestamp,
+                    self.location,
+                    data.pointing.0 as f64,
+                    data.pointing.1 as f64,
+                )
+            };
+        }
+    }
 }
 
 impl TecData {
+    /// Compute TEC from a pair of measurements, where `m0` is on the
+    /// higher carrier frequency.
+    fn from_pair(
+        source: GnssSatellite,
+        ch: &SatPathInfo,
+        m0: &CarrierMeas,
+        m1: &CarrierMeas,
+    ) -> Option<Self> {
+        let gf = GeometryFree::new(m0.channel.get_freq(), m1.channel.get_freq()).ok()?;
+        // Never combine a resolved phase with a half-cycle ambiguous one
+        let phase_tec = match (m0.phase(), m1.phase()) {
+            (Some(l0), Some(l1)) if m0.ambiguity() == m1.ambiguity() => {
+                Some(gf.phase_tec(l0, l1).0)
+            }
+            _ => None,
+        };
+        let range_tec = match (m0.range(), m1.range()) {
+            (Some(p0), Some(p1)) => Some(gf.code_tec(p0, p1).0),
+            _ => None,
+        };
+        if phase_tec.is_none() && range_tec.is_none() {
+            return None;
+        }
+        Some(TecData {
+            source,
+            pointing: (ch.azimuth, ch.elevation),
+            channels: (m0.channel, m1.channel),
+            phase_tec,
+            range_tec,
+            trk_stat: (m0.trk_stat, m1.trk_stat),
+            modeled_tec: None,
+        })
+    }
+
     /// Get the satellite source of the TEC data
     pub fn source(&self) -> GnssSatellite {
         self.source
@@ -147,12 +311,15 @@ impl TecData {
         self.channels
     }
 
-    /// Get the phase TEC information
+    /// Get the phase TEC information (TECU).
+    ///
+    /// The phase TEC is precise, but offset by an arbitrary constant over
+    /// each continuous carrier phase arc due to the phase ambiguities.
     pub fn phase_tec(&self) -> Option<Uncertain<f64>> {
         self.phase_tec
     }
 
-    /// Get the range TEC information
+    /// Get the range TEC information (TECU)
     pub fn range_tec(&self) -> Option<Uncertain<f64>> {
         self.range_tec
     }
@@ -161,4 +328,141 @@ impl TecData {
     pub fn signal_status(&self) -> (TrkStat, TrkStat) {
         self.trk_stat
     }
+
+    /// Get the modeled slant TEC, set by [`TecInfo::apply_model`]
+    pub fn modeled_tec(&self) -> Option<f64> {
+        self.modeled_tec
+    }
+
+    /// Get the residual of the range TEC with respect to the modeled TEC
+    /// (measured - modeled)
+    pub fn residual(&self) -> Option<Uncertain<f64>> {
+        Some(self.range_tec? - self.modeled_tec?.into())
+    }
+}
+
+mod test {
+    #[test]
+    fn test_pair_selection() {
+        use super::*;
+        let meas = |channel: GnssFreq| CarrierMeas {
+            channel,
+            pseudo_range: Some((2.2e7, 0.5)),
+            carrier_phase: Some((1.1e8, 0.01)),
+            doppler: (0.0, 0.0),
+            locktime: 0,
+            carrier_snr: 40,
+            trk_stat: TrkStat::new(),
+        };
+        // Sorted by descending frequency, as in UBX-RXM-RAWX decoding
+        let gps = [
+            meas(GpsFreq::L1CA.into()),
+            meas(GpsFreq::L2CM.into()),
+            meas(GpsFreq::L2CL.into()),
+            meas(GpsFreq::L5.into()),
+        ];
+        let policy = SignalPairPolicy::default();
+        let pairs = policy.select(&gps);
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(pairs[0].0.channel, GpsFreq::L1CA.into());
+        assert_eq!(pairs[0].1.channel, GpsFreq::L2CL.into());
+
+        // Same-frequency pairs are never produced
+        let policy = SignalPairPolicy::default().with_selection(PairSelection::All);
+        let pairs = policy.select(&gps);
+        assert_eq!(pairs.len(), 5);
+        assert!(pairs
+            .iter()
+            .all(|(a, b)| a.channel.get_freq() > b.channel.get_freq()));
+
+        let policy = SignalPairPolicy::new(
+            vec![(GpsFreq::L2CL.into(), GpsFreq::L2CM.into())],
+            PairSelection::Listed,
+        );
+        assert!(policy.select(&gps).is_empty());
+
+        // GLONASS pairs match any frequency channel
+        let glo = [
+            meas(GlonassFreq::L1OF(-3).into()),
+            meas(GlonassFreq::L2OF(-3).into()),
+        ];
+        let pairs = SignalPairPolicy::default().select(&glo);
+        assert_eq!(pairs.len(), 1);
+    }
+
+    #[test]
+    fn test_geometry_free() {
+        use super::*;
+        use crate::{
+            ubx::{UbxFormat, UbxMessage, UbxRxmRawx},
+            Cycles, Meters, NmeaGpsInfo, NmeaMsgGroup,
+        };
+        const C: f64 = 299_792_458.0;
+        let (f1, f2) = (GpsFreq::L1CA.get_freq(), GpsFreq::L2CL.get_freq());
+        // Synthetic UBX-RXM-RAWX frame for one GPS satellite on L1 C/A and L2 CL,
+        // with a slant TEC of `tec` and integer ambiguities `n1`, `n2`
+        let frame = |tec: f64, n1: f64, n2: f64| {
+            let range = 2.2e7;
+            let mut payload = Vec::new();
+            payload.extend_from_slice(&345_600.0f64.to_le_bytes());
+            payload.extend_from_slice(&2300u16.to_le_bytes());
+            payload.extend_from_slice(&[18, 2, 0x1, 0x1, 0, 0]);
+            for (sig_id, freq, n) in [(0u8, f1, n1), (3, f2, n2)] {
+                let iono = 40.308e16 * tec / (freq * freq);
+                let pr = range + iono;
+                let cp = (range - iono) * freq / C + n;
+                payload.extend_from_slice(&pr.to_le_bytes());
+                payload.extend_from_slice(&cp.to_le_bytes());
+                payload.extend_from_slice(&0f32.to_le_bytes());
+                payload.extend_from_slice(&[0, 7, sig_id, 0]);
+                payload.extend_from_slice(&1000u16.to_le_bytes());
+                payload.extend_from_slice(&[45, 2, 1, 1, 0x7, 0]);
+            }
+            let rxm = UbxRxmRawx::from_message(UbxMessage {
+                class: 0x2,
+                id: 0x15,
+                payload,
+            })
+            .unwrap();
+            let mut nmea = NmeaGpsInfo::default();
+            nmea.sat_views.insert(GnssSatellite::Gps(7), (60, 120));
+            let info = UbxGpsInfo::new(nmea, Some(rxm), NmeaMsgGroup(Default::default()));
+            TecInfo::assimilate(&info).unwrap()
+        };
+
+        // Without ambiguities, phase and range TEC recover the injected TEC
+        let tec = frame(25.0, 0.0, 0.0);
+        assert_eq!(tec.tec().len(), 1);
+        let data = &tec.tec()[0];
+        assert_eq!(data.source(), GnssSatellite::Gps(7));
+        assert_eq!(
+            data.channels(),
+            (GpsFreq::L1CA.into(), GpsFreq::L2CL.into())
+        );
+        assert!((data.phase_tec().unwrap().value() - 25.0).abs() < 1e-4);
+        assert!((data.range_tec().unwrap().value() - 25.0).abs() < 1e-4);
+        // Range TEC uncertainty of two 0.04 m pseudoranges
+        let gf = GeometryFree::new(f1, f2).unwrap();
+        let sigma = gf.to_tecu(Meters::new(0.0, 0.04 * 2f64.sqrt())).error();
+        assert!((data.range_tec().unwrap().error() - sigma).abs() < 1e-6);
+        // About 9.52 TECU per metre of differential delay on L1/L2
+        assert!((gf.to_tecu(Meters::new(1.0, 0.0)).value() - 9.52).abs() < 0.01);
+
+        // Ambiguities offset the phase TEC by a constant, with the same sign convention
+        let offset = gf
+            .phase(Cycles::new(1000.0, 0.0), Cycles::new(-500.0, 0.0))
+            .value();
+        let offset = gf.to_tecu(Meters::new(offset, 0.0)).value();
+        for injected in [5.0, 50.0] {
+            let tec = frame(injected, 1000.0, -500.0);
+            let data = &tec.tec()[0];
+            let phase = data.phase_tec().unwrap().value();
+            assert!((phase - offset - injected).abs() < 1e-4);
+            assert!((data.range_tec().unwrap().value() - injected).abs() < 1e-4);
+        }
+
+        // Invalid frequency pairs are rejected
+        assert!(GeometryFree::new(f2, f1).is_err());
+        assert!(GeometryFree::new(f1, f1).is_err());
+    }
 }
@@ -0,0 +1,356 @@
+use std::{
+    fmt::Display,
+    ops::{Add, Sub},
+};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::ubx::GPS_EPOCH;
+
+/// Nanoseconds in a second
+const NANOS_PER_SEC: i64 = 1_000_000_000;
+/// Nanoseconds in a week
+const NANOS_PER_WEEK: i64 = 604_800 * NANOS_PER_SEC;
+/// GPS week at the start of Galileo system time
+const GST_WEEK_OFFSET: i64 = 1024;
+/// GPS week at the start of BeiDou time
+const BDT_WEEK_OFFSET: i64 = 1356;
+/// Offset of BeiDou time from GPS time (s)
+const BDT_OFFSET: i64 = 14;
+/// Offset of GLONASS time from UTC (s)
+const GLONASST_OFFSET: i64 = 3 * 3600;
+
+/// GPS - UTC offset (s) since the given UTC date
+const LEAP_SECONDS: [(i32, u32, u32, i32); 18] = [
+    (1981, 7, 1, 1),
+    (1982, 7, 1, 2),
+    (1983, 7, 1, 3),
+    (1985, 7, 1, 4),
+    (1988, 1, 1, 5),
+    (1990, 1, 1, 6),
+    (1991, 1, 1, 7),
+    (1992, 7, 1, 8),
+    (1993, 7, 1, 9),
+    (1994, 7, 1, 10),
+    (1996, 1, 1, 11),
+    (1997, 7, 1, 12),
+    (1999, 1, 1, 13),
+    (2006, 1, 1, 14),
+    (2009, 1, 1, 15),
+    (2012, 7, 1, 16),
+    (2015, 7, 1, 17),
+    (2017, 1, 1, 18),
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// GNSS time scales
+pub enum TimeScale {
+    /// GPS time
+    Gpst,
+    /// Galileo system time
+    Gst,
+    /// BeiDou time
+    Bdt,
+    /// GLONASS time (UTC(SU) + 3 h)
+    Glonasst,
+    /// Coordinated universal time
+    Utc,
+}
+
+impl TimeScale {
+    /// Get the GPS week at the start of week 0 of this time scale, and
+    /// the offset (s) of this time scale behind GPS time.
+    ///
+    /// Returns `None` for time scales that are not continuous.
+    fn week_origin(&self) -> Option<(i64, i64)> {
+        match self {
+            TimeScale::Gpst => Some((0, 0)),
+            TimeScale::Gst => Some((GST_WEEK_OFFSET, 0)),
+            TimeScale::Bdt => Some((BDT_WEEK_OFFSET, BDT_OFFSET)),
+            TimeScale::Glonasst | TimeScale::Utc => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// Table of GPS - UTC leap second offsets
+pub struct LeapSeconds {
+    table: Vec<(DateTime<Utc>, i32)>,
+    fixed: Option<i32>,
+}
+
+impl Default for LeapSeconds {
+    fn default() -> Self {
+        let table = LEAP_SECONDS
+            .iter()
+            .filter_map(|(y, m, d, leap)| {
+                let date = NaiveDate::from_ymd_opt(*y, *m, *d)?.and_hms_opt(0, 0, 0)?;
+                Some((date.and_utc(), *leap))
+            })
+            .collect();
+        Self { table, fixed: None }
+    }
+}
+
+impl LeapSeconds {
+    /// Override the table with a fixed GPS - UTC offset (s), for example
+    /// the offset broadcast by the satellites.
+    pub fn with_override(mut self, leap: i32) -> Self {
+        self.fixed = Some(leap);
+        self
+    }
+
+    /// Add a leap second offset to the table
+    ///
+    /// # Arguments
+    /// - `since`: UTC time at which the offset takes effect
+    /// - `leap`: GPS - UTC offset (s)
+    pub fn insert(&mut self, since: DateTime<Utc>, leap: i32) {
+        let idx = self.table.partition_point(|(t, _)| *t < since);
+        match self.table.get_mut(idx) {
+            Some(entry) if entry.0 == since => entry.1 = leap,
+            _ => self.table.insert(idx, (since, leap)),
+        }
+    }
+
+    /// Get the override offset, if any
+    pub fn fixed(&self) -> Option<i32> {
+        self.fixed
+    }
+
+    /// Get the GPS - UTC offset (s) at a UTC time
+    pub fn at_utc(&self, utc: DateTime<Utc>) -> i32 {
+        if let Some(leap) = self.fixed {
+            return leap;
+        }
+        let idx = self.table.partition_point(|(t, _)| *t <= utc);
+        idx.checked_sub(1).map(|i| self.table[i].1).unwrap_or(0)
+    }
+
+    /// Get the GPS - UTC offset (s) at a GPS time
+    pub fn at_gpst(&self, time: GnssTime) -> i32 {
+        if let Some(leap) = self.fixed {
+            return leap;
+        }
+        let idx = self.table.partition_point(|(t, leap)| {
+            GnssTime::from_label(*t, *leap as i64).is_some_and(|t| t <= time)
+        });
+        idx.checked_sub(1).map(|i| self.table[i].1).unwrap_or(0)
+    }
+}
+
+#[derive(
+    Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+/// A GNSS time, stored as nanoseconds of GPS time since the GPS epoch
+/// (1980-01-06 00:00:00 UTC).
+pub struct GnssTime(i64);
+
+impl GnssTime {
+    /// Create a time from nanoseconds of GPS time since the GPS epoch
+    pub fn from_nanos(nanos: i64) -> Self {
+        Self(nanos)
+    }
+
+    /// Get the nanoseconds of GPS time since the GPS epoch
+    pub fn nanos(&self) -> i64 {
+        self.0
+    }
+
+    /// Create a time from a calendar reading `label` of a time scale that is
+    /// `offset` seconds behind GPS time
+    fn from_label(label: DateTime<Utc>, offset: i64) -> Option<Self> {
+        let ns = (label - GPS_EPOCH).num_nanoseconds()?;
+        Some(Self(ns.checked_add(offset * NANOS_PER_SEC)?))
+    }
+
+    /// Get the calendar reading of a time scale that is `offset` seconds
+    /// behind GPS time
+    fn label(&self, offset: i64) -> DateTime<Utc> {
+        GPS_EPOCH + TimeDelta::nanoseconds(self.0 - offset * NANOS_PER_SEC)
+    }
+
+    /// Create a time from a week number and time of week (ns).
+    ///
+    /// # Errors
+    /// - If the time scale is not week based (GLONASS time and UTC)
+    pub fn from_week_tow_nanos(
+        scale: TimeScale,
+        week: u32,
+        tow: i64,
+    ) -> Result<Self, &'static str> {
+        let (origin, offset) = scale.week_origin().ok_or("Time scale has no weeks")?;
+        Ok(Self(
+            (origin + week as i64) * NANOS_PER_WEEK + tow + offset * NANOS_PER_SEC,
+        ))
+    }
+
+    /// Create a time from a week number and time of week (s), rounded to
+    /// the nearest nanosecond.
+    ///
+    /// # Errors
+    /// - If the time scale is not week based (GLONASS time and UTC)
+    pub fn from_week_tow(scale: TimeScale, week: u32, tow: f64) -> Result<Self, &'static str> {
+        if !tow.is_finite() {
+            return Err("Invalid time of week");
+        }
+        Self::from_week_tow_nanos(scale, week, (tow * 1e9).round() as i64)
+    }
+
+    /// Get the week number and time of week (ns) in a time scale.
+    ///
+    /// Returns `None` for time scales that are not week based (GLONASS time
+    /// and UTC), or for times before week 0 of the time scale.
+    pub fn week_tow_nanos(&self, scale: TimeScale) -> Option<(u32, i64)> {
+        let (origin, offset) = scale.week_origin()?;
+        let ns = self.0 - offset * NANOS_PER_SEC - origin * NANOS_PER_WEEK;
+        let week = u32::try_from(ns.div_euclid(NANOS_PER_WEEK)).ok()?;
+        Some((week, ns.rem_euclid(NANOS_PER_WEEK)))
+    }
+
+    /// Get the week number and time of week (s) in a time scale.
+    ///
+    /// Returns `None` for time scales that are not week based (GLONASS time
+    /// and UTC), or for times before week 0 of the time scale.
+    pub fn week_tow(&self, scale: TimeScale) -> Option<(u32, f64)> {
+        self.week_tow_nanos(scale)
+            .map(|(week, tow)| (week, tow as f64 * 1e-9))
+    }
+
+    /// Create a time from a calendar reading in a time scale.
+    ///
+    /// Readings of GLONASS time and UTC within a leap second are ambiguous.
+    pub fn from_scale(
+        scale: TimeScale,
+        reading: NaiveDateTime,
+        leaps: &LeapSeconds,
+    ) -> Option<Self> {
+        let reading = reading.and_utc();
+        match scale {
+            TimeScale::Gpst | TimeScale::Gst => Self::from_label(reading, 0),
+            TimeScale::Bdt => Self::from_label(reading, BDT_OFFSET),
+            TimeScale::Utc => Self::from_utc(reading, leaps),
+            TimeScale::Glonasst => {
+                Self::from_utc(reading - TimeDelta::seconds(GLONASST_OFFSET), leaps)
+            }
+        }
+    }
+
+    /// Get the calendar reading in a time scale
+    pub fn to_scale(&self, scale: TimeScale, leaps: &LeapSeconds) -> NaiveDateTime {
+        match scale {
+            TimeScale::Gpst | TimeScale::Gst => self.label(0),
+            TimeScale::Bdt => self.label(BDT_OFFSET),
+            TimeScale::Utc => self.to_utc(leaps),
+            TimeScale::Glonasst => self.to_utc(leaps) + TimeDelta::seconds(GLONASST_OFFSET),
+        }
+        .naive_utc()
+    }
+
+    /// Create a time from UTC
+    pub fn from_utc(utc: DateTime<Utc>, leaps: &LeapSeconds) -> Option<Self> {
+        Self::from_label(utc, leaps.at_utc(utc) as i64)
+    }
+
+    /// Convert the time to UTC
+    pub fn to_utc(&self, leaps: &LeapSeconds) -> DateTime<Utc> {
+        self.label(leaps.at_gpst(*self) as i64)
+    }
+}
+
+impl Add<TimeDelta> for GnssTime {
+    type Output = GnssTime;
+
+    fn add(self, rhs: TimeDelta) -> GnssTime {
+        GnssTime(self.0 + rhs.num_nanoseconds().unwrap_or(i64::MAX))
+    }
+}
+
+impl Sub<TimeDelta> for GnssTime {
+    type Output = GnssTime;
+
+    fn sub(self, rhs: TimeDelta) -> GnssTime {
+        GnssTime(self.0 - rhs.num_nanoseconds().unwrap_or(i64::MAX))
+    }
+}
+
+impl Sub for GnssTime {
+    type Output = TimeDelta;
+
+    fn sub(self, rhs: GnssTime) -> TimeDelta {
+        TimeDelta::nanoseconds(self.0 - rhs.0)
+    }
+}
+
+impl Display for GnssTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.week_tow_nanos(TimeScale::Gpst) {
+            Some((week, tow)) => write!(
+                f,
+                "GPST {week}:{}.{:09}",
+                tow / NANOS_PER_SEC,
+                tow % NANOS_PER_SEC
+            ),
+            None => write!(f, "GPST {} ns", self.0),
+        }
+    }
+}
+
+mod test {
+    #[test]
+    fn test_gnss_time() {
+        use super::*;
+        let leaps = LeapSeconds::default();
+        // 2024-01-01 00:00:00 UTC = GPS week 2295, TOW 86418 s
+        let utc = DateTime::from_timestamp(1_704_067_200, 0).unwrap();
+        let time = GnssTime::from_utc(utc, &leaps).unwrap();
+        assert_eq!(time.week_tow(TimeScale::Gpst), Some((2295, 86418.0)));
+        assert_eq!(time.week_tow(TimeScale::Gst), Some((1271, 86418.0)));
+        assert_eq!(time.week_tow(TimeScale::Bdt), Some((939, 86404.0)));
+        assert_eq!(time.week_tow(TimeScale::Utc), None);
+        assert_eq!(time.to_utc(&leaps), utc);
+        assert_eq!(
+            time.to_scale(TimeScale::Glonasst, &leaps),
+            (utc + TimeDelta::hours(3)).naive_utc()
+        );
+        for scale in [
+            TimeScale::Gpst,
+            TimeScale::Gst,
+            TimeScale::Bdt,
+            TimeScale::Glonasst,
+            TimeScale::Utc,
+        ] {
+            let reading = time.to_scale(scale, &leaps);
+            assert_eq!(GnssTime::from_scale(scale, reading, &leaps), Some(time));
+        }
+
+        // Nanosecond round trip
+        let time = GnssTime::from_week_tow_nanos(TimeScale::Bdt, 939, 86_404_123_456_789).unwrap();
+        assert_eq!(
+            time.week_tow_nanos(TimeScale::Bdt),
+            Some((939, 86_404_123_456_789))
+        );
+        let utc = time.to_utc(&leaps);
+        assert_eq!(utc.timestamp_subsec_nanos(), 123_456_789);
+        assert_eq!(GnssTime::from_utc(utc, &leaps), Some(time));
+        assert_eq!(time.to_string(), "GPST 2295:86418.123456789");
+        assert!(GnssTime::from_week_tow(TimeScale::Utc, 0, 0.0).is_err());
+
+        // Leap second boundaries
+        let before = DateTime::from_timestamp(1_483_228_799, 0).unwrap();
+        assert_eq!(leaps.at_utc(before), 17);
+        assert_eq!(leaps.at_utc(before + TimeDelta::seconds(1)), 18);
+        let time = GnssTime::from_utc(before, &leaps).unwrap();
+        assert_eq!(leaps.at_gpst(time + TimeDelta::seconds(1)), 17);
+        assert_eq!(leaps.at_gpst(time + TimeDelta::seconds(2)), 18);
+        let fixed = LeapSeconds::default().with_override(20);
+        assert_eq!(time.to_utc(&fixed), before - TimeDelta::seconds(3));
+        let mut leaps = LeapSeconds::default();
+        leaps.insert(DateTime::from_timestamp(1_900_000_000, 0).unwrap(), 19);
+        assert_eq!(
+            leaps.at_utc(DateTime::from_timestamp(1_900_000_000, 0).unwrap()),
+            19
+        );
+    }
+}
@@ -0,0 +1,469 @@
+//! Tropospheric delay models and mapping functions
+
+use std::f64::consts::PI;
+
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{geo::Geodetic, GnssSatellite, UbxGpsInfo};
+
+/// Relative humidity of the standard atmosphere
+const STD_HUMIDITY: f64 = 0.7;
+/// Standard gravity (m/s²)
+const GRAVITY: f64 = 9.80665;
+/// Specific gas constant of dry air (J/kg/K)
+const R_DRY: f64 = 287.054;
+/// Day of year of the minimum of the seasonal variations (northern hemisphere)
+const DOY_MIN: f64 = 28.0;
+/// Length of the year (days)
+const YEAR: f64 = 365.25;
+/// Latitudes (degrees) of the Niell and UNB3m lookup tables
+const TABLE_LAT: [f64; 5] = [15.0, 30.0, 45.0, 60.0, 75.0];
+
+/// Niell hydrostatic mapping coefficients (a, b, c): average
+const NIELL_HYD_AVG: [[f64; 3]; 5] = [
+    [1.276_993_4e-3, 2.915_369_5e-3, 62.610_505e-3],
+    [1.268_323_0e-3, 2.915_229_9e-3, 62.837_393e-3],
+    [1.246_539_7e-3, 2.928_844_5e-3, 63.721_774e-3],
+    [1.219_604_9e-3, 2.902_256_5e-3, 63.824_265e-3],
+    [1.204_599_6e-3, 2.902_491_2e-3, 64.258_455e-3],
+];
+/// Niell hydrostatic mapping coefficients (a, b, c): seasonal amplitude
+const NIELL_HYD_AMP: [[f64; 3]; 5] = [
+    [0.0, 0.0, 0.0],
+    [1.270_962_6e-5, 2.141_497_9e-5, 9.012_840_0e-5],
+    [2.652_366_2e-5, 3.016_077_9e-5, 4.349_703_7e-5],
+    [3.400_045_2e-5, 7.256_272_2e-5, 84.795_348e-5],
+    [4.120_219_1e-5, 11.723_375e-5, 170.372_06e-5],
+];
+/// Niell hydrostatic height correction coefficients (a, b, c)
+const NIELL_HEIGHT: [f64; 3] = [2.53e-5, 5.49e-3, 1.14e-3];
+/// Niell wet mapping coefficients (a, b, c)
+const NIELL_WET: [[f64; 3]; 5] = [
+    [5.802_189_7e-4, 1.427_526_8e-3, 4.347_296_1e-2],
+    [5.679_484_7e-4, 1.513_862_5e-3, 4.672_951_0e-2],
+    [5.811_801_9e-4, 1.457_275_2e-3, 4.390_893_1e-2],
+    [5.972_754_2e-4, 1.500_742_8e-3, 4.462_698_2e-2],
+    [6.164_169_3e-4, 1.759_908_2e-3, 5.473_603_8e-2],
+];
+
+/// UNB3m meteorological parameters: pressure (hPa), temperature (K),
+/// relative humidity (%), temperature lapse rate (K/m) and water vapour
+/// lapse rate: average
+const UNB3M_AVG: [[f64; 5]; 5] = [
+    [1013.25, 299.65, 75.0, 6.30e-3, 2.77],
+    [1017.25, 294.15, 80.0, 6.05e-3, 3.15],
+    [1015.75, 283.15, 76.0, 5.58e-3, 2.57],
+    [1011.75, 272.15, 77.5, 5.39e-3, 1.81],
+    [1013.00, 263.65, 82.5, 4.53e-3, 1.55],
+];
+/// UNB3m meteorological parameters: seasonal amplitude
+const UNB3M_AMP: [[f64; 5]; 5] = [
+    [0.00, 0.00, 0.0, 0.00e-3, 0.00],
+    [-3.75, 7.00, 0.0, 0.25e-3, 0.33],
+    [-2.25, 11.00, -1.0, 0.32e-3, 0.46],
+    [-1.75, 15.00, -2.5, 0.81e-3, 0.74],
+    [-0.50, 14.50, 2.5, 0.62e-3, 0.30],
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+/// Zenith tropospheric delay (m)
+pub struct ZenithDelay {
+    /// Hydrostatic (dry) delay
+    pub hydrostatic: f64,
+    /// Wet delay
+    pub wet: f64,
+}
+
+impl ZenithDelay {
+    /// Get the total zenith delay (m)
+    pub fn total(&self) -> f64 {
+        self.hydrostatic + self.wet
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+/// Model of the zenith tropospheric delay
+pub enum ZenithModel {
+    /// Saastamoinen model with a standard atmosphere
+    #[default]
+    Saastamoinen,
+    /// UNB3m model, with seasonal meteorological parameters
+    Unb3m,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+/// Mapping function from the zenith to the slant tropospheric delay
+pub enum MappingFunction {
+    /// Cosecant of the elevation, for a flat troposphere
+    Cosecant,
+    /// Niell (1996) mapping functions
+    #[default]
+    Niell,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+/// Tropospheric delay model, combining a zenith delay model
+/// and a mapping function
+pub struct Troposphere {
+    /// Zenith delay model
+    pub zenith: ZenithModel,
+    /// Mapping function
+    pub mapping: MappingFunction,
+}
+
+impl Troposphere {
+    /// Create a new tropospheric delay model
+    pub fn new(zenith: ZenithModel, mapping: MappingFunction) -> Self {
+        Self { zenith, mapping }
+    }
+
+    /// Get the zenith delay.
+    ///
+    /// # Arguments
+    /// - `location`: Receiver location
+    /// - `doy`: Day of year, see [`day_of_year`]
+    pub fn zenith_delay(&self, location: &Geodetic, doy: f64) -> ZenithDelay {
+        match self.zenith {
+            ZenithModel::Saastamoinen => saastamoinen_zenith(location),
+            ZenithModel::Unb3m => unb3m(location, doy),
+        }
+    }
+
+    /// Get the hydrostatic and wet mapping functions.
+    ///
+    /// # Arguments
+    /// - `location`: Receiver location
+    /// - `doy`: Day of year, see [`day_of_year`]
+    /// - `elevation`: Satellite elevation (degrees)
+    pub fn mapping(&self, location: &Geodetic, doy: f64, elevation: f64) -> (f64, f64) {
+        match self.mapping {
+            MappingFunction::Cosecant => {
+                let m = 1.0 / elevation.to_radians().sin();
+                (m, m)
+            }
+            MappingFunction::Niell => niell(location, doy, elevation),
+        }
+    }
+
+    /// Get the slant tropospheric delay (m).
+    ///
+    /// # Arguments
+    /// - `location`: Receiver location
+    /// - `doy`: Day of year, see [`day_of_year`]
+    /// - `elevation`: Satellite elevation (degrees)
+    ///
+    /// # Returns
+    /// - The delay, or `0` for satellites below the horizon
+    pub fn slant_delay(&self, location: &Geodetic, doy: f64, elevation: f64) -> f64 {
+        if elevation <= 0.0 {
+            return 0.0;
+        }
+        let zenith = self.zenith_delay(location, doy);
+        let (mh, mw) = self.mapping(location, doy, elevation);
+        zenith.hydrostatic * mh + zenith.wet * mw
+    }
+}
+
+/// Get the fractional day of year of a time, starting at 1.0 at
+/// midnight of January 1st.
+pub fn day_of_year(time: DateTime<Utc>) -> f64 {
+    time.ordinal() as f64 + time.num_seconds_from_midnight() as f64 / 86400.0
+}
+
+/// Get the slant tropospheric delay (m) from the Saastamoinen model with a
+/// standard atmosphere.
+///
+/// # Arguments
+/// - `location`: Receiver location
+/// - `elevation`: Satellite elevation (degrees)
+///
+/// # Returns
+/// - The delay, or `0` for satellites below the horizon or receivers
+///   outside the troposphere model (below -100 m or above 10 km)
+pub fn saastamoinen(location: &Geodetic, elevation: f64) -> f64 {
+    if elevation <= 0.0 {
+        return 0.0;
+    }
+    let z = PI / 2.0 - elevation.to_radians();
+    saastamoinen_zenith(location).total() / z.cos()
+}
+
+/// Get the zenith tropospheric delay from the Saastamoinen model with a
+/// standard atmosphere.
+///
+/// # Returns
+/// - The delay, or `0` for receivers outside the troposphere model
+///   (below -100 m or above 10 km)
+pub fn saastamoinen_zenith(location: &Geodetic) -> ZenithDelay {
+    if !(-100.0..=1e4).contains(&location.height) {
+        return ZenithDelay::default();
+    }
+    let hgt = location.height.max(0.0);
+    // Standard atmosphere at the receiver height
+    let pres = 1013.25 * (1.0 - 2.2557e-5 * hgt).powf(5.2568);
+    let temp = 15.0 - 6.5e-3 * hgt + 273.16;
+    let e = 6.108 * STD_HUMIDITY * ((17.15 * temp - 4684.0) / (temp - 38.45)).exp();
+    ZenithDelay {
+        hydrostatic: 0.0022768 * pres
+            / (1.0 - 0.00266 * (2.0 * location.lat.to_radians()).cos() - 0.00028 * hgt / 1e3),
+        wet: 0.002277 * (1255.0 / temp + 0.05) * e,
+    }
+}
+
+/// Interpolate a latitude table, with seasonal variations, at the
+/// location and day of year.
+fn seasonal<const N: usize>(
+    avg: &[[f64; N]; 5],
+    amp: &[[f64; N]; 5],
+    lat: f64,
+    doy: f64,
+) -> [f64; N] {
+    let avg = interpolate(avg, lat);
+    let amp = interpolate(amp, lat);
+    // Seasons are reversed in the southern hemisphere
+    let doy = if lat < 0.0 { doy + YEAR / 2.0 } else { doy };
+    let phase = (2.0 * PI * (doy - DOY_MIN) / YEAR).cos();
+    let mut res = [0.0; N];
+    for ((r, avg), amp) in res.iter_mut().zip(avg).zip(amp) {
+        *r = avg - amp * phase;
+    }
+    res
+}
+
+/// Linearly interpolate a latitude table at `|lat|`, clamping to the
+/// first and last rows.
+fn interpolate<const N: usize>(table: &[[f64; N]; 5], lat: f64) -> [f64; N] {
+    let lat = lat.abs();
+    let idx = TABLE_LAT.iter().rposition(|l| *l <= lat);
+    match idx {
+        None => table[0],
+        Some(4) => table[4],
+        Some(i) => {
+            let w = (lat - TABLE_LAT[i]) / (TABLE_LAT[i + 1] - TABLE_LAT[i]);
+            let mut res = [0.0; N];
+            for ((r, a), b) in res.iter_mut().zip(table[i]).zip(table[i + 1]) {
+                *r = a + w * (b - a);
+            }
+            res
+        }
+    }
+}
+
+/// Continued fraction form of the Marini mapping function
+fn marini(elevation: f64, [a, b, c]: [f64; 3]) -> f64 {
+    let s = elevation.to_radians().sin();
+    (1.0 + a / (1.0 + b / (1.0 + c))) / (s + a / (s + b / (s + c)))
+}
+
+/// Get the Niell hydrostatic and wet mapping functions.
+///
+/// # Arguments
+/// - `location`: Receiver location
+/// - `doy`: Day of year, see [`day_of_year`]
+/// - `elevation`: Satellite elevation (degrees)
+pub fn niell(location: &Geodetic, doy: f64, elevation: f64) -> (f64, f64) {
+    let hyd = seasonal(&NIELL_HYD_AVG, &NIELL_HYD_AMP, location.lat, doy);
+    let wet = interpolate(&NIELL_WET, location.lat);
+    // Height correction of the hydrostatic mapping function
+    let dm = (1.0 / elevation.to_radians().sin() - marini(elevation, NIELL_HEIGHT))
+        * location.height
+        / 1e3;
+    (marini(elevation, hyd) + dm, marini(elevation, wet))
+}
+
+/// Get the zenith tropospheric delay from the UNB3m model (Leandro et
+/// al., 2006), using seasonal meteorological parameters.
+///
+/// # Arguments
+/// - `location`: Receiver location
+/// - `doy`: Day of year, see [`day_of_year`]
+pub fn unb3m(location: &Geodetic, doy: f64) -> ZenithDelay {
+    const K1: f64 = 77.604;
+    const K2: f64 = 16.6;
+    const K3: f64 = 377_600.0;
+    let [p0, t0, rh, beta, lambda] = seasonal(&UNB3M_AVG, &UNB3M_AMP, location.lat, doy);
+    // Water vapour pressure at sea level (hPa)
+    let es = 0.01
+        * (1.237_884_7e-5 * t0 * t0 - 1.912_131_6e-2 * t0 + 33.937_110_47 - 6.343_164_5e3 / t0)
+            .exp();
+    let fw = 1.00062 + 3.14e-6 * p0 + 5.6e-7 * (t0 - 273.15).powi(2);
+    let e0 = rh / 100.0 * es * fw;
+    // Meteorological parameters at the receiver height
+    let hgt = location.height;
+    let lambda1 = lambda + 1.0;
+    let t = t0 - beta * hgt;
+    let ratio = t / t0;
+    let p = p0 * ratio.powf(GRAVITY / (R_DRY * beta));
+    let e = e0 * ratio.powf(lambda1 * GRAVITY / (R_DRY * beta));
+    // Gravity at the centroid of the atmospheric column
+    let gm = 9.784 * (1.0 - 2.66e-3 * (2.0 * location.lat.to_radians()).cos() - 2.8e-7 * hgt);
+    let tm = t * (1.0 - beta * R_DRY / (gm * lambda1));
+    ZenithDelay {
+        hydrostatic: 1e-6 * K1 * R_DRY / gm * p,
+        wet: 1e-6 * (tm * K2 + K3) * R_DRY / (gm * lambda1 - beta * R_DRY) * e / t,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Modeled tropospheric delay along the line of sight to a satellite
+pub struct TropoData {
+    source: GnssSatellite,
+    pointing: (u16, i8),
+    mapping: (f64, f64),
+    slant_delay: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Modeled tropospheric delays for all satellites in view
+/// of a [`UbxGpsInfo`].
+pub struct TropoInfo {
+    timestamp: DateTime<Utc>,
+    location: Geodetic,
+    zenith: ZenithDelay,
+    tropo: Vec<TropoData>,
+}
+
+impl TropoInfo {
+    /// Compute the tropospheric delays of the satellites in a
+    /// [`UbxGpsInfo`] object.
+    ///
+    /// Uses the default [`Troposphere`] model.
+    pub fn assimilate(src: &UbxGpsInfo) -> Option<Self> {
+        Self::assimilate_with(src, &Troposphere::default())
+    }
+
+    /// Compute the tropospheric delays of the satellites in a
+    /// [`UbxGpsInfo`] object using the given [`Troposphere`] model.
+    ///
+    /// Satellites at or below the horizon, or with unknown elevation,
+    /// are ignored.
+    pub fn assimilate_with(src: &UbxGpsInfo, model: &Troposphere) -> Option<Self> {
+        let timestamp = src.timestamp();
+        let location = src.location();
+        let doy = day_of_year(timestamp);
+        let zenith = model.zenith_delay(&location, doy);
+        let mut tropo: Vec<_> = src
+            .carrier_phase()
+            .iter()
+            .filter(|(_, ch)| ch.elevation > 0)
+            .map(|(sat, ch)| {
+                let mapping = model.mapping(&location, doy, ch.elevation as f64);
+                TropoData {
+                    source: *sat,
+                    pointing: (ch.azimuth, ch.elevation),
+                    mapping,
+                    slant_delay: zenith.hydrostatic * mapping.0 + zenith.wet * mapping.1,
+                }
+            })
+            .collect();
+        if tropo.is_empty() {
+            None
+        } else {
+            tropo.sort_by_key(|a| a.source);
+            Some(TropoInfo {
+                timestamp,
+                location,
+                zenith,
+                tropo,
+            })
+        }
+    }
+
+    /// Get the timestamp of the tropospheric delays
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    /// Get the location of the receiver
+    pub fn location(&self) -> Geodetic {
+        self.location
+    }
+
+    /// Get the zenith tropospheric delay at the receiver
+    pub fn zenith(&self) -> ZenithDelay {
+        self.zenith
+    }
+
+    /// Get the tropospheric delay of each satellite
+    pub fn tropo(&self) -> &Vec<TropoData> {
+        &self.tropo
+    }
+}
+
+impl TropoData {
+    /// Get the satellite source of the tropospheric delay
+    pub fn source(&self) -> GnssSatellite {
+        self.source
+    }
+
+    /// Get the azimuth of the source satellite
+    pub fn azimuth(&self) -> u16 {
+        self.pointing.0
+    }
+
+    /// Get the elevation of the source satellite
+    pub fn elevation(&self) -> i8 {
+        self.pointing.1
+    }
+
+    /// Get the hydrostatic and wet mapping functions
+    pub fn mapping(&self) -> (f64, f64) {
+        self.mapping
+    }
+
+    /// Get the slant tropospheric delay (m)
+    pub fn slant_delay(&self) -> f64 {
+        self.slant_delay
+    }
+}
+
+mod test {
+    #[test]
+    fn test_tropo() {
+        use super::*;
+        let loc = Geodetic::new(45.0, 10.0, 0.0);
+        // Standard atmosphere at sea level: ~2.3 m hydrostatic
+        let zen = saastamoinen_zenith(&loc);
+        assert!((zen.hydrostatic - 2.3).abs() < 0.02, "{:?}", zen);
+        assert!(zen.wet > 0.05 && zen.wet < 0.3, "{:?}", zen);
+        assert!((saastamoinen(&loc, 90.0) - zen.total()).abs() < 1e-9);
+        assert!((saastamoinen(&loc, 30.0) - 2.0 * zen.total()).abs() < 1e-9);
+        assert_eq!(saastamoinen(&loc, -5.0), 0.0);
+
+        // UNB3m: hydrostatic delay close to the standard atmosphere,
+        // and decreasing with height
+        let winter = unb3m(&loc, 28.0);
+        let summer = unb3m(&loc, 28.0 + YEAR / 2.0);
+        assert!((winter.hydrostatic - 2.31).abs() < 0.03, "{:?}", winter);
+        assert!(summer.wet > winter.wet);
+        let high = unb3m(&Geodetic::new(45.0, 10.0, 2000.0), 28.0);
+        assert!(high.hydrostatic < 0.8 * winter.hydrostatic);
+        // Seasons are reversed in the southern hemisphere
+        let south = unb3m(&Geodetic::new(-45.0, 10.0, 0.0), 28.0 + YEAR / 2.0);
+        assert!((south.total() - winter.total()).abs() < 1e-9);
+        // No seasonal variation at the equator
+        let eq = Geodetic::new(10.0, 0.0, 0.0);
+        assert!((unb3m(&eq, 1.0).total() - unb3m(&eq, 180.0).total()).abs() < 1e-12);
+
+        // Niell mapping: unity at zenith, close to the cosecant at
+        // moderate elevations and smaller near the horizon
+        let (mh, mw) = niell(&loc, 100.0, 90.0);
+        assert!((mh - 1.0).abs() < 1e-6 && (mw - 1.0).abs() < 1e-6);
+        let (mh, mw) = niell(&loc, 100.0, 30.0);
+        assert!((mh - 2.0).abs() < 0.01 && (mw - 2.0).abs() < 0.01);
+        let (mh, mw) = niell(&loc, 100.0, 5.0);
+        let csc = 1.0 / 5f64.to_radians().sin();
+        assert!(mh < csc && mw < csc);
+        assert!((mh - 10.15).abs() < 0.1, "{}", mh);
+        assert!(mw > mh);
+
+        let model = Troposphere::new(ZenithModel::Unb3m, MappingFunction::Niell);
+        let doy = day_of_year(DateTime::from_timestamp(1_704_110_400, 0).unwrap());
+        assert_eq!(doy, 1.5);
+        let slant = model.slant_delay(&loc, doy, 10.0);
+        assert!(slant > 12.0 && slant < 15.0, "{}", slant);
+        let cosecant = Troposphere::new(ZenithModel::Saastamoinen, MappingFunction::Cosecant);
+        assert!((cosecant.slant_delay(&loc, doy, 30.0) - saastamoinen(&loc, 30.0)).abs() < 1e-9);
+    }
+}
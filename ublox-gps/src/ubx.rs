@@ -3,25 +3,36 @@
 //!
 //! This crate provides a parser for UBX messages from a serial port.
 
-use std::{
-    collections::{hash_map::Entry, HashMap},
-    time::Duration,
-};
+use std::collections::{hash_map::Entry, HashMap};
 
 use bitfield_struct::bitfield;
-use chrono::{DateTime, TimeDelta, Utc};
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
 use log::warn;
 use serde::{Deserialize, Serialize};
 
-use crate::{nmea::{GnssSatellite, NmeaGpsInfo}, NmeaMsgGroup};
+use crate::{
+    fix::{FixMode, FixQuality},
+    geo::Geodetic,
+    nav::{NavStore, UbxRxmSfrbx},
+    nmea::{
+        GnssSatellite, GpsError, NmeaGpsInfo, PositionAccuracy, PositionErrors, SignalView,
+        TimeSource,
+    },
+    spp::{Spp, VelocitySolution},
+    time::{GnssTime, LeapSeconds, TimeScale},
+    units::{Cycles, Meters},
+    NmeaMsgGroup,
+};
 
-const GPS_EPOCH: DateTime<Utc> = DateTime::from_timestamp_nanos(315_964_800_000_000_000);
+pub(crate) const GPS_EPOCH: DateTime<Utc> = DateTime::from_timestamp_nanos(315_964_800_000_000_000);
 
 #[non_exhaustive]
 #[repr(u8)]
 #[derive(Debug, Copy, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
 /// UBX message classes
 pub enum UbxClass {
+    /// Navigation results messages
+    Navigation(UbxNav) = 0x1,
     /// Receiver messages
     Receiver(UbxRxm) = 0x2,
     /// Acknowledgement messages
@@ -34,6 +45,15 @@ impl TryFrom<(u8, u8)> for UbxClass {
     fn try_from(value: (u8, u8)) -> Result<Self, Self::Error> {
         let (cls, id) = value;
         let res = match cls {
+            0x1 => UbxClass::Navigation({
+                match id {
+                    0x07 => UbxNav::Pvt,
+                    _ => {
+                        warn!("Invalid UBX NAV ID: {}", id);
+                        return Err("Invalid UBX NAV ID");
+                    }
+                }
+            }),
             0x2 => UbxClass::Receiver({
                 match id {
                     0x14 => UbxRxm::MeasX,
@@ -76,6 +96,15 @@ pub enum UbxAck {
     Nack = 0x0,
 }
 
+#[non_exhaustive]
+#[repr(u8)]
+#[derive(Debug, Copy, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+/// UBX NAV message types
+pub enum UbxNav {
+    /// Navigation position velocity time solution
+    Pvt = 0x07,
+}
+
 #[non_exhaustive]
 #[repr(u8)]
 #[derive(Debug, Copy, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
@@ -112,9 +141,28 @@ pub enum GnssFreq {
     Glonass(GlonassFreq),
     /// QZSS frequency channel
     Qzss(QzssFreq),
+    /// NavIC frequency channel
+    Navic(NavicFreq),
+    /// A signal that is not known to the parser, kept with its UBX
+    /// GNSS ID and signal ID
+    Unknown {
+        /// UBX GNSS ID
+        gnss_id: u8,
+        /// UBX signal ID
+        sig_id: u8,
+    },
+}
+
+impl GnssFreq {
+    /// Check if the frequency of the channel is known, i.e. the channel
+    /// is not [`GnssFreq::Unknown`]
+    pub fn is_known(&self) -> bool {
+        !matches!(self, GnssFreq::Unknown { .. })
+    }
 }
 
 impl Frequency for GnssFreq {
+    /// Get the carrier frequency, or `NaN` for an unknown signal
     fn get_freq(&self) -> f64 {
         match self {
             GnssFreq::Gps(freq) => freq.get_freq(),
@@ -122,6 +170,8 @@ impl Frequency for GnssFreq {
             GnssFreq::Beidou(freq) => freq.get_freq(),
             GnssFreq::Glonass(freq) => freq.get_freq(),
             GnssFreq::Qzss(freq) => freq.get_freq(),
+            GnssFreq::Navic(freq) => freq.get_freq(),
+            GnssFreq::Unknown { .. } => f64::NAN,
         }
     }
 }
@@ -133,15 +183,17 @@ fn parse_sat_ids(
     glonass: i8,
 ) -> Result<(GnssSatellite, GnssFreq), &'static str> {
     use GnssSatellite::*;
-    let sat = GnssSatellite::from_ubx(gnss_id, sat_id);
+    let sat = GnssSatellite::from_ubx(gnss_id, sat_id).ok_or("Invalid GNSS satellite ID")?;
     let freq = match sat {
-        Gps(_) => GpsFreq::try_from(sig_id)?.into(),
-        Sbas(_) => GpsFreq::try_from(sig_id)?.into(),
-        Galileo(_) => GalileoFreq::try_from(sig_id)?.into(),
-        Beidou(_) => BeidouFreq::try_from(sig_id)?.into(),
-        Qzss(_) => QzssFreq::try_from(sig_id)?.into(),
-        Glonass(_) => GlonassFreq::try_from((sig_id, glonass))?.into(),
+        Gps(_) | Sbas(_) => GpsFreq::try_from(sig_id).map(GnssFreq::from),
+        Galileo(_) => GalileoFreq::try_from(sig_id).map(GnssFreq::from),
+        Beidou(_) => BeidouFreq::try_from(sig_id).map(GnssFreq::from),
+        Qzss(_) => QzssFreq::try_from(sig_id).map(GnssFreq::from),
+        Glonass(_) => GlonassFreq::try_from((sig_id, glonass)).map(GnssFreq::from),
+        Navic(_) => NavicFreq::try_from(sig_id).map(GnssFreq::from),
     };
+    // Keep measurements of signals from newer firmware
+    let freq = freq.unwrap_or(GnssFreq::Unknown { gnss_id, sig_id });
     Ok((sat, freq))
 }
 
@@ -156,6 +208,8 @@ pub enum GpsFreq {
     L2CM,
     /// GPS L5 frequency
     L5,
+    /// GPS L1C frequency
+    L1C,
 }
 
 impl From<GpsFreq> for GnssFreq {
@@ -173,10 +227,9 @@ impl TryFrom<u8> for GpsFreq {
             3 => Ok(GpsFreq::L2CL),
             4 => Ok(GpsFreq::L2CM),
             6 | 7 => Ok(GpsFreq::L5),
-            _ => {
-                warn!("Invalid GPS frequency ID: {}", value);
-                Err("Invalid GPS frequency ID")
-            }
+            // Data and pilot components
+            1 | 2 => Ok(GpsFreq::L1C),
+            _ => Err("Invalid GPS frequency ID"),
         }
     }
 }
@@ -185,7 +238,7 @@ impl Frequency for GpsFreq {
     fn get_freq(&self) -> f64 {
         use GpsFreq::*;
         match self {
-            L1CA => 1575.42e6,
+            L1CA | L1C => 1575.42e6,
             L2CL | L2CM => 1227.60e6,
             L5 => 1176.45e6,
         }
@@ -207,6 +260,10 @@ pub enum GalileoFreq {
     E5bI,
     /// Galileo E5b-Q frequency
     E5bQ,
+    /// Galileo E6-B frequency
+    E6B,
+    /// Galileo E6-C frequency
+    E6C,
 }
 
 impl From<GalileoFreq> for GnssFreq {
@@ -226,10 +283,9 @@ impl TryFrom<u8> for GalileoFreq {
             4 => Ok(GalileoFreq::E5aQ),
             5 => Ok(GalileoFreq::E5bI),
             6 => Ok(GalileoFreq::E5bQ),
-            _ => {
-                warn!("Invalid Galileo frequency ID: {}", value);
-                Err("Invalid Galileo frequency ID")
-            }
+            8 => Ok(GalileoFreq::E6B),
+            9 => Ok(GalileoFreq::E6C),
+            _ => Err("Invalid Galileo frequency ID"),
         }
     }
 }
@@ -241,6 +297,7 @@ impl Frequency for GalileoFreq {
             E1C | E1B => 1575.42e6,
             E5aI | E5aQ => 1176.45e6,
             E5bI | E5bQ => 1207.14e6,
+            E6B | E6C => 1278.75e6,
         }
     }
 }
@@ -259,6 +316,14 @@ pub enum BeidouFreq {
     B2I_D2,
     /// Beidou B2A frequency
     B2A,
+    /// Beidou B1C frequency
+    B1C,
+    /// Beidou B3I D1 frequency
+    B3I_D1,
+    /// Beidou B3I D2 frequency
+    B3I_D2,
+    /// Beidou B2b frequency
+    B2b,
 }
 
 impl Frequency for BeidouFreq {
@@ -266,8 +331,10 @@ impl Frequency for BeidouFreq {
         use BeidouFreq::*;
         match self {
             B1I_D1 | B1I_D2 => 1561.098e6,
-            B2I_D1 | B2I_D2 => 1207.14e6,
+            B2I_D1 | B2I_D2 | B2b => 1207.14e6,
             B2A => 1176.45e6,
+            B1C => 1575.42e6,
+            B3I_D1 | B3I_D2 => 1268.52e6,
         }
     }
 }
@@ -287,11 +354,12 @@ impl TryFrom<u8> for BeidouFreq {
             1 => Ok(BeidouFreq::B1I_D2),
             2 => Ok(BeidouFreq::B2I_D1),
             3 => Ok(BeidouFreq::B2I_D2),
-            7 => Ok(BeidouFreq::B2A),
-            _ => {
-                warn!("Invalid Beidou frequency ID: {}", value);
-                Err("Invalid Beidou frequency ID")
-            }
+            4 => Ok(BeidouFreq::B3I_D1),
+            5 | 6 => Ok(BeidouFreq::B1C),
+            7 | 8 => Ok(BeidouFreq::B2A),
+            10 => Ok(BeidouFreq::B3I_D2),
+            11 => Ok(BeidouFreq::B2b),
+            _ => Err("Invalid Beidou frequency ID"),
         }
     }
 }
@@ -319,10 +387,7 @@ impl TryFrom<(u8, i8)> for GlonassFreq {
         match value {
             0 => Ok(GlonassFreq::L1OF(channel)),
             2 => Ok(GlonassFreq::L2OF(channel)),
-            _ => {
-                warn!("Invalid Glonass frequency ID: {}", value);
-                Err("Invalid Glonass frequency ID")
-            }
+            _ => Err("Invalid Glonass frequency ID"),
         }
     }
 }
@@ -350,6 +415,8 @@ pub enum QzssFreq {
     L2CL,
     /// QZSS L5 frequency
     L5,
+    /// QZSS L1C/B frequency
+    L1CB,
 }
 
 impl From<QzssFreq> for GnssFreq {
@@ -366,6 +433,7 @@ impl Frequency for QzssFreq {
             QzssFreq::L2CM => 1227.60e6,
             QzssFreq::L2CL => 1227.60e6,
             QzssFreq::L5 => 1176.45e6,
+            QzssFreq::L1CB => 1575.42e6,
         }
     }
 }
@@ -380,10 +448,40 @@ impl TryFrom<u8> for QzssFreq {
             4 => Ok(QzssFreq::L2CM),
             5 => Ok(QzssFreq::L2CL),
             7 | 8 => Ok(QzssFreq::L5),
-            _ => {
-                warn!("Invalid QZSS frequency ID: {}", value);
-                Err("Invalid QZSS frequency ID")
-            }
+            12 => Ok(QzssFreq::L1CB),
+            _ => Err("Invalid QZSS frequency ID"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, PartialEq, Eq, PartialOrd, Ord, Clone, Hash, Serialize, Deserialize)]
+/// NavIC frequency channels
+pub enum NavicFreq {
+    /// NavIC L5-A frequency
+    L5A,
+}
+
+impl From<NavicFreq> for GnssFreq {
+    fn from(val: NavicFreq) -> Self {
+        GnssFreq::Navic(val)
+    }
+}
+
+impl Frequency for NavicFreq {
+    fn get_freq(&self) -> f64 {
+        match self {
+            NavicFreq::L5A => 1176.45e6,
+        }
+    }
+}
+
+impl TryFrom<u8> for NavicFreq {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(NavicFreq::L5A),
+            _ => Err("Invalid NavIC frequency ID"),
         }
     }
 }
@@ -399,6 +497,15 @@ pub(crate) trait Frequency {
 pub struct UbxRxmRawx {
     /// Timestamp of the message
     pub timestamp: DateTime<Utc>,
+    /// Receiver time of week (s)
+    #[serde(default)]
+    pub rcv_tow: f64,
+    /// GPS week number
+    #[serde(default)]
+    pub week: u16,
+    /// GPS leap seconds (GPS - UTC, s)
+    #[serde(default)]
+    pub leap_second: i8,
     /// Receiver status
     pub receiver_status: RecvStat,
     /// Message version (0x1)
@@ -414,7 +521,7 @@ pub struct CarrierMeas {
     pub channel: GnssFreq,
     /// Pseudo-range measurement and standard deviation (m)
     pub pseudo_range: Option<(f64, f32)>,
-    /// Carrier-phase measurement and standard deviation (Hz)
+    /// Carrier-phase measurement and standard deviation (cycles)
     pub carrier_phase: Option<(f64, f32)>,
     /// Doppler measurement and standard deviation (Hz)
     ///
@@ -439,10 +546,10 @@ pub struct TrkStat {
     /// Carrier-phase measurement is valid
     cp_valid: bool,
     #[bits(1)]
-    /// Half-cycle ambiguity is fixed
+    /// Half-cycle ambiguity is resolved
     half_cycle: bool,
     #[bits(1)]
-    /// Sub-half-cycle ambiguity is fixed
+    /// Half cycle has been subtracted from the carrier phase
     sub_half_cycle: bool,
     #[bits(4)]
     _reserved: u8,
@@ -463,11 +570,89 @@ pub struct RecvStat {
     _reserved: u8,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// Carrier phase ambiguity status of a measurement
+pub enum PhaseAmbiguity {
+    /// Carrier phase is not valid
+    Invalid,
+    /// Carrier phase is valid, but may be offset by half a cycle
+    HalfCycle,
+    /// Carrier phase is valid, and the half-cycle ambiguity is resolved
+    Resolved,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+/// Treatment of carrier phase measurements with an unresolved
+/// half-cycle ambiguity
+pub enum HalfCyclePolicy {
+    /// Keep the carrier phase
+    #[default]
+    Keep,
+    /// Remove the carrier phase
+    Drop,
+}
+
+impl CarrierMeas {
+    /// Get the carrier phase ambiguity status
+    pub fn ambiguity(&self) -> PhaseAmbiguity {
+        if self.carrier_phase.is_none() || !self.trk_stat.cp_valid() {
+            PhaseAmbiguity::Invalid
+        } else if self.trk_stat.half_cycle() {
+            PhaseAmbiguity::Resolved
+        } else {
+            PhaseAmbiguity::HalfCycle
+        }
+    }
+
+    /// Check if half a cycle has been subtracted from the carrier phase
+    /// by the receiver to resolve the half-cycle ambiguity
+    pub fn half_cycle_subtracted(&self) -> bool {
+        self.trk_stat.sub_half_cycle()
+    }
+
+    /// Apply a half-cycle ambiguity policy to the carrier phase
+    pub fn apply_half_cycle(&mut self, policy: HalfCyclePolicy) {
+        if policy == HalfCyclePolicy::Drop && self.ambiguity() == PhaseAmbiguity::HalfCycle {
+            self.carrier_phase = None;
+        }
+    }
+
+    /// Get the pseudo-range measurement
+    pub fn range(&self) -> Option<Meters> {
+        self.pseudo_range
+            .map(|(pr, std)| Meters::new(pr, std as f64))
+    }
+
+    /// Get the carrier-phase measurement
+    pub fn phase(&self) -> Option<Cycles> {
+        self.carrier_phase
+            .map(|(cp, std)| Cycles::new(cp, std as f64))
+    }
+}
+
 impl UbxRxmRawx {
+    /// Get the receiver time of the measurements in GPS time.
+    ///
+    /// Returns `None` if the week number is not available.
+    pub fn gnss_time(&self) -> Option<GnssTime> {
+        if self.week == 0 {
+            return None;
+        }
+        GnssTime::from_week_tow(TimeScale::Gpst, self.week as u32, self.rcv_tow).ok()
+    }
+
     /// Remove carrier phase and pseudo-range measurements where only one frequency band is available
     pub fn remove_single_band(&mut self) {
         self.meas.retain(|_, v| v.len() > 1);
     }
+
+    /// Apply a half-cycle ambiguity policy to all carrier phase measurements
+    pub fn apply_half_cycle(&mut self, policy: HalfCyclePolicy) {
+        self.meas
+            .values_mut()
+            .flatten()
+            .for_each(|m| m.apply_half_cycle(policy));
+    }
 }
 
 impl UbxFormat for UbxRxmRawx {
@@ -507,13 +692,19 @@ impl UbxFormat for UbxRxmRawx {
                 .try_into()
                 .map_err(|_| "Failed to convert bytes to i8")?,
         );
-        let mut week =
-            TimeDelta::try_weeks(week as i64).ok_or("Failed to convert week to duration")?;
-        let dur = Duration::from_secs_f64(time_of_week) + Duration::from_secs(leap_second as u64);
-        week += TimeDelta::from_std(dur).map_err(|_| "Failed to convert duration to time delta")?;
+        let receiver_status: RecvStat = message.payload[12].into();
+        let time = GnssTime::from_week_tow(TimeScale::Gpst, week as u32, time_of_week)?;
+        let leaps = if receiver_status.leap_second_ready() {
+            LeapSeconds::default().with_override(leap_second as i32)
+        } else {
+            LeapSeconds::default()
+        };
         let mut msg = UbxRxmRawx {
-            timestamp: GPS_EPOCH + week,
-            receiver_status: message.payload[12].into(),
+            timestamp: time.to_utc(&leaps),
+            rcv_tow: time_of_week,
+            week,
+            leap_second,
+            receiver_status,
             version: message.payload[13],
             meas: Default::default(),
         };
@@ -526,7 +717,7 @@ impl UbxFormat for UbxRxmRawx {
             match parse_sat_ids(gnss_id, sat_id, sig_id, glonass) {
                 Ok((sat, freq)) => {
                     let trk_stat: TrkStat = message.payload[start + 30].into();
-                    let pr = if trk_stat.cp_valid() {
+                    let pr = if trk_stat.pr_valid() {
                         let pr = f64::from_le_bytes(
                             message.payload[start..start + 8]
                                 .try_into()
@@ -584,10 +775,13 @@ impl UbxFormat for UbxRxmRawx {
                         }
                     }
                     for (_, v) in msg.meas.iter_mut() {
+                        // Descending frequency, with unknown signals last
                         v.sort_by(|a, b| {
-                            a.channel
-                                .get_freq()
-                                .partial_cmp(&b.channel.get_freq())
+                            let freq = |m: &CarrierMeas| {
+                                Some(m.channel.get_freq()).filter(|f| !f.is_nan())
+                            };
+                            freq(a)
+                                .partial_cmp(&freq(b))
                                 .expect("Failed to compare frequencies?")
                         });
                         v.reverse();
@@ -683,6 +877,228 @@ fn rxm_checksum(buf: &[u8]) -> (u8, u8) {
     (ck_a, ck_b)
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// UBX NAV-PVT message
+pub struct UbxNavPvt {
+    /// GPS time of week of the navigation epoch (ms)
+    pub itow: u32,
+    /// UTC time of the solution, if the date and time are valid
+    pub timestamp: Option<DateTime<Utc>>,
+    /// GNSS fix type: `0` no fix, `1` dead reckoning only, `2` 2D fix,
+    /// `3` 3D fix, `4` GNSS + dead reckoning, `5` time only fix
+    pub fix_type: u8,
+    /// The fix is valid, i.e. within the DOP and accuracy masks
+    pub gnss_fix_ok: bool,
+    /// Differential corrections were applied
+    pub diff_soln: bool,
+    /// Carrier phase range solution: `0` none, `1` float ambiguities,
+    /// `2` fixed ambiguities
+    pub carr_soln: u8,
+    /// Number of satellites used in the solution
+    pub num_sv: u8,
+    /// Location of the solution
+    pub loc: Geodetic,
+    /// Height above mean sea level (m)
+    pub msl: f64,
+    /// Horizontal accuracy estimate (m)
+    pub h_acc: f64,
+    /// Vertical accuracy estimate (m)
+    pub v_acc: f64,
+    /// North, east and down velocity (m/s)
+    pub vel_ned: [f64; 3],
+    /// Ground speed (m/s)
+    pub ground_speed: f64,
+    /// Heading of motion (degrees)
+    pub heading: f64,
+    /// Speed accuracy estimate (m/s)
+    pub s_acc: f64,
+    /// Heading accuracy estimate (degrees)
+    pub head_acc: f64,
+    /// Position dilution of precision
+    pub pdop: f32,
+    /// Magnetic declination (degrees), if valid
+    pub mag_dec: Option<f32>,
+}
+
+impl UbxFormat for UbxNavPvt {
+    fn from_message(message: UbxMessage) -> Result<Self, &'static str>
+    where
+        Self: Sized,
+    {
+        if message.class != 0x1 {
+            return Err("Invalid UBX message class");
+        }
+        if message.id != 0x07 {
+            return Err("Invalid UBX message ID");
+        }
+        let p = &message.payload;
+        if p.len() != 92 {
+            return Err("Invalid UBX message length, malformed message");
+        }
+        let u2 = |i: usize| u16::from_le_bytes([p[i], p[i + 1]]);
+        let u4 = |i: usize| u32::from_le_bytes([p[i], p[i + 1], p[i + 2], p[i + 3]]);
+        let i4 = |i: usize| u4(i) as i32;
+        let valid = p[11];
+        let flags = p[21];
+        // Valid date and time
+        let timestamp = if valid & 0x3 == 0x3 {
+            NaiveDate::from_ymd_opt(u2(4) as i32, p[6] as u32, p[7] as u32)
+                // Leap seconds are represented as a second of 60
+                .and_then(|d| d.and_hms_opt(p[8] as u32, p[9] as u32, p[10].min(59) as u32))
+                .map(|t| {
+                    t.and_utc()
+                        + TimeDelta::seconds(p[10].saturating_sub(59) as i64)
+                        + TimeDelta::nanoseconds(i4(16) as i64)
+                })
+        } else {
+            None
+        };
+        Ok(UbxNavPvt {
+            itow: u4(0),
+            timestamp,
+            fix_type: p[20],
+            gnss_fix_ok: flags & 0x1 != 0,
+            diff_soln: flags & 0x2 != 0,
+            carr_soln: flags >> 6,
+            num_sv: p[23],
+            loc: Geodetic::new(
+                i4(28) as f64 * 1e-7,
+                i4(24) as f64 * 1e-7,
+                i4(32) as f64 * 1e-3,
+            ),
+            msl: i4(36) as f64 * 1e-3,
+            h_acc: u4(40) as f64 * 1e-3,
+            v_acc: u4(44) as f64 * 1e-3,
+            vel_ned: [
+                i4(48) as f64 * 1e-3,
+                i4(52) as f64 * 1e-3,
+                i4(56) as f64 * 1e-3,
+            ],
+            ground_speed: i4(60) as f64 * 1e-3,
+            heading: i4(64) as f64 * 1e-5,
+            s_acc: u4(68) as f64 * 1e-3,
+            head_acc: u4(72) as f64 * 1e-5,
+            pdop: u2(76) as f32 * 0.01,
+            mag_dec: (valid & 0x8 != 0).then(|| u2(88) as i16 as f32 * 1e-2),
+        })
+    }
+}
+
+impl UbxNavPvt {
+    /// Get the quality of the fix
+    pub fn quality(&self) -> FixQuality {
+        match (self.fix_type, self.gnss_fix_ok) {
+            (1, _) => FixQuality::DeadReckoning,
+            (2..=4, true) => match self.carr_soln {
+                2 => FixQuality::RtkFixed,
+                1 => FixQuality::RtkFloat,
+                _ if self.diff_soln => FixQuality::Differential,
+                _ => FixQuality::Autonomous,
+            },
+            _ => FixQuality::Invalid,
+        }
+    }
+
+    /// Get the type of the fix
+    pub fn fix_mode(&self) -> Option<FixMode> {
+        FixMode::from_nav_pvt(self.fix_type)
+    }
+}
+
+impl TryFrom<&UbxNavPvt> for NmeaGpsInfo {
+    type Error = GpsError;
+
+    /// Convert a NAV-PVT solution into GPS info, e.g. to encode it as
+    /// NMEA sentences with [`crate::NmeaEncoder`]
+    ///
+    /// # Errors
+    /// - [`GpsError::NoFix`] if the UTC time of the solution is not valid
+    fn try_from(pvt: &UbxNavPvt) -> Result<Self, Self::Error> {
+        let time = pvt.timestamp.ok_or(GpsError::NoFix)?;
+        let quality = pvt.quality();
+        let fix_mode = pvt.fix_mode().unwrap_or(FixMode::NoFix);
+        // Horizontal accuracy, shared between latitude and longitude
+        let h_acc = (pvt.h_acc / std::f64::consts::SQRT_2) as f32;
+        Ok(NmeaGpsInfo {
+            time,
+            loc: pvt.loc,
+            msl: pvt.msl as f32,
+            separation: Some((pvt.loc.height - pvt.msl) as f32),
+            true_heading: pvt.heading as f32,
+            ground_speed: (pvt.ground_speed * 3.6) as f32,
+            quality: quality.into(),
+            pdop: pvt.pdop,
+            date: Some(time.date_naive()),
+            course: Some(pvt.heading as f32),
+            mag_variation: pvt.mag_dec,
+            valid: Some(quality.is_valid()),
+            mode: Some(quality.mode()),
+            num_sats: Some(pvt.num_sv),
+            errors: Some(PositionErrors {
+                lat: Some(h_acc),
+                lon: Some(h_acc),
+                alt: Some(pvt.v_acc as f32),
+                ..Default::default()
+            }),
+            time_source: TimeSource::NavPvt,
+            accuracy: Some(PositionAccuracy {
+                horizontal: Some(pvt.h_acc as f32),
+                vertical: Some(pvt.v_acc as f32),
+                vertical_velocity: Some(pvt.vel_ned[2] as f32),
+                ..Default::default()
+            }),
+            fix_mode: Some(fix_mode.gsa()),
+            selection_mode: fix_mode.has_position().then_some('A'),
+            ..Default::default()
+        })
+    }
+}
+
+impl From<&UbxGpsInfo> for NmeaGpsInfo {
+    /// Convert GPS info into NMEA GPS info, e.g. to encode it as NMEA
+    /// sentences with [`crate::NmeaEncoder`]
+    ///
+    /// Satellites in view, their signals and the satellites used in the
+    /// fix are taken from the carrier phase measurements.
+    fn from(info: &UbxGpsInfo) -> Self {
+        let mut nmea = NmeaGpsInfo {
+            time: info.timestamp,
+            loc: info.loc,
+            msl: info.msl,
+            separation: info.separation,
+            true_heading: info.true_heading,
+            mag_heading: info.mag_heading,
+            ground_speed: info.ground_speed,
+            quality: info.quality,
+            hdop: info.hdop,
+            vdop: info.vdop,
+            pdop: info.pdop,
+            time_source: info.time_source,
+            fix_mode: info.fix_type.map(|x| x.gsa()),
+            ..Default::default()
+        };
+        for (sat, path) in &info.meas {
+            if path.elevation >= 0 {
+                nmea.sat_views.insert(*sat, (path.elevation, path.azimuth));
+            }
+            nmea.signals.insert(
+                *sat,
+                path.meas
+                    .iter()
+                    .map(|m| SignalView {
+                        channel: m.channel,
+                        snr: Some(m.carrier_snr),
+                    })
+                    .collect(),
+            );
+            if path.used_in_fix {
+                nmea.used_in_fix.insert(*sat);
+            }
+        }
+        nmea
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 /// U-Blox Satellite Carrier Phase Measurements
 pub struct SatPathInfo {
@@ -692,25 +1108,38 @@ pub struct SatPathInfo {
     pub azimuth: u16,
     /// Pseudo-range and carrier phase measurements
     pub meas: Vec<CarrierMeas>,
+    /// The satellite is used in the fix by the receiver (NMEA GSA)
+    #[serde(default)]
+    pub used_in_fix: bool,
 }
 
 /// U-Blox Combined GPS info and Carrier Phase
+///
+/// Also deserializes from the layout of earlier versions, as
+/// [`NmeaGpsInfo`] does.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub struct UbxGpsInfo {
     /// Timestamp of the message
     pub timestamp: DateTime<Utc>,
     /// Location of the fix
-    loc: (f64, f64, f32),
+    loc: Geodetic,
     /// Altitude above mean sea level
     msl: f32,
+    /// Geoid separation, if known
+    #[serde(default)]
+    separation: Option<f32>,
     /// True heading
     true_heading: f32,
     /// Magnetic heading
     mag_heading: f32,
     /// Ground speed
     ground_speed: f32,
-    /// Quality of the fix
+    /// Quality of the fix, as the raw GGA value
     quality: u8,
+    /// Type of the fix
+    #[serde(default)]
+    fix_type: Option<FixMode>,
     /// Horizontal dilution of precision
     hdop: f32,
     /// Vertical dilution of precision
@@ -721,8 +1150,92 @@ pub struct UbxGpsInfo {
     meas: HashMap<GnssSatellite, SatPathInfo>,
     /// Receiver status
     receiver_status: Option<RecvStat>,
+    /// Receiver time of the carrier phase measurements
+    #[serde(default)]
+    gnss_time: Option<GnssTime>,
+    /// Source of the timestamp
+    #[serde(default)]
+    time_source: TimeSource,
     /// Raw NMEA messages
     nmea_raw: NmeaMsgGroup,
+    /// Navigation data subframes
+    #[serde(default)]
+    nav: Vec<UbxRxmSfrbx>,
+}
+
+impl Serialize for UbxGpsInfo {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        UbxGpsInfo::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for UbxGpsInfo {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(match UbxGpsInfoRepr::deserialize(deserializer)? {
+            UbxGpsInfoRepr::Legacy(info) => info.into(),
+            UbxGpsInfoRepr::Current(info) => info,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum UbxGpsInfoRepr {
+    // Tried first, as the current layout also accepts a location array
+    Legacy(LegacyUbxGpsInfo),
+    Current(#[serde(with = "UbxGpsInfo")] UbxGpsInfo),
+}
+
+/// GPS information in the layout of earlier versions
+#[derive(Deserialize)]
+struct LegacyUbxGpsInfo {
+    timestamp: DateTime<Utc>,
+    /// Latitude, longitude and altitude above mean sea level
+    loc: (f64, f64, f64),
+    /// Geoid separation
+    msl: f32,
+    true_heading: f32,
+    mag_heading: f32,
+    ground_speed: f32,
+    quality: u8,
+    hdop: f32,
+    vdop: f32,
+    pdop: f32,
+    meas: HashMap<GnssSatellite, SatPathInfo>,
+    receiver_status: Option<RecvStat>,
+    nmea_raw: NmeaMsgGroup,
+}
+
+impl From<LegacyUbxGpsInfo> for UbxGpsInfo {
+    fn from(value: LegacyUbxGpsInfo) -> Self {
+        let (lat, lon, alt) = value.loc;
+        Self {
+            timestamp: value.timestamp,
+            loc: Geodetic::new(lat, lon, alt + value.msl as f64),
+            msl: alt as f32,
+            separation: Some(value.msl),
+            true_heading: value.true_heading,
+            mag_heading: value.mag_heading,
+            ground_speed: value.ground_speed,
+            quality: value.quality,
+            fix_type: None,
+            hdop: value.hdop,
+            vdop: value.vdop,
+            pdop: value.pdop,
+            meas: value.meas,
+            receiver_status: value.receiver_status,
+            gnss_time: None,
+            time_source: TimeSource::default(),
+            nmea_raw: value.nmea_raw,
+            nav: Vec::new(),
+        }
+    }
 }
 
 impl UbxGpsInfo {
@@ -730,7 +1243,9 @@ impl UbxGpsInfo {
     pub fn new(nmea: NmeaGpsInfo, rxm: Option<UbxRxmRawx>, nmea_raw: NmeaMsgGroup) -> Self {
         let mut meas = HashMap::new();
         let mut recv_stat = None;
+        let mut gnss_time = None;
         if let Some(rxm) = rxm {
+            gnss_time = rxm.gnss_time();
             for (sat, v) in rxm.meas {
                 let (el, az) = nmea.sat_views.get(&sat).unwrap_or(&(-1, 0));
                 meas.insert(
@@ -739,6 +1254,7 @@ impl UbxGpsInfo {
                         elevation: *el,
                         azimuth: *az,
                         meas: v,
+                        used_in_fix: nmea.used_in_fix.contains(&sat),
                     },
                 );
             }
@@ -748,28 +1264,47 @@ impl UbxGpsInfo {
             timestamp: nmea.time,
             loc: nmea.loc,
             msl: nmea.msl,
+            separation: nmea.separation,
             true_heading: nmea.true_heading,
             mag_heading: nmea.mag_heading,
             ground_speed: nmea.ground_speed,
             quality: nmea.quality,
+            fix_type: nmea.fix_type(),
             hdop: nmea.hdop,
             vdop: nmea.vdop,
             pdop: nmea.pdop,
             meas,
             receiver_status: recv_stat,
+            gnss_time,
+            time_source: nmea.time_source,
             nmea_raw,
+            nav: Vec::new(),
         }
     }
 
+    /// Attach navigation data subframes to the GPS info
+    pub fn with_navigation(mut self, nav: Vec<UbxRxmSfrbx>) -> Self {
+        self.nav = nav;
+        self
+    }
+
     /// Get the timestamp of the message
     pub fn timestamp(&self) -> DateTime<Utc> {
         self.timestamp
     }
 
+    /// Get the receiver time of the carrier phase measurements
+    pub fn gnss_time(&self) -> Option<GnssTime> {
+        self.gnss_time
+    }
+
+    /// Get the source of the timestamp
+    pub fn time_source(&self) -> TimeSource {
+        self.time_source
+    }
+
     /// Get the location of the fix
-    ///
-    /// Returns a tuple of (latitude in deg, longitude in deg, altitude in m)
-    pub fn location(&self) -> (f64, f64, f32) {
+    pub fn location(&self) -> Geodetic {
         self.loc
     }
 
@@ -778,6 +1313,12 @@ impl UbxGpsInfo {
         self.msl
     }
 
+    /// Get the geoid separation (m), or `None` if it is not known, in
+    /// which case the height of the location is not known either
+    pub fn separation(&self) -> Option<f32> {
+        self.separation
+    }
+
     /// Get the true heading (degrees)
     pub fn true_heading(&self) -> f32 {
         self.true_heading
@@ -793,11 +1334,31 @@ impl UbxGpsInfo {
         self.ground_speed
     }
 
+    /// Get the 3-D velocity and receiver clock drift from the Doppler
+    /// measurements, at the location of the fix
+    ///
+    /// # Errors
+    /// - If the receiver time is not available, or there are not enough
+    ///   satellites with ephemerides for a solution
+    pub fn velocity(&self, nav: &NavStore) -> Result<VelocitySolution, &'static str> {
+        Spp::new(Default::default()).velocity(self, nav, &self.loc)
+    }
+
     /// Get the quality of the fix
-    pub fn quality(&self) -> u8 {
+    pub fn quality(&self) -> FixQuality {
+        self.quality.into()
+    }
+
+    /// Get the quality of the fix, as the raw GGA value
+    pub fn raw_quality(&self) -> u8 {
         self.quality
     }
 
+    /// Get the type of the fix
+    pub fn fix_type(&self) -> Option<FixMode> {
+        self.fix_type
+    }
+
     /// Get the horizontal dilution of precision
     pub fn hdop(&self) -> f32 {
         self.hdop
@@ -823,11 +1384,24 @@ impl UbxGpsInfo {
         &self.meas
     }
 
+    /// Apply a half-cycle ambiguity policy to all carrier phase measurements
+    pub fn apply_half_cycle(&mut self, policy: HalfCyclePolicy) {
+        self.meas
+            .values_mut()
+            .flat_map(|x| x.meas.iter_mut())
+            .for_each(|m| m.apply_half_cycle(policy));
+    }
+
     /// Remove the carrier phase measurements
     pub fn remove_carrier_phase(&mut self) -> HashMap<GnssSatellite, SatPathInfo> {
         self.meas.drain().collect()
     }
 
+    /// Get the navigation data subframes
+    pub fn navigation(&self) -> &Vec<UbxRxmSfrbx> {
+        &self.nav
+    }
+
     /// Calculate the total electron content (TEC) from the carrier phase measurements
     pub fn calculate_tec(&self) {
         todo!()
@@ -1009,4 +1583,200 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_trk_stat() {
+        use super::*;
+        // GPS L1 C/A measurements of four satellites with different tracking states
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&345_600.0f64.to_le_bytes());
+        payload.extend_from_slice(&2300u16.to_le_bytes());
+        payload.extend_from_slice(&[18, 4, 0x1, 0x1, 0, 0]);
+        for (sv, trk_stat) in [(1u8, 0x1u8), (2, 0x2), (3, 0x3), (4, 0xF)] {
+            payload.extend_from_slice(&2.2e7f64.to_le_bytes());
+            payload.extend_from_slice(&1.1e8f64.to_le_bytes());
+            payload.extend_from_slice(&0f32.to_le_bytes());
+            payload.extend_from_slice(&[0, sv, 0, 0]);
+            payload.extend_from_slice(&1000u16.to_le_bytes());
+            payload.extend_from_slice(&[45, 2, 1, 1, trk_stat, 0]);
+        }
+        let mut rxm = UbxRxmRawx::from_message(UbxMessage {
+            class: 0x2,
+            id: 0x15,
+            payload,
+        })
+        .unwrap();
+        let meas = |rxm: &UbxRxmRawx, sv| rxm.meas[&GnssSatellite::Gps(sv)][0].clone();
+        // Pseudorange only
+        let m = meas(&rxm, 1);
+        assert!(m.pseudo_range.is_some());
+        assert_eq!(m.ambiguity(), PhaseAmbiguity::Invalid);
+        // Carrier phase only
+        let m = meas(&rxm, 2);
+        assert!(m.pseudo_range.is_none());
+        assert_eq!(m.ambiguity(), PhaseAmbiguity::HalfCycle);
+        // Both, with the half-cycle ambiguity unresolved
+        let m = meas(&rxm, 3);
+        assert!(m.pseudo_range.is_some());
+        assert_eq!(m.ambiguity(), PhaseAmbiguity::HalfCycle);
+        assert!(!m.half_cycle_subtracted());
+        // Both, with the half-cycle ambiguity resolved
+        let m = meas(&rxm, 4);
+        assert_eq!(m.ambiguity(), PhaseAmbiguity::Resolved);
+        assert!(m.half_cycle_subtracted());
+
+        rxm.apply_half_cycle(HalfCyclePolicy::Keep);
+        assert!(meas(&rxm, 3).carrier_phase.is_some());
+        rxm.apply_half_cycle(HalfCyclePolicy::Drop);
+        assert!(meas(&rxm, 2).carrier_phase.is_none());
+        assert!(meas(&rxm, 3).carrier_phase.is_none());
+        assert!(meas(&rxm, 3).pseudo_range.is_some());
+        assert!(meas(&rxm, 4).carrier_phase.is_some());
+    }
+
+    #[test]
+    fn test_rawx_time() {
+        use super::*;
+        let rawx = |leap: i8, recv_stat: u8| {
+            let mut payload = Vec::new();
+            payload.extend_from_slice(&345_600.25f64.to_le_bytes());
+            payload.extend_from_slice(&2300u16.to_le_bytes());
+            payload.extend_from_slice(&[leap as u8, 0, recv_stat, 0x1, 0, 0]);
+            UbxRxmRawx::from_message(UbxMessage {
+                class: 0x2,
+                id: 0x15,
+                payload,
+            })
+            .unwrap()
+        };
+        let utc = DateTime::from_timestamp(1_707_350_382, 250_000_000).unwrap();
+        // Leap seconds from the receiver
+        let rxm = rawx(18, 0x1);
+        assert_eq!(rxm.timestamp, utc);
+        assert_eq!(rxm.rcv_tow, 345_600.25);
+        assert_eq!(rxm.week, 2300);
+        let time = rxm.gnss_time().unwrap();
+        assert_eq!(time.week_tow(TimeScale::Gpst), Some((2300, 345_600.25)));
+        let rxm = rawx(17, 0x1);
+        assert_eq!(rxm.timestamp, utc + chrono::TimeDelta::seconds(1));
+        // Leap seconds not available from the receiver
+        let rxm = rawx(-128, 0x0);
+        assert_eq!(rxm.timestamp, utc);
+    }
+
+    #[test]
+    fn test_sat_ids() {
+        use super::*;
+        assert_eq!(
+            parse_sat_ids(0, 5, 0, 0).unwrap(),
+            (GnssSatellite::Gps(5), GpsFreq::L1CA.into())
+        );
+        assert_eq!(
+            parse_sat_ids(2, 11, 8, 0).unwrap().1,
+            GalileoFreq::E6B.into()
+        );
+        assert_eq!(
+            parse_sat_ids(3, 30, 5, 0).unwrap().1,
+            BeidouFreq::B1C.into()
+        );
+        assert_eq!(
+            parse_sat_ids(3, 30, 10, 0).unwrap().1,
+            BeidouFreq::B3I_D2.into()
+        );
+        let (_, l1c) = parse_sat_ids(0, 5, 2, 0).unwrap();
+        assert_eq!(l1c, GpsFreq::L1C.into());
+        assert_eq!(l1c.get_freq(), 1575.42e6);
+        let (_, b2b) = parse_sat_ids(3, 30, 11, 0).unwrap();
+        assert_eq!(b2b, BeidouFreq::B2b.into());
+        assert_eq!(b2b.get_freq(), 1207.14e6);
+        assert_eq!(
+            parse_sat_ids(7, 3, 0, 0).unwrap(),
+            (GnssSatellite::Navic(3), NavicFreq::L5A.into())
+        );
+        // Signals of newer firmware are kept
+        let (sat, freq) = parse_sat_ids(0, 5, 14, 0).unwrap();
+        assert_eq!(sat, GnssSatellite::Gps(5));
+        assert_eq!(
+            freq,
+            GnssFreq::Unknown {
+                gnss_id: 0,
+                sig_id: 14
+            }
+        );
+        assert!(!freq.is_known() && freq.get_freq().is_nan());
+        // Unknown systems are rejected
+        assert!(parse_sat_ids(4, 1, 0, 0).is_err());
+    }
+
+    #[test]
+    fn test_nav_pvt() {
+        use super::*;
+        let mut payload = vec![0u8; 92];
+        let mut put = |i: usize, x: &[u8]| payload[i..i + x.len()].copy_from_slice(x);
+        put(4, &2024u16.to_le_bytes());
+        put(6, &[10, 3, 22, 15, 15, 0x7]);
+        put(16, &250_000_000i32.to_le_bytes());
+        // 3D fix, gnssFixOK, diffSoln, float RTK
+        put(20, &[3, 0x43, 0, 14]);
+        put(24, &(-711_496_572i32).to_le_bytes());
+        put(28, &426_493_903i32.to_le_bytes());
+        put(32, &3_700i32.to_le_bytes());
+        put(36, &36_700i32.to_le_bytes());
+        put(40, &1_414u32.to_le_bytes());
+        put(44, &2_000u32.to_le_bytes());
+        put(60, &1_000i32.to_le_bytes());
+        put(64, &9_000_000i32.to_le_bytes());
+        put(76, &183u16.to_le_bytes());
+        let pvt = UbxNavPvt::from_message(UbxMessage {
+            class: 0x1,
+            id: 0x07,
+            payload: payload.clone(),
+        })
+        .unwrap();
+        assert_eq!(
+            pvt.timestamp.unwrap().to_rfc3339(),
+            "2024-10-03T22:15:15.250+00:00"
+        );
+        assert_eq!((pvt.num_sv, pvt.quality()), (14, FixQuality::RtkFloat));
+        assert_eq!(pvt.fix_mode(), Some(FixMode::Fix3D));
+        assert!((pvt.loc.lat - 42.649_390_3).abs() < 1e-9);
+        assert!(pvt.mag_dec.is_none());
+
+        let info = NmeaGpsInfo::try_from(&pvt).unwrap();
+        assert_eq!(info.quality, 5);
+        assert_eq!(info.fix_type(), Some(FixMode::Fix3D));
+        assert_eq!(info.mode, Some('F'));
+        assert!((info.ground_speed - 3.6).abs() < 1e-5);
+        assert!((info.true_heading - 90.0).abs() < 1e-5);
+        assert_eq!(info.errors.and_then(|x| x.alt), Some(2.0));
+        assert_eq!(info.accuracy.and_then(|x| x.horizontal), Some(1.414));
+
+        // The time is required to convert the solution
+        payload[11] = 0;
+        let pvt = UbxNavPvt::from_message(UbxMessage {
+            class: 0x1,
+            id: 0x07,
+            payload,
+        })
+        .unwrap();
+        assert!(pvt.timestamp.is_none());
+        assert!(NmeaGpsInfo::try_from(&pvt).is_err());
+    }
+
+    #[test]
+    fn test_legacy_info() {
+        use super::*;
+        // Record of earlier versions, with the GGA altitude in the location
+        // and the geoid separation as `msl`
+        let legacy = r#"{"timestamp":"2024-10-03T22:15:15Z","loc":[42.5,-71.25,36.7],
+            "msl":-33.0,"true_heading":0.0,"mag_heading":0.0,"ground_speed":0.0,
+            "quality":1,"hdop":1.0,"vdop":1.5,"pdop":1.8,"meas":{},
+            "receiver_status":null,"nmea_raw":{}}"#;
+        let info: UbxGpsInfo = serde_json::from_str(legacy).unwrap();
+        assert_eq!(info.location(), Geodetic::new(42.5, -71.25, 36.7 - 33.0));
+        assert_eq!((info.msl(), info.separation()), (36.7, Some(-33.0)));
+        let back: UbxGpsInfo =
+            serde_json::from_str(&serde_json::to_string(&info).unwrap()).unwrap();
+        assert_eq!((back.location(), back.msl()), (info.location(), info.msl()));
+    }
 }
@@ -1,9 +1,17 @@
 use num_traits::{Inv, Num, NumAssignOps, NumAssignRef, NumCast, NumOps, ToPrimitive};
 use serde::{Deserialize, Serialize};
-use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::{
+    fmt::Display,
+    ops::{Add, Div, Mul, Neg, Sub},
+    sync::atomic::{AtomicU64, Ordering},
+};
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-/// A type representing a value with an associated uncertainty.
+/// A type representing a value with an associated uncertainty
+/// (one standard deviation).
+///
+/// Arithmetic assumes that the operands are independent. Use
+/// [`Correlated`] when operands share sources of error.
 pub struct Uncertain<T>(pub T, pub T);
 
 impl<T: Num + NumCast + ToPrimitive + NumOps + NumAssignOps + NumAssignRef + Neg + Copy>
@@ -25,6 +33,53 @@ impl<T: Num + NumCast + ToPrimitive + NumOps + NumAssignOps + NumAssignRef + Neg
     }
 }
 
+impl<T: NumCast + Copy> Uncertain<T> {
+    fn as_f64(&self) -> (f64, f64) {
+        (
+            NumCast::from(self.0).unwrap_or(f64::NAN),
+            NumCast::from(self.1).unwrap_or(f64::NAN),
+        )
+    }
+
+    /// Get the relative uncertainty of the value.
+    pub fn relative_error(&self) -> f64 {
+        let (v, u) = self.as_f64();
+        (u / v).abs()
+    }
+
+    /// Get the difference between two values in units of their combined
+    /// uncertainty.
+    pub fn z_score(&self, other: &Self) -> f64 {
+        let (v1, u1) = self.as_f64();
+        let (v2, u2) = other.as_f64();
+        (v1 - v2) / u1.hypot(u2)
+    }
+
+    /// Check if two values agree within `k` times their combined uncertainty.
+    pub fn is_consistent(&self, other: &Self, k: f64) -> bool {
+        self.z_score(other).abs() <= k
+    }
+
+    /// Check if the value differs from zero by more than `k` times
+    /// its uncertainty.
+    pub fn is_significant(&self, k: f64) -> bool {
+        let (v, u) = self.as_f64();
+        v.abs() > k * u.abs()
+    }
+}
+
+impl Uncertain<f64> {
+    /// Get the inverse-variance weighted mean of a set of values.
+    ///
+    /// See [`crate::stats::weighted_mean`].
+    pub fn weighted_mean<'a, I: IntoIterator<Item = &'a Uncertain<f64>>>(
+        values: I,
+    ) -> Option<Uncertain<f64>> {
+        let values: Vec<_> = values.into_iter().copied().collect();
+        crate::stats::weighted_mean(&values)
+    }
+}
+
 impl<T: Num> From<T> for Uncertain<T> {
     fn from(value: T) -> Self {
         Uncertain(value, T::zero())
@@ -44,6 +99,33 @@ impl<T: NumCast + Copy> Uncertain<T> {
     }
 }
 
+impl<T: NumCast + Copy> Display for Uncertain<T> {
+    /// Format the value and its uncertainty, rounded to the significant
+    /// figures of the uncertainty. The precision of the formatter sets the
+    /// number of significant figures of the uncertainty (default: 2).
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (v, u) = self.as_f64();
+        let u = u.abs();
+        if u == 0.0 || !u.is_finite() || !v.is_finite() {
+            return write!(f, "{v} ± {u}");
+        }
+        let sig = f.precision().unwrap_or(2).max(1) as i32;
+        let decimals = sig - 1 - u.log10().floor() as i32;
+        if decimals >= 0 {
+            let d = decimals as usize;
+            write!(f, "{v:.d$} ± {u:.d$}")
+        } else {
+            let scale = 10f64.powi(-decimals);
+            write!(
+                f,
+                "{:.0} ± {:.0}",
+                (v / scale).round() * scale,
+                (u / scale).round() * scale
+            )
+        }
+    }
+}
+
 impl<T: Num + ToPrimitive + NumOps + NumCast> Add for Uncertain<T> {
     type Output = Uncertain<T>;
 
@@ -75,11 +157,9 @@ impl<T: Num + ToPrimitive + NumOps + NumCast + Copy> Mul for Uncertain<T> {
     type Output = Uncertain<T>;
 
     fn mul(self, other: Uncertain<T>) -> Uncertain<T> {
-        let v1: f64 = NumCast::from(self.0).unwrap();
-        let v2: f64 = NumCast::from(other.0).unwrap();
-        let u1: f64 = NumCast::from(self.1).unwrap();
-        let u2: f64 = NumCast::from(other.1).unwrap();
-        let err = ((u1 / v1) * (u1 / v1) + (u2 / v2) * (u2 / v2)).sqrt();
+        let (v1, u1) = self.as_f64();
+        let (v2, u2) = other.as_f64();
+        let err = (v2 * u1).hypot(v1 * u2);
         Uncertain(self.0 * other.0, NumCast::from(err).unwrap())
     }
 }
@@ -99,11 +179,433 @@ impl<T: Num + ToPrimitive + NumOps + NumCast + Copy> Div for Uncertain<T> {
     type Output = Uncertain<T>;
 
     fn div(self, other: Uncertain<T>) -> Uncertain<T> {
-        let v1: f64 = NumCast::from(self.0).unwrap();
-        let v2: f64 = NumCast::from(other.0).unwrap();
-        let u1: f64 = NumCast::from(self.1).unwrap();
-        let u2: f64 = NumCast::from(other.1).unwrap();
-        let err = ((u1 / v1) * (u1 / v1) + (u2 / v2) * (u2 / v2)).sqrt();
+        let (v1, u1) = self.as_f64();
+        let (v2, u2) = other.as_f64();
+        let err = (u1 / v2).hypot(v1 * u2 / (v2 * v2));
         Uncertain(self.0 / other.0, NumCast::from(err).unwrap())
     }
 }
+
+/// Source of unique identifiers for independent error sources
+static NEXT_SOURCE: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, PartialEq)]
+/// A value with linear error propagation that tracks correlations.
+///
+/// Each value holds its partial derivatives with respect to a set of
+/// independent, unit-variance error sources. Values derived from common
+/// inputs share sources, so expressions such as `a * b - a * c` or
+/// `x / x` propagate uncertainty correctly.
+pub struct Correlated {
+    value: f64,
+    /// Scaled partial derivatives, sorted by source identifier
+    terms: Vec<(u64, f64)>,
+}
+
+impl Correlated {
+    /// Create a value with an independent uncertainty (one standard deviation).
+    pub fn new(value: f64, uncertainty: f64) -> Self {
+        if uncertainty == 0.0 {
+            return Self::constant(value);
+        }
+        let id = NEXT_SOURCE.fetch_add(1, Ordering::Relaxed);
+        Self {
+            value,
+            terms: vec![(id, uncertainty.abs())],
+        }
+    }
+
+    /// Create an exact value
+    pub fn constant(value: f64) -> Self {
+        Self {
+            value,
+            terms: Vec::new(),
+        }
+    }
+
+    /// Create a set of correlated values from their means and
+    /// covariance matrix.
+    ///
+    /// # Returns
+    /// - `None` if the dimensions mismatch, or the covariance matrix
+    ///   is not symmetric positive semi-definite.
+    pub fn from_covariance(values: &[f64], covariance: &[Vec<f64>]) -> Option<Vec<Self>> {
+        let n = values.len();
+        if covariance.len() != n || covariance.iter().any(|row| row.len() != n) {
+            return None;
+        }
+        // Cholesky decomposition, C = L L^T
+        let mut l = vec![vec![0.0; n]; n];
+        for i in 0..n {
+            for j in 0..=i {
+                if (covariance[i][j] - covariance[j][i]).abs()
+                    > 1e-12 * covariance[i][j].abs().max(1.0)
+                {
+                    return None;
+                }
+                let sum = covariance[i][j] - (0..j).map(|k| l[i][k] * l[j][k]).sum::<f64>();
+                if i == j {
+                    if sum < -1e-12 * covariance[i][i].abs().max(1.0) {
+                        return None;
+                    }
+                    l[i][i] = sum.max(0.0).sqrt();
+                } else if l[j][j] > 0.0 {
+                    l[i][j] = sum / l[j][j];
+                }
+            }
+        }
+        let base = NEXT_SOURCE.fetch_add(n as u64, Ordering::Relaxed);
+        Some(
+            values
+                .iter()
+                .zip(l)
+                .map(|(value, row)| Self {
+                    value: *value,
+                    terms: row
+                        .into_iter()
+                        .enumerate()
+                        .filter(|(_, d)| *d != 0.0)
+                        .map(|(j, d)| (base + j as u64, d))
+                        .collect(),
+                })
+                .collect(),
+        )
+    }
+
+    /// Get the value
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// Get the variance
+    pub fn variance(&self) -> f64 {
+        self.terms.iter().map(|(_, d)| d * d).sum()
+    }
+
+    /// Get the uncertainty (one standard deviation)
+    pub fn error(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    /// Get the covariance with another value
+    pub fn covariance(&self, other: &Self) -> f64 {
+        let (mut i, mut j, mut cov) = (0, 0, 0.0);
+        while i < self.terms.len() && j < other.terms.len() {
+            let (a, b) = (self.terms[i], other.terms[j]);
+            match a.0.cmp(&b.0) {
+                std::cmp::Ordering::Less => i += 1,
+                std::cmp::Ordering::Greater => j += 1,
+                std::cmp::Ordering::Equal => {
+                    cov += a.1 * b.1;
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        cov
+    }
+
+    /// Get the correlation coefficient with another value
+    pub fn correlation(&self, other: &Self) -> f64 {
+        self.covariance(other) / (self.error() * other.error())
+    }
+
+    /// Apply a differentiable function.
+    ///
+    /// # Arguments
+    /// - `f`: The function
+    /// - `df`: The derivative of the function
+    pub fn map<F: Fn(f64) -> f64, D: Fn(f64) -> f64>(&self, f: F, df: D) -> Self {
+        let d = df(self.value);
+        Self {
+            value: f(self.value),
+            terms: self.terms.iter().map(|(id, x)| (*id, x * d)).collect(),
+        }
+    }
+
+    /// Raise to an integer power
+    pub fn powi(&self, n: i32) -> Self {
+        self.map(|x| x.powi(n), |x| n as f64 * x.powi(n - 1))
+    }
+
+    /// Take the square root
+    pub fn sqrt(&self) -> Self {
+        self.map(f64::sqrt, |x| 0.5 / x.sqrt())
+    }
+
+    /// Linear combination `a * self + b * other`
+    fn combine(&self, a: f64, other: &Self, b: f64, value: f64) -> Self {
+        let mut terms = Vec::with_capacity(self.terms.len() + other.terms.len());
+        let (mut i, mut j) = (0, 0);
+        while i < self.terms.len() || j < other.terms.len() {
+            let x = self.terms.get(i);
+            let y = other.terms.get(j);
+            let (id, d) = match (x, y) {
+                (Some(x), Some(y)) if x.0 == y.0 => {
+                    i += 1;
+                    j += 1;
+                    (x.0, a * x.1 + b * y.1)
+                }
+                (Some(x), Some(y)) if x.0 < y.0 => {
+                    i += 1;
+                    (x.0, a * x.1)
+                }
+                (Some(x), None) => {
+                    i += 1;
+                    (x.0, a * x.1)
+                }
+                (_, Some(y)) => {
+                    j += 1;
+                    (y.0, b * y.1)
+                }
+                (None, None) => unreachable!(),
+            };
+            if d != 0.0 {
+                terms.push((id, d));
+            }
+        }
+        Self { value, terms }
+    }
+}
+
+impl From<Uncertain<f64>> for Correlated {
+    fn from(value: Uncertain<f64>) -> Self {
+        Self::new(value.0, value.1)
+    }
+}
+
+impl From<f64> for Correlated {
+    fn from(value: f64) -> Self {
+        Self::constant(value)
+    }
+}
+
+impl From<&Correlated> for Uncertain<f64> {
+    fn from(value: &Correlated) -> Self {
+        Uncertain(value.value, value.error())
+    }
+}
+
+impl From<Correlated> for Uncertain<f64> {
+    fn from(value: Correlated) -> Self {
+        (&value).into()
+    }
+}
+
+impl Display for Correlated {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&Uncertain::from(self), f)
+    }
+}
+
+impl Neg for &Correlated {
+    type Output = Correlated;
+
+    fn neg(self) -> Correlated {
+        self.map(|x| -x, |_| -1.0)
+    }
+}
+
+impl Neg for Correlated {
+    type Output = Correlated;
+
+    fn neg(self) -> Correlated {
+        -&self
+    }
+}
+
+macro_rules! correlated_op {
+    ($trait:ident, $method:ident, |$a:ident, $b:ident| $body:expr) => {
+        impl $trait<&Correlated> for &Correlated {
+            type Output = Correlated;
+
+            fn $method(self, other: &Correlated) -> Correlated {
+                let ($a, $b) = (self, other);
+                $body
+            }
+        }
+
+        impl $trait<Correlated> for Correlated {
+            type Output = Correlated;
+
+            fn $method(self, other: Correlated) -> Correlated {
+                (&self).$method(&other)
+            }
+        }
+
+        impl $trait<&Correlated> for Correlated {
+            type Output = Correlated;
+
+            fn $method(self, other: &Correlated) -> Correlated {
+                (&self).$method(other)
+            }
+        }
+
+        impl $trait<Correlated> for &Correlated {
+            type Output = Correlated;
+
+            fn $method(self, other: Correlated) -> Correlated {
+                self.$method(&other)
+            }
+        }
+
+        impl $trait<f64> for Correlated {
+            type Output = Correlated;
+
+            fn $method(self, other: f64) -> Correlated {
+                (&self).$method(&Correlated::constant(other))
+            }
+        }
+
+        impl $trait<f64> for &Correlated {
+            type Output = Correlated;
+
+            fn $method(self, other: f64) -> Correlated {
+                self.$method(&Correlated::constant(other))
+            }
+        }
+    };
+}
+
+correlated_op!(Add, add, |a, b| a.combine(1.0, b, 1.0, a.value + b.value));
+correlated_op!(Sub, sub, |a, b| a.combine(1.0, b, -1.0, a.value - b.value));
+correlated_op!(Mul, mul, |a, b| a.combine(
+    b.value,
+    b,
+    a.value,
+    a.value * b.value
+));
+correlated_op!(Div, div, |a, b| a.combine(
+    1.0 / b.value,
+    b,
+    -a.value / (b.value * b.value),
+    a.value / b.value
+));
+
+mod test {
+    #[test]
+    fn test_add_sub() {
+        use super::*;
+        let a = Uncertain::new(10.0, 3.0);
+        let b = Uncertain::new(4.0, 4.0);
+        let c = a + b;
+        assert_eq!((c.value(), c.error()), (14.0, 5.0));
+        let c = a - b;
+        assert_eq!((c.value(), c.error()), (6.0, 5.0));
+        let c = -a;
+        assert_eq!((c.value(), c.error()), (-10.0, 3.0));
+    }
+
+    #[test]
+    fn test_mul_div() {
+        use super::*;
+        let a = Uncertain::new(10.0f64, 0.3);
+        let b = Uncertain::new(2.0, 0.08);
+        // Relative errors of 3% and 4% combine to 5%
+        let c = a * b;
+        assert_eq!(c.value(), 20.0);
+        assert!((c.error() - 1.0).abs() < 1e-12);
+        let c = a / b;
+        assert_eq!(c.value(), 5.0);
+        assert!((c.error() - 0.25).abs() < 1e-12);
+        // Exact scaling, and zero-valued operands
+        let c = a * Uncertain::from(-2.0);
+        assert_eq!((c.value(), c.error()), (-20.0, 0.6));
+        let c = Uncertain::new(0.0, 1.0) * Uncertain::new(3.0, 0.0);
+        assert_eq!((c.value(), c.error()), (0.0, 3.0));
+        let c = a.inv();
+        assert_eq!(c.value(), 0.1);
+        assert!((c.error() - 0.003).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_display() {
+        use super::*;
+        assert_eq!(
+            Uncertain::new(12.3456, 0.0123).to_string(),
+            "12.346 ± 0.012"
+        );
+        assert_eq!(
+            format!("{:.1}", Uncertain::new(12.3456, 0.0123)),
+            "12.35 ± 0.01"
+        );
+        assert_eq!(Uncertain::new(12345.6, 234.5).to_string(), "12350 ± 230");
+        assert_eq!(Uncertain::new(1.5, 0.0).to_string(), "1.5 ± 0");
+        assert_eq!(Uncertain::new(-0.5f32, 0.25).to_string(), "-0.50 ± 0.25");
+    }
+
+    #[test]
+    fn test_helpers() {
+        use super::*;
+        let a = Uncertain::new(10.0, 3.0);
+        let b = Uncertain::new(2.0, 4.0);
+        assert!((a.z_score(&b) - 1.6).abs() < 1e-12);
+        assert!(a.is_consistent(&b, 2.0));
+        assert!(!a.is_consistent(&b, 1.0));
+        assert!(a.is_significant(3.0));
+        assert!(!b.is_significant(1.0));
+        assert!((a.relative_error() - 0.3).abs() < 1e-12);
+        let mean = Uncertain::weighted_mean(&[
+            Uncertain::new(1.0, 1.0),
+            Uncertain::new(2.0, 1.0),
+            Uncertain::new(4.0, 0.0),
+        ])
+        .unwrap();
+        assert_eq!(mean.value(), 1.5);
+        assert!((mean.error() - 0.5f64.sqrt()).abs() < 1e-12);
+        assert!(Uncertain::weighted_mean(&[]).is_none());
+        let mean = Uncertain::weighted_mean(&[Uncertain::new(1.0, 0.0), Uncertain::new(3.0, 0.0)])
+            .unwrap();
+        assert_eq!(mean.value(), 2.0);
+        assert_eq!(mean.error(), 1.0);
+    }
+
+    #[test]
+    fn test_correlated() {
+        use super::*;
+        let a = Correlated::new(10.0, 0.3);
+        let b = Correlated::new(2.0, 0.08);
+        // Independent operands agree with Uncertain
+        let c = &a * &b;
+        assert_eq!(c.value(), 20.0);
+        assert!((c.error() - 1.0).abs() < 1e-12);
+        let c = &a / &b;
+        assert!((c.error() - 0.25).abs() < 1e-12);
+        let c = &a + &b;
+        assert!((c.error() - 0.3f64.hypot(0.08)).abs() < 1e-12);
+        // Reused operands are fully correlated
+        assert_eq!((&a - &a).error(), 0.0);
+        assert_eq!((&a / &a).error(), 0.0);
+        assert!(((&a + &a).error() - 0.6).abs() < 1e-12);
+        let k = Correlated::new(3.0, 0.1);
+        let d = &k * &a - &k * &b;
+        assert_eq!(d.value(), 24.0);
+        let expected = (3.0f64 * 0.3).hypot(3.0 * 0.08).hypot(8.0 * 0.1);
+        assert!((d.error() - expected).abs() < 1e-12);
+        // Scalar operations and functions
+        let c = &a * 2.0 + 1.0;
+        assert_eq!((c.value(), c.error()), (21.0, 0.6));
+        assert!((a.powi(2).error() - 6.0).abs() < 1e-12);
+        assert!((Correlated::new(4.0, 0.4).sqrt().error() - 0.1).abs() < 1e-12);
+        assert!(((-&a).correlation(&a) + 1.0).abs() < 1e-12);
+        let u: Uncertain<f64> = (&a - &b).into();
+        assert!((u.error() - 0.3f64.hypot(0.08)).abs() < 1e-12);
+        assert_eq!(a.to_string(), "10.00 ± 0.30");
+    }
+
+    #[test]
+    fn test_covariance() {
+        use super::*;
+        let cov = vec![vec![4.0, 2.0], vec![2.0, 9.0]];
+        let x = Correlated::from_covariance(&[1.0, 2.0], &cov).unwrap();
+        assert!((x[0].variance() - 4.0).abs() < 1e-12);
+        assert!((x[1].variance() - 9.0).abs() < 1e-12);
+        assert!((x[0].covariance(&x[1]) - 2.0).abs() < 1e-12);
+        // var(x + y) = 4 + 9 + 2 * 2
+        assert!(((&x[0] + &x[1]).variance() - 17.0).abs() < 1e-12);
+        assert!(
+            Correlated::from_covariance(&[1.0, 2.0], &[vec![1.0, 2.0], vec![2.0, 1.0]]).is_none()
+        );
+        assert!(Correlated::from_covariance(&[1.0], &cov).is_none());
+    }
+}
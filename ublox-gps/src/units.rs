@@ -0,0 +1,192 @@
+use std::ops::{Add, Neg, Sub};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{iono::SPEED_OF_LIGHT, uncertain::Uncertain};
+
+/// Scale an uncertain value by an exact factor
+fn scale(x: Uncertain<f64>, k: f64) -> Uncertain<f64> {
+    Uncertain::new(x.value() * k, x.error() * k.abs())
+}
+
+macro_rules! quantity {
+    ($name:ident, $unit:literal) => {
+        #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+        #[doc = concat!("A quantity in ", $unit, ", with its uncertainty")]
+        pub struct $name(pub Uncertain<f64>);
+
+        impl $name {
+            #[doc = concat!("Create a new quantity in ", $unit)]
+            pub fn new(value: f64, uncertainty: f64) -> Self {
+                Self(Uncertain::new(value, uncertainty))
+            }
+
+            #[doc = concat!("Get the value in ", $unit)]
+            pub fn value(&self) -> f64 {
+                self.0.value()
+            }
+
+            #[doc = concat!("Get the uncertainty in ", $unit)]
+            pub fn error(&self) -> f64 {
+                self.0.error()
+            }
+        }
+
+        impl From<$name> for Uncertain<f64> {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl Add for $name {
+            type Output = $name;
+
+            fn add(self, other: $name) -> $name {
+                $name(self.0 + other.0)
+            }
+        }
+
+        impl Sub for $name {
+            type Output = $name;
+
+            fn sub(self, other: $name) -> $name {
+                $name(self.0 - other.0)
+            }
+        }
+
+        impl Neg for $name {
+            type Output = $name;
+
+            fn neg(self) -> $name {
+                $name(-self.0)
+            }
+        }
+    };
+}
+
+quantity!(Cycles, "carrier cycles");
+quantity!(Meters, "metres");
+quantity!(Tecu, "TEC units (10¹⁶ electrons/m²)");
+
+impl Cycles {
+    /// Convert carrier cycles to metres on a carrier of frequency `freq` (Hz)
+    pub fn to_meters(self, freq: f64) -> Meters {
+        Meters(scale(self.0, SPEED_OF_LIGHT / freq))
+    }
+}
+
+impl Meters {
+    /// Convert metres to carrier cycles on a carrier of frequency `freq` (Hz)
+    pub fn to_cycles(self, freq: f64) -> Cycles {
+        Cycles(scale(self.0, freq / SPEED_OF_LIGHT))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+/// Geometry-free (ionospheric) combination of dual-frequency observations.
+///
+/// Follows the usual convention where both combinations grow with the
+/// ionospheric delay: `L1 - L2` for carrier phases and `P2 - P1` for
+/// pseudoranges, with `f1 > f2` and phases having the same sign as
+/// pseudoranges (as in RINEX and UBX-RXM-RAWX). The phase combination
+/// contains an arbitrary constant per continuous arc, due to the carrier
+/// phase ambiguities.
+pub struct GeometryFree {
+    f1: f64,
+    f2: f64,
+    factor: f64,
+}
+
+impl GeometryFree {
+    /// Create the combination for a pair of carrier frequencies (Hz).
+    ///
+    /// # Errors
+    /// - If `f1` is not higher than `f2` by at least 1 MHz
+    pub fn new(f1: f64, f2: f64) -> Result<Self, &'static str> {
+        if !(f1.is_finite() && f2.is_finite() && f2 > 0.0) {
+            return Err("Invalid carrier frequency");
+        }
+        if f1 - f2 < crate::tec::MIN_FREQ_SEPARATION {
+            return Err("First carrier frequency must be higher than the second");
+        }
+        let (a, b) = (f1 * f1, f2 * f2);
+        Ok(Self {
+            f1,
+            f2,
+            factor: a * b / (40.308e16 * (a - b)),
+        })
+    }
+
+    /// Get the carrier frequencies (Hz)
+    pub fn frequencies(&self) -> (f64, f64) {
+        (self.f1, self.f2)
+    }
+
+    /// Get the geometry-free phase combination `L1 - L2`
+    pub fn phase(&self, l1: Cycles, l2: Cycles) -> Meters {
+        l1.to_meters(self.f1) - l2.to_meters(self.f2)
+    }
+
+    /// Get the geometry-free code combination `P2 - P1`
+    pub fn code(&self, p1: Meters, p2: Meters) -> Meters {
+        p2 - p1
+    }
+
+    /// Convert a geometry-free combination to slant TEC
+    pub fn to_tecu(&self, gf: Meters) -> Tecu {
+        Tecu(scale(gf.0, self.factor))
+    }
+
+    /// Get the (ambiguous) slant TEC from carrier phases
+    pub fn phase_tec(&self, l1: Cycles, l2: Cycles) -> Tecu {
+        self.to_tecu(self.phase(l1, l2))
+    }
+
+    /// Get the slant TEC from pseudoranges
+    pub fn code_tec(&self, p1: Meters, p2: Meters) -> Tecu {
+        self.to_tecu(self.code(p1, p2))
+    }
+}
+
+mod test {
+    #[test]
+    fn test_units() {
+        use super::*;
+        use crate::{ubx::Frequency, GnssFreq, GpsFreq};
+        let (f1, f5) = (GpsFreq::L1CA.get_freq(), GpsFreq::L5.get_freq());
+
+        // GPS L1 wavelength of 19.03 cm
+        let m = Cycles::new(1000.0, 10.0).to_meters(f1);
+        assert!((m.value() - 190.29367279836487).abs() < 1e-9);
+        assert!((m.error() - 1.9029367279836487).abs() < 1e-12);
+        let cycles = m.to_cycles(f1);
+        assert!((cycles.value() - 1000.0).abs() < 1e-9);
+        assert!((cycles.error() - 10.0).abs() < 1e-12);
+        assert!(((-m).value() + m.value()).abs() < 1e-12);
+
+        // 7.76 TECU per metre of differential delay on L1/L5
+        let gf = GeometryFree::new(f1, f5).unwrap();
+        assert_eq!(gf.frequencies(), (f1, f5));
+        let tec = gf.code_tec(Meters::new(20e6, 0.0), Meters::new(20e6 + 2.0, 0.1));
+        assert!((tec.value() - 2.0 * 7.762118219329407).abs() < 1e-6);
+        assert!((tec.error() - 0.1 * 7.762118219329407).abs() < 1e-9);
+        // Phase advanced by 1 m more on L5 than on L1
+        let l1 = Cycles::new(0.0, 0.0);
+        let l5 = Meters::new(-1.0, 0.0).to_cycles(f5);
+        assert!((gf.phase_tec(l1, l5).value() - 7.762118219329407).abs() < 1e-6);
+
+        // Pairs less than 1 MHz apart are rejected
+        assert!(GeometryFree::new(f1, f1 - 0.5e6).is_err());
+        assert!(GeometryFree::new(f1, f1 - 2e6).is_ok());
+        // Unknown signals have no carrier frequency
+        let unknown = GnssFreq::Unknown {
+            gnss_id: 0,
+            sig_id: 9,
+        };
+        assert_eq!(
+            GeometryFree::new(f1, unknown.get_freq()),
+            Err("Invalid carrier frequency")
+        );
+        assert!(GeometryFree::new(unknown.get_freq(), f5).is_err());
+    }
+}
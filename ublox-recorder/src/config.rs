@@ -1,8 +1,9 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
 use argh::FromArgs;
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
+use ublox_gps_tec::RotiConfig;
 
 #[derive(FromArgs, Serialize, Deserialize, Debug)]
 /// Configuration for the recorder
@@ -19,6 +20,14 @@ pub struct RecorderCfg {
     /// save data to this directory
     #[argh(option, default = "PathBuf::from(\".\")")]
     pub save_dir: PathBuf,
+    /// length of the ROTI window in seconds
+    #[argh(option, default = "300")]
+    #[serde(default = "default_roti_window")]
+    pub roti_window: u64,
+    /// minimum number of ROT samples in a window to compute ROTI
+    #[argh(option, default = "10")]
+    #[serde(default = "default_roti_min_samples")]
+    pub roti_min_samples: usize,
 }
 
 impl RecorderCfg {
@@ -29,20 +38,36 @@ impl RecorderCfg {
         path.push("config.json");
         std::fs::write(
             path,
-            serde_json::to_string(self)
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?,
+            serde_json::to_string(self).map_err(std::io::Error::other)?,
         )
     }
 
+    /// Get the ROTI estimator configuration
+    pub fn roti_config(&self) -> RotiConfig {
+        RotiConfig {
+            window: Duration::from_secs(self.roti_window),
+            min_samples: self.roti_min_samples,
+            ..Default::default()
+        }
+    }
+
     /// Load the configuration from the default location
     pub fn load_default() -> Result<Self, std::io::Error> {
         let mut path = get_default_path();
         path.push("config.json");
         let data = std::fs::read(path)?;
-        serde_json::from_slice(&data).map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
+        serde_json::from_slice(&data).map_err(std::io::Error::other)
     }
 }
 
+fn default_roti_window() -> u64 {
+    300
+}
+
+fn default_roti_min_samples() -> usize {
+    10
+}
+
 fn get_default_path() -> PathBuf {
     if let Some(path) = ProjectDirs::from("", "", "ublox_gps_recorder") {
         path.config_dir().to_path_buf()
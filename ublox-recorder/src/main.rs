@@ -4,20 +4,21 @@ mod config;
 mod store;
 use chrono::Utc;
 use crossterm::terminal;
-use std::{io::ErrorKind, path::Path, time::Duration};
-use ublox_gps_tec::{GnssFreq, GnssSatellite};
+use std::{io::ErrorKind, time::Duration};
+use ublox_gps_tec::{GnssFreq, GnssSatellite, RotiEstimator};
 
 pub use config::RecorderCfg;
 use store::{StoreCfg, StoreKind};
 
 fn main() {
-    // Try to load the config file and open the serial port from the config file
-    let save_dir = Path::new("./");
-    let mut ser = serialport::new("/dev/ttyACM3", 115200)
+    // Read the configuration and open the serial port
+    let cfg: RecorderCfg = argh::from_env();
+    let save_dir = cfg.save_dir.as_path();
+    let mut ser = serialport::new(&cfg.serial_port, cfg.baud_rate)
         .open()
         .expect("Failed to open serial port");
     // Set the timeout on the serial port
-    ser.set_timeout(Duration::from_millis(100))
+    ser.set_timeout(Duration::from_millis(cfg.timeout))
         .expect("Failed to set timeout");
     // Create the raw data directory
     let raw_dir = save_dir.join("raw");
@@ -27,6 +28,11 @@ fn main() {
     let tec_dir = save_dir.join("ubx");
     let mut tec_writer =
         StoreCfg::new(tec_dir, StoreKind::Json, true).expect("Failed to create TEC data directory");
+    // Create the ROTI data directory
+    let roti_dir = save_dir.join("roti");
+    let mut roti_writer = StoreCfg::new(roti_dir, StoreKind::Json, true)
+        .expect("Failed to create ROTI data directory");
+    let mut roti = RotiEstimator::new(cfg.roti_config());
     // Main loop
     loop {
         let systime = Utc::now();
@@ -55,6 +61,16 @@ fn main() {
                     )
                     .expect("Failed to store TEC data");
                 if let Some(tec) = ublox_gps_tec::TecInfo::assimilate(&info) {
+                    if let Some(rinfo) = roti.push(&tec) {
+                        roti_writer
+                            .store(
+                                rinfo.timestamp(),
+                                json5::to_string(&rinfo)
+                                    .expect("Could not convert ROTI info to JSON string")
+                                    .as_bytes(),
+                            )
+                            .expect("Failed to store ROTI data");
+                    }
                     let width = terminal::size().expect("Failed to get terminal size").0;
                     // header
                     println!(
@@ -62,9 +78,9 @@ fn main() {
                         format!(
                             "{} ({:.3}, {:.3}, {:.3}) [{}]",
                             tec.timestamp().format("%Y-%m-%d %H:%M:%S%Z"),
-                            tec.location().0,
-                            tec.location().1,
-                            tec.location().2 * 1e-3,
+                            tec.location().lat,
+                            tec.location().lon,
+                            tec.location().height * 1e-3,
                             tec.tec().len()
                         ),
                         width = width as usize
@@ -79,6 +95,7 @@ fn main() {
                             GnssSatellite::Glonass(prn) => format!("GLO-{:02}", prn),
                             GnssSatellite::Qzss(prn) => format!("QZS-{:02}", prn),
                             GnssSatellite::Sbas(prn) => format!("SBA-{:02}", prn),
+                            GnssSatellite::Navic(prn) => format!("NAV-{:02}", prn),
                         };
                         print!(
                             "\t{}: {:>3} AZ {:>2} EL | ",
@@ -104,6 +121,10 @@ fn main() {
                                 Beidou(freq) => print!("{:?}: ", freq),
                                 Glonass(freq) => print!("{:?}: ", freq),
                                 Qzss(freq) => print!("{:?}: ", freq),
+                                Navic(freq) => print!("{:?}: ", freq),
+                                Unknown { gnss_id, sig_id } => {
+                                    print!("Unknown({}, {}): ", gnss_id, sig_id)
+                                }
                             }
                             if let Some(prn) = m.pseudo_range {
                                 print!("PRN {:.3}km, ", prn.0 * 1e-3);
@@ -176,10 +176,7 @@ impl StoreCfg {
             writer.write_all(self.kind.delimiter())?;
             writer.flush()
         } else {
-            Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "No file writer",
-            ))
+            Err(std::io::Error::other("No file writer"))
         }
     }
 }